@@ -0,0 +1,137 @@
+//! Sampled access logging.
+//!
+//! Replaces `actix_web::middleware::Logger` so busy deployments can log only
+//! a fraction of requests via `LOG_SAMPLE_RATE`. Sampling is deterministic:
+//! with a rate of 0.25 every fourth successful request is logged. Responses
+//! with a 4xx or 5xx status are always logged, whatever the rate.
+
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::USER_AGENT;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Decides which requests get an access log line and writes it.
+#[derive(Debug)]
+pub struct AccessLog {
+    sample_rate: f64,
+    seen: AtomicU64,
+}
+
+impl AccessLog {
+    /// Creates an access log keeping `sample_rate` (clamped to 0.0–1.0) of
+    /// successful requests.
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+            seen: AtomicU64::new(0),
+        }
+    }
+
+    /// Logs `res` at info level if it is an error or falls in the sample.
+    pub fn log_response<B>(&self, res: &ServiceResponse<B>, elapsed: Duration) {
+        let status = res.status();
+        if !status.is_client_error() && !status.is_server_error() && !self.sampled() {
+            return;
+        }
+
+        let req = res.request();
+        let peer = req
+            .peer_addr()
+            .map_or_else(|| "-".to_string(), |addr| addr.ip().to_string());
+        let user_agent = req
+            .headers()
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("-");
+
+        tracing::info!(
+            "{} \"{} {} {:?}\" {} \"{}\" {:.6}",
+            peer,
+            req.method(),
+            req.path(),
+            req.version(),
+            status.as_u16(),
+            user_agent,
+            elapsed.as_secs_f64()
+        );
+    }
+
+    /// Whether the next successful request is logged.
+    ///
+    /// Logs request `n` when `n * rate` crosses an integer, which spreads
+    /// the logged requests evenly instead of leaving it to chance.
+    fn sampled(&self) -> bool {
+        if self.sample_rate >= 1.0 {
+            return true;
+        }
+        if self.sample_rate <= 0.0 {
+            return false;
+        }
+        let n = self.seen.fetch_add(1, Ordering::Relaxed) as f64;
+        ((n + 1.0) * self.sample_rate).floor() > (n * self.sample_rate).floor()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, HttpResponse};
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    /// Collects formatted tracing output.
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Returns the lines `log` writes for a request to `path` answered with `response`.
+    fn log_lines(log: &AccessLog, path: &str, response: HttpResponse) -> Vec<String> {
+        let res = test::TestRequest::get().uri(path).to_srv_request().into_response(response);
+
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, || log.log_response(&res, Duration::from_millis(5)));
+
+        let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        output.lines().map(str::to_string).collect()
+    }
+
+    #[actix_web::test]
+    async fn test_zero_rate_logs_errors_only() {
+        let log = AccessLog::new(0.0);
+
+        for _ in 0..10 {
+            assert!(log_lines(&log, "/api/health", HttpResponse::Ok().finish()).is_empty());
+        }
+
+        let lines = log_lines(&log, "/api/upload", HttpResponse::BadRequest().finish());
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("\"GET /api/upload HTTP/1.1\" 400"), "{}", lines[0]);
+        assert_eq!(log_lines(&log, "/api/upload", HttpResponse::InternalServerError().finish()).len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_rate_logs_matching_fraction() {
+        let log = AccessLog::new(0.25);
+        let logged = (0..100)
+            .filter(|_| !log_lines(&log, "/api/health", HttpResponse::Ok().finish()).is_empty())
+            .count();
+        assert_eq!(logged, 25);
+
+        let log = AccessLog::new(1.0);
+        assert_eq!(log_lines(&log, "/api/health", HttpResponse::Ok().finish()).len(), 1);
+    }
+}
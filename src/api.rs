@@ -2,87 +2,437 @@
 //! 
 //! This module contains all the REST API endpoints, organized by functionality.
 
-use actix_web::{get, post, web, HttpResponse};
-use crate::error::{FaceDetectionError, Result, IoSnafu, ImageProcessingSnafu};
-use crate::types::{ApiResponse, CropRequest, CropResponse, DetectionResponse, HealthResponse};
+use actix_cors::Cors;
+use actix_web::http::header::{self, ContentDisposition, HeaderName, HeaderValue};
+use actix_web::http::Method;
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use crate::backend::AsyncDetector;
+use crate::cache::DecodeCache;
+use crate::config::AppConfig;
+use crate::dedup::RecentUploads;
+use crate::detection::DimensionLimits;
 use crate::detector::FaceDetector;
+use crate::embedding::Embedder;
+use crate::error::{decode_error, validation_error, FaceDetectionError, IoSnafu, Result};
+use crate::last_image::LastAnnotatedImage;
+use crate::limiter::DetectionLimiter;
+use crate::matcher::{CosineMatcher, FaceMatcher};
+use crate::selftest::SelfTestReport;
+use crate::stats::RuntimeStats;
+use crate::storage::Storage;
+use crate::stream::{stream_array_at, STREAM_FACE_THRESHOLD};
+use crate::types::{
+    to_snake_case_keys, AnnotateRequest, AnnotateResponse, ApiResponse, BatchRequest, CropFormat,
+    CropManifestEntry, CropQuery, CropRequest, CropResponse, CroppedFace, DebugConfigResponse,
+    DetectAndCropRequest, DetectAndCropResponse, DetectionFormat, DetectionResponse,
+    DetectionResult, DetectorParams, EmbedRequest, EmbedResponse, EnvelopeVersion, Face, FaceOrder,
+    FaceRef, FormatsResponse, GroupRequest, GroupResponse, HasFaceResponse, HealthResponse,
+    KeyCase, OutputFormat, OverlayRequest, PhaseTimings, PrimaryFaceRequest, PrimaryFaceResponse,
+    ReadinessResponse, ResizeOptions, ResponseFormatQuery, ScanRequest, SmartCropRequest,
+    SmartCropResponse, UploadQuery, ValidateRequest, ValidateResponse, DEFAULT_CLUSTER_DISTANCE,
+};
+use image::DynamicImage;
 use snafu::ResultExt;
 use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::OwnedSemaphorePermit;
 
 /// Health check endpoint.
 /// 
 /// Returns the current health status of the service.
 #[get("/api/health")]
-pub async fn health_check() -> HttpResponse {
+pub async fn health_check(format: web::Query<ResponseFormatQuery>, version: EnvelopeVersion) -> HttpResponse {
     let response = HealthResponse::default();
-    HttpResponse::Ok().json(ApiResponse::success(response))
+    ok_json(response, &format, version)
+}
+
+/// Readiness check endpoint.
+/// 
+/// Unlike the health check, fails with 503 while the instance should not take
+/// traffic: currently when the startup pipeline self-test did not pass.
+#[get("/api/ready")]
+pub async fn readiness(
+    req: HttpRequest,
+    format: web::Query<ResponseFormatQuery>,
+    version: EnvelopeVersion,
+) -> Result<HttpResponse> {
+    let self_test = req
+        .app_data::<web::Data<SelfTestReport>>()
+        .map(|report| report.as_ref().clone());
+    
+    if let Some(SelfTestReport { passed: false, error, .. }) = &self_test {
+        return Err(FaceDetectionError::NotReady {
+            reason: format!("pipeline self-test failed: {}", error.as_deref().unwrap_or("unknown error")),
+        });
+    }
+    
+    Ok(ok_json(ReadinessResponse { ready: true, self_test }, &format, version))
+}
+
+/// Debug configuration endpoint.
+/// 
+/// Returns the redacted application configuration, detector parameters and
+/// runtime counters. Only registered when debug endpoints are enabled.
+#[get("/api/debug/config")]
+pub async fn debug_config(
+    config: web::Data<AppConfig>,
+    detector: web::Data<FaceDetector>,
+    backend: web::Data<dyn AsyncDetector>,
+    stats: web::Data<RuntimeStats>,
+    format: web::Query<ResponseFormatQuery>,
+    version: EnvelopeVersion,
+) -> HttpResponse {
+    let response = DebugConfigResponse {
+        config: config.redacted(),
+        detector: DetectorParams {
+            min_dimension: detector.min_dimension(),
+            confidence_threshold: detector.confidence_threshold(),
+            model_version: backend.model_version().to_string(),
+        },
+        stats: stats.snapshot(),
+    };
+    ok_json(response, &format, version)
+}
+
+/// Last annotated image endpoint.
+/// 
+/// Returns the image most recently drawn on by `/api/upload` or
+/// `/api/annotate` as a PNG, so front-end work can check an annotation
+/// without uploading again. Responds with 404 until an image has been
+/// annotated; only the latest image is kept, in memory.
+#[get("/api/debug/last")]
+pub async fn debug_last_image(last: web::Data<LastAnnotatedImage>) -> Result<HttpResponse> {
+    let Some(image) = last.get() else {
+        return Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error("No image has been annotated yet")));
+    };
+    
+    let png = crate::detection::encode_png(&image)?;
+    Ok(HttpResponse::Ok().content_type("image/png").body(png))
+}
+
+/// Registers the `/api/debug` routes when `ENABLE_DEBUG_ENDPOINTS` is set.
+pub fn configure_debug_routes(cfg: &mut web::ServiceConfig, config: &AppConfig) {
+    if config.enable_debug_endpoints {
+        cfg.service(debug_config);
+        cfg.service(debug_last_image);
+    }
+}
+
+/// Directory scan endpoint.
+/// 
+/// Detects faces in every image directly inside `dir`, a directory below the
+/// configured scan root, and streams one JSON object per image as JSON Lines.
+/// Paths resolving outside the scan root are rejected with 403. The whole
+/// scan holds one detection slot. Only registered when `ENABLE_LOCAL_SCAN`
+/// is set.
+#[post("/api/scan")]
+pub async fn scan_directory(
+    req: HttpRequest,
+    request: web::Json<ScanRequest>,
+    config: web::Data<AppConfig>,
+    detector: web::Data<FaceDetector>,
+) -> Result<HttpResponse> {
+    let dir = crate::scan::resolve_scan_dir(Path::new(&config.scan_root), &request.dir)?;
+    let files = crate::scan::list_image_files(&dir)?;
+    tracing::info!("Scanning {} images in {:?}", files.len(), dir);
+    
+    let slot = acquire_detection_slot(&req).await?;
+    let detector = detector.into_inner();
+    let timeout = std::time::Duration::from_secs(config.detection_timeout_secs);
+    let limits = config.dimension_limits();
+    let lines = crate::scan::scan_lines(files, timeout, move |path| {
+        let bytes = std::fs::read(path).context(IoSnafu)?;
+        check_image_header(&bytes, &limits)?;
+        let img = crate::detection::decode_image(&bytes)?;
+        detector.detect_image(&img)
+    });
+    
+    // Keep the slot until the last line has been sent
+    let lines = futures_util::StreamExt::map(lines, move |line| {
+        let _held = &slot;
+        line
+    });
+    
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(lines))
+}
+
+/// Registers `/api/scan` when `ENABLE_LOCAL_SCAN` is set.
+pub fn configure_scan_routes(cfg: &mut web::ServiceConfig, config: &AppConfig) {
+    if config.enable_local_scan {
+        cfg.service(scan_directory);
+    }
+}
+
+/// Supported formats endpoint.
+/// 
+/// Lists the input formats the upload sniffer accepts and the output formats
+/// returned images can be encoded in.
+#[get("/api/formats")]
+pub async fn supported_formats(format: web::Query<ResponseFormatQuery>, version: EnvelopeVersion) -> HttpResponse {
+    use crate::detection::{format_name, SUPPORTED_INPUT_FORMATS, SUPPORTED_OUTPUT_FORMATS};
+    
+    let response = FormatsResponse {
+        input_formats: SUPPORTED_INPUT_FORMATS.iter().copied().map(format_name).collect(),
+        output_formats: SUPPORTED_OUTPUT_FORMATS.iter().copied().map(format_name).collect(),
+    };
+    ok_json(response, &format, version)
 }
 
 /// Image upload and face detection endpoint.
 /// 
 /// Accepts an image file via multipart form data and returns detection results.
+/// With `?require_faces=true`, an image without faces is rejected with 422.
+/// With `?square=true`, the annotated image is padded to a square using
+/// `?pad_color=rrggbb` (black by default); face coordinates stay relative to
+/// the unpadded image and the applied padding is reported as `padOffset`.
+/// With `?order=reading`, faces are returned top-to-bottom, left-to-right.
+/// With `?frame=N`, detection runs on page/frame N of a multi-page TIFF or
+/// animated GIF. `?quality=1..100` sets the JPEG quality of returned images,
+/// `?progressive=true` makes them progressive and `?subsampling=444|420`
+/// selects their chroma subsampling.
+/// With `?timings=true`, per-phase timings are added to the detection result.
+/// With `?debug=true`, candidates dropped by the detector's filters are listed
+/// with the reason in `rejected`; this needs `ENABLE_DEBUG_ENDPOINTS`.
+/// With `?embed_metadata=true`, the detection result is also stored as JSON
+/// in a comment segment of the processed JPEG.
+/// The face count, image dimensions and processing time are also sent as
+/// `X-Face-Count`, `X-Image-Width`, `X-Image-Height` and
+/// `X-Processing-Time-Ms` headers, for clients that branch before parsing
+/// the body.
+/// 
+/// A declared `Content-Length` above the configured maximum is rejected before
+/// the body is read, and an upload that stalls for longer than the configured
+/// idle period is aborted. When every detection slot stays busy for the
+/// configured queue timeout, the request is shed with 503 and `Retry-After`.
 #[post("/api/upload")]
 pub async fn upload_image(
+    req: HttpRequest,
     mut payload: actix_multipart::Multipart,
+    query: web::Query<UploadQuery>,
+    format: web::Query<ResponseFormatQuery>,
+    version: EnvelopeVersion,
+    config: web::Data<AppConfig>,
     detector: web::Data<FaceDetector>,
 ) -> Result<HttpResponse> {
     use futures_util::TryStreamExt;
-    use std::io::Write;
+    use tracing::Instrument;
     use uuid::Uuid;
     
     tracing::info!("Received upload request");
     
+    require_content_type(&req, "multipart/form-data")?;
+    check_declared_content_length(&req, config.max_file_size)?;
+    let backend = req
+        .app_data::<web::Data<dyn AsyncDetector>>()
+        .cloned()
+        .ok_or_else(|| crate::error::config_error("No detection backend is configured"))?;
+    if query.debug && !config.enable_debug_endpoints {
+        return Err(validation_error("debug output requires ENABLE_DEBUG_ENDPOINTS"));
+    }
+    let quality = match query.quality {
+        None => crate::detection::DEFAULT_JPEG_QUALITY,
+        Some(q @ 1..=100) => q as u8,
+        Some(q) => return Err(validation_error(format!("Quality {} must be between 1 and 100", q))),
+    };
+    let output_format = OutputFormat::Jpeg {
+        quality,
+        progressive: query.progressive,
+        subsampling: query.subsampling,
+    };
+    let resize_options = ResizeOptions {
+        pad_color: parse_pad_color(query.pad_color.as_deref())?,
+        ..ResizeOptions::default()
+    };
+    let idle_timeout = std::time::Duration::from_secs(config.upload_idle_timeout_secs);
+    
     // Process multipart form data
-    while let Some(mut field) = payload.try_next().await
+    while let Some(mut field) = with_idle_timeout(idle_timeout, payload.try_next()).await?
         .map_err(|_| FaceDetectionError::MultipartError)? {
         let content_disposition = field.content_disposition();
         
         if let Some(name) = content_disposition.get_name() {
             if name == "image" {
-                // Generate unique filename
-                let filename = format!("{}.jpg", Uuid::new_v4());
-                let filepath = format!("uploads/{}", filename);
-                
-                // Create file
-                let mut file = std::fs::File::create(&filepath)
-                    .context(IoSnafu)?;
+                // The client filename is only logged and reported; stored names are always generated
+                let client_filename = content_disposition.get_filename().map(sanitize_filename);
                 
-                // Write field data to file
-                while let Some(chunk) = field.try_next().await
+                // Buffer field data in memory, capped on the bytes actually received
+                // since chunked uploads declare no Content-Length
+                let mut bytes = Vec::new();
+                while let Some(chunk) = with_idle_timeout(idle_timeout, field.try_next()).await?
                     .map_err(|_| FaceDetectionError::MultipartError)? {
-                    file.write_all(&chunk)
-                        .context(IoSnafu)?;
+                    let size = bytes.len() + chunk.len();
+                    if size > config.max_file_size {
+                        tracing::warn!("Rejecting upload exceeding {} bytes", config.max_file_size);
+                        return Err(FaceDetectionError::FileTooLarge { size, max_size: config.max_file_size });
+                    }
+                    bytes.extend_from_slice(&chunk);
                 }
                 
-                tracing::info!("File saved: {}", filepath);
+                // Reject empty uploads before trying to decode them
+                if bytes.is_empty() {
+                    return Err(validation_error("Uploaded image file is empty"));
+                }
+                
+                let upload_id = Uuid::new_v4().to_string();
+                tracing::info!(
+                    "Upload {} received ({} bytes, client filename {:?})",
+                    upload_id,
+                    bytes.len(),
+                    client_filename.as_deref().unwrap_or("<none>")
+                );
+                
+                // Reject unsupported and oversized images from the header before decoding
+                check_image_header(&bytes, &config.dimension_limits())?;
+                
+                // Hold a detection slot until the response is built
+                let _slot = acquire_detection_slot(&req).await?;
                 
-                // Validate file is an image
-                validate_image_file(&filepath)?;
+                let pipeline_span = tracing::info_span!("upload_pipeline", upload = %upload_id);
+                let pipeline_start = std::time::Instant::now();
+                
+                // Load original image, or the requested frame of a multi-frame one,
+                // and hash the bytes exactly as uploaded
+                let (decoded, decode_ms) = timed(tracing::info_span!(parent: &pipeline_span, "decode"), || {
+                    let image_hash = crate::detection::image_content_hash(&bytes);
+                    let image = if query.frame == 0 {
+                        decode_cached(&req, &bytes)?
+                    } else {
+                        Arc::new(crate::detection::decode_frame(&bytes, query.frame, &config.dimension_limits())?)
+                    };
+                    Ok::<_, FaceDetectionError>((image, image_hash))
+                });
+                let (original_image, image_hash) = decoded?;
                 
                 // Perform face detection
-                let detection_result = detector.detect_faces(Path::new(&filepath))?;
+                let detect_start = std::time::Instant::now();
+                let mut detection_result = detector.detect_with(backend.get_ref(), &original_image, query.debug)
+                    .instrument(tracing::info_span!(parent: &pipeline_span, "detect"))
+                    .await?;
+                let detect_ms = detect_start.elapsed().as_secs_f64() * 1000.0;
+                if query.offset > 0 || query.limit.is_some() {
+                    detection_result.paginate(query.offset, query.limit);
+                }
+                if query.order == FaceOrder::Reading {
+                    // Faces overlapping by half the smallest face height share a row
+                    let tolerance = detection_result.faces.iter().map(|f| (f.bbox.height / 2.0) as u32).min().unwrap_or(0);
+                    detection_result.faces = detection_result
+                        .in_reading_order(tolerance)
+                        .into_iter()
+                        .cloned()
+                        .collect();
+                }
+                if query.cluster {
+                    detection_result.clusters = Some(detection_result.cluster(DEFAULT_CLUSTER_DISTANCE));
+                }
+                if let Some(stats) = req.app_data::<web::Data<RuntimeStats>>() {
+                    stats.record_faces(detection_result.total_faces);
+                }
+                
+                if query.require_faces && detection_result.total_faces == 0 {
+                    return Err(FaceDetectionError::NoFacesDetected);
+                }
                 
-                // Load original image
-                let original_image = image::open(&filepath)
-                    .context(ImageProcessingSnafu)?;
+                // Draw bounding boxes, then pad to a square so boxes stay aligned.
+                // With nothing to draw or pad, the original is returned as is.
+                let (drawn, draw_ms) = timed(tracing::info_span!(parent: &pipeline_span, "draw"), || {
+                    if !detection_result.has_faces() && !query.square {
+                        return Ok((None, None));
+                    }
+                    let processed_image = detector.draw_bounding_boxes(&original_image, &detection_result.faces)?;
+                    if query.square {
+                        let (padded, offset) = crate::detection::pad_to_square(&processed_image, &resize_options);
+                        Ok((Some(padded), Some(offset)))
+                    } else {
+                        Ok((Some(processed_image), None))
+                    }
+                });
+                let (processed_image, pad_offset) = drawn?;
                 
-                // Draw bounding boxes on processed image
-                let processed_image = detector.draw_bounding_boxes(&original_image, &detection_result.faces)?;
+                // Cap the drawn image; face coordinates stay at original resolution.
+                // An untouched original is passed through as is, cap or not.
+                let max_dimension = config.max_response_image_dimension;
+                let processed_image = processed_image
+                    .map(|image| crate::detection::downscale_to_fit(&image, max_dimension).unwrap_or(image));
                 
                 // Convert images to base64
-                let original_base64 = crate::detection::image_to_base64(&original_image)?;
-                let processed_base64 = crate::detection::image_to_base64(&processed_image)?;
+                let (encoded, encode_ms) = timed(tracing::info_span!(parent: &pipeline_span, "encode"), || {
+                    let encode = |image: &DynamicImage| crate::detection::encode_image(image, &output_format);
+                    let original_base64 = if query.preserve_original {
+                        crate::detection::bytes_to_data_uri(&bytes)?
+                    } else {
+                        crate::detection::jpeg_bytes_to_base64(&encode(&original_image)?)
+                    };
+                    let processed_base64 = if query.embed_metadata {
+                        let processed = processed_image.as_ref().unwrap_or(&*original_image);
+                        let jpeg = encode(processed)?;
+                        let jpeg = crate::detection::embed_result_metadata(
+                            &jpeg,
+                            &detection_result,
+                            image::ImageFormat::Jpeg,
+                        )?;
+                        crate::detection::jpeg_bytes_to_base64(&jpeg)
+                    } else if let Some(processed) = &processed_image {
+                        crate::detection::jpeg_bytes_to_base64(&encode(processed)?)
+                    } else {
+                        // Byte-identical to the original, so clients can tell nothing changed
+                        original_base64.clone()
+                    };
+                    Ok::<_, FaceDetectionError>((original_base64, processed_base64))
+                });
+                let (original_base64, processed_base64) = encoded?;
+                
+                if let Some(last) = req.app_data::<web::Data<LastAnnotatedImage>>() {
+                    last.store(processed_image.map_or_else(|| original_image.clone(), Arc::new));
+                }
+                
+                if query.timings {
+                    detection_result.timings = Some(PhaseTimings {
+                        decode_ms,
+                        detect_ms,
+                        draw_ms,
+                        encode_ms,
+                        total_ms: pipeline_start.elapsed().as_secs_f64() * 1000.0,
+                    });
+                }
                 
-                // Clean up uploaded file
-                if let Err(e) = std::fs::remove_file(&filepath) {
-                    tracing::warn!("Failed to remove temporary file {}: {}", filepath, e);
+                // Report near duplicates of recent uploads
+                let hash = crate::detection::dhash(&original_image);
+                let duplicate_of = req
+                    .app_data::<web::Data<RecentUploads>>()
+                    .and_then(|recent| recent.check_and_insert(hash, &upload_id));
+                if let Some(previous) = &duplicate_of {
+                    tracing::info!("Upload {} is a near duplicate of {}", upload_id, previous);
                 }
                 
+                // Keep the original for review
+                let retained_path = if config.retain_uploads {
+                    let storage = req
+                        .app_data::<web::Data<dyn Storage>>()
+                        .ok_or_else(|| crate::error::storage_error("No storage backend is configured"))?;
+                    match crate::retention::retain_upload(storage.get_ref(), &bytes, &upload_id).await {
+                        Ok(key) => Some(key),
+                        Err(e) => {
+                            tracing::error!("Failed to retain upload {}: {}", upload_id, e);
+                            return Err(e);
+                        }
+                    }
+                } else {
+                    None
+                };
+                
                 // Create response
                 let response_data = DetectionResponse {
                     original_image: original_base64,
                     processed_image: processed_base64,
                     detection_result,
+                    pad_offset,
+                    upload_id,
+                    image_hash,
+                    phash: format!("{:016x}", hash),
+                    duplicate_of,
+                    retained_path,
                 };
                 
                 tracing::info!(
@@ -91,7 +441,19 @@ pub async fn upload_image(
                     response_data.detection_result.processing_time_ms
                 );
                 
-                return Ok(HttpResponse::Ok().json(ApiResponse::success(response_data)));
+                let summary = DetectionSummaryHeaders::from(&response_data.detection_result);
+                let response = if query.format == DetectionFormat::Voc {
+                    let result = &response_data.detection_result;
+                    let filename = client_filename.unwrap_or_else(|| format!("{}.jpg", response_data.upload_id));
+                    HttpResponse::Ok()
+                        .content_type("application/xml")
+                        .body(result.to_voc_xml(&filename, result.image_width, result.image_height))
+                } else if response_data.detection_result.faces.len() > STREAM_FACE_THRESHOLD {
+                    ok_json_streamed(response_data, &format, version)?
+                } else {
+                    ok_json(response_data, &format, version)
+                };
+                return Ok(summary.apply(response));
             }
         }
     }
@@ -99,95 +461,1714 @@ pub async fn upload_image(
     Err(FaceDetectionError::NoFileUploaded)
 }
 
+/// Annotation endpoint.
+/// 
+/// Draws caller-supplied faces on a caller-supplied image and returns the
+/// annotated image, downscaled to the configured response image cap. No
+/// detection is performed.
+#[post("/api/annotate")]
+pub async fn annotate_image(
+    req: HttpRequest,
+    request: web::Json<AnnotateRequest>,
+    format: web::Query<ResponseFormatQuery>,
+    version: EnvelopeVersion,
+    config: web::Data<AppConfig>,
+) -> Result<HttpResponse> {
+    tracing::info!("Received annotate request for {} faces", request.faces.len());
+    
+    validate_supplied_faces(&request.faces, config.max_crop_faces, &config.dimension_limits())?;
+    
+    let _slot = acquire_detection_slot(&req).await?;
+    let image_bytes = crate::detection::decode_base64_image(&request.image_data)?;
+    let img = image::load_from_memory(&image_bytes)
+        .map_err(decode_error)?;
+    
+    let annotated = crate::detector::draw_bounding_boxes_with(&img, &request.faces, &request.draw_options)?;
+    let annotated = crate::detection::downscale_to_fit(&annotated, config.max_response_image_dimension)
+        .unwrap_or(annotated);
+    let response = AnnotateResponse {
+        annotated_image: crate::detection::image_to_base64(&annotated)?,
+    };
+    if let Some(last) = req.app_data::<web::Data<LastAnnotatedImage>>() {
+        last.store(Arc::new(annotated));
+    }
+    
+    Ok(ok_json(response, &format, version))
+}
+
+/// Overlay endpoint.
+/// 
+/// Draws caller-supplied faces on a transparent canvas of the requested size
+/// and returns it as a PNG, for clients that composite the boxes over the
+/// original image themselves.
+#[post("/api/overlay.png")]
+pub async fn overlay_png(
+    req: HttpRequest,
+    request: web::Json<OverlayRequest>,
+    config: web::Data<AppConfig>,
+) -> Result<HttpResponse> {
+    tracing::info!(
+        "Received overlay request for {} faces on {}x{}",
+        request.faces.len(),
+        request.width,
+        request.height
+    );
+    
+    if request.width == 0 || request.height == 0 {
+        return Err(validation_error("Overlay width and height must be positive"));
+    }
+    crate::detection::check_dimensions(request.width, request.height, &config.dimension_limits())?;
+    validate_supplied_faces(&request.faces, config.max_crop_faces, &config.dimension_limits())?;
+    request.draw_options.validate()?;
+    
+    let _slot = acquire_detection_slot(&req).await?;
+    let overlay = crate::detection::draw_overlay(
+        request.width,
+        request.height,
+        &request.faces,
+        &request.draw_options,
+    );
+    let png = crate::detection::encode_png(&overlay)?;
+    
+    Ok(HttpResponse::Ok().content_type("image/png").body(png))
+}
+
+/// Largest `?size=` accepted by the crop endpoint, in pixels.
+pub const MAX_CROP_SIZE: u32 = 4096;
+
+/// IoU above which `dedupeOverlapping` treats two requested faces as one.
+pub const CROP_DEDUPE_IOU_THRESHOLD: f32 = 0.7;
+
+/// Storage key prefix of crops persisted with `?persist=true`.
+pub const CROP_KEY_PREFIX: &str = "crops";
+
 /// Face cropping endpoint.
 /// 
-/// Accepts an image and face coordinates, returns cropped face images.
+/// Accepts an image and face coordinates, returns one entry per requested face
+/// carrying either the cropped image or the reason it could not be cropped.
+/// With `?srgb=true`, images carrying an ICC profile are converted to sRGB first.
+/// Requests with more than the configured number of faces, or with face
+/// fields outside sane bounds, are rejected before the image is decoded.
+/// With `?size=N`, each crop is resized to fit an NxN square using
+/// `?filter=nearest|triangle|catmull|lanczos` (Lanczos by default) and
+/// letterboxed with `?pad_color=rrggbb` (black by default). `outputSize` in
+/// the body does the same and wins over `?size=`, e.g. for recognition
+/// models expecting fixed 160x160 inputs.
+/// Transparent pixels are composited over `?background=rrggbb` (white by
+/// default), since JPEG crops have no alpha channel.
+/// With `?format=zip`, the crops are returned as JPEGs in a zip archive
+/// download instead, with a `manifest.json` describing every face.
+/// With `dedupeOverlapping` set in the body, a face overlapping an earlier
+/// cropped face by more than [`CROP_DEDUPE_IOU_THRESHOLD`] IoU is not cropped;
+/// its entry names the face it duplicates and its index is listed in `skipped`.
+/// With `?persist=true`, each crop is also stored as a JPEG through the
+/// configured [`Storage`] under `crops/{id}/face_{index}.jpg` and its entry
+/// carries the `storageKey`.
+/// Like the other crop endpoints, crops are exempt from the response image
+/// cap, so recognition pipelines always get native-resolution face pixels.
 #[post("/api/crop")]
 pub async fn crop_faces(
     request: web::Json<CropRequest>,
+    query: web::Query<CropQuery>,
+    format: web::Query<ResponseFormatQuery>,
+    version: EnvelopeVersion,
     _detector: web::Data<FaceDetector>,
+    req: HttpRequest,
 ) -> Result<HttpResponse> {
     tracing::info!("Received crop request for {} faces", request.faces.len());
     
+    // Limits fall back to the defaults when no configuration is registered
+    let default_config;
+    let config = match req.app_data::<web::Data<AppConfig>>() {
+        Some(config) => config.get_ref(),
+        None => {
+            default_config = AppConfig::default();
+            &default_config
+        }
+    };
+    validate_crop_request(&request, config.max_crop_faces, &config.dimension_limits())?;
+    
+    let output_size = request.output_size.or(query.size);
+    if let Some(size) = output_size {
+        if !(1..=MAX_CROP_SIZE).contains(&size) {
+            return Err(validation_error(format!("Crop size {} must be between 1 and {}", size, MAX_CROP_SIZE)));
+        }
+    }
+    let resize_options = ResizeOptions {
+        filter: query.filter.into(),
+        pad_color: parse_pad_color(query.pad_color.as_deref())?,
+        ..ResizeOptions::default()
+    };
+    let background = query
+        .background
+        .as_deref()
+        .map_or(Ok(crate::detection::DEFAULT_CROP_BACKGROUND), crate::detection::parse_hex_color)?;
+    let storage = if query.persist {
+        if query.format == CropFormat::Zip {
+            return Err(validation_error("persist is not supported with format=zip"));
+        }
+        let storage = req
+            .app_data::<web::Data<dyn Storage>>()
+            .ok_or_else(|| crate::error::storage_error("No storage backend is configured"))?;
+        Some(storage.clone())
+    } else {
+        None
+    };
+    
+    // Decoding, cropping and encoding hold a detection slot like detection does
+    let _slot = acquire_detection_slot(&req).await?;
+    
     // Decode base64 image
     let image_bytes = crate::detection::decode_base64_image(&request.image_data)?;
     
-    // Load image from bytes
-    let img = image::load_from_memory(&image_bytes)
-        .context(ImageProcessingSnafu)?;
+    // Load image from bytes, reusing a decode from an earlier request when possible
+    let mut img = decode_cached(&req, &image_bytes)?;
+    if query.srgb {
+        if let Some(profile) = crate::detection::extract_icc_profile(&image_bytes) {
+            img = Arc::new(crate::detection::convert_to_srgb(&img, &profile));
+        }
+    }
     
-    let mut cropped_faces = Vec::new();
+    let duplicates = if request.dedupe_overlapping {
+        overlapping_duplicates(&request.faces, CROP_DEDUPE_IOU_THRESHOLD)
+    } else {
+        vec![None; request.faces.len()]
+    };
     
-    // Crop each face
-    for (index, face) in request.faces.iter().enumerate() {
+    // Crop each face, leaving duplicates without an outcome
+    let crops = request.faces.iter().zip(duplicates).enumerate().map(|(index, (face, duplicate_of))| {
+        if let Some(kept) = duplicate_of {
+            tracing::info!("Skipping face {} as a duplicate of face {}", index + 1, kept + 1);
+            return (index, face, duplicate_of, None);
+        }
         tracing::info!("Cropping face {} at ({}, {}) size {}x{}", 
-            index + 1, face.x, face.y, face.width, face.height);
+            index + 1, face.bbox.x, face.bbox.y, face.bbox.width, face.bbox.height);
         
-        match crate::detection::crop_face(&img, face) {
-            Ok(cropped_img) => {
-                // Convert to base64
-                let base64_string = crate::detection::image_to_base64(&cropped_img)?;
-                cropped_faces.push(base64_string);
+        let outcome = crate::detection::crop_face_with_background(&img, face, background)
+            .map(|cropped_img| match output_size {
+                Some(size) => crate::detection::fit_to(&cropped_img, size, size, &resize_options).0,
+                None => cropped_img,
+            });
+        (index, face, None, Some(outcome))
+    });
+    
+    if query.format == CropFormat::Zip {
+        return crop_archive_response(crops);
+    }
+    
+    // Crops to persist, as positions in `cropped_faces` with their JPEG bytes
+    let mut to_persist = Vec::new();
+    let mut cropped_faces: Vec<_> = crops
+        .enumerate()
+        .map(|(position, (index, face, duplicate_of, outcome))| {
+            let encoded = outcome.map(|outcome| {
+                outcome.and_then(|cropped_img| {
+                    if storage.is_some() {
+                        let jpeg = crate::detection::encode_jpeg(&cropped_img, crate::detection::DEFAULT_JPEG_QUALITY)?;
+                        to_persist.push((position, index, jpeg));
+                    }
+                    crate::detection::image_to_base64(&cropped_img)
+                })
+            });
+            let (image, error) = match encoded {
+                Some(Ok(base64_string)) => (Some(base64_string), None),
+                Some(Err(e)) => {
+                    tracing::warn!("Failed to crop face {}: {}", index + 1, e);
+                    (None, Some(e.to_string()))
+                }
+                None => (None, None),
+            };
+            CroppedFace {
+                index,
+                face: face.clone(),
+                image,
+                error,
+                duplicate_of,
+                storage_key: None,
+            }
+        })
+        .collect();
+    
+    if let Some(storage) = storage {
+        let crop_id = uuid::Uuid::new_v4();
+        for (position, index, jpeg) in to_persist {
+            let key = format!("{}/{}/face_{}.jpg", CROP_KEY_PREFIX, crop_id, index);
+            storage.put(&key, &jpeg).await?;
+            cropped_faces[position].storage_key = Some(key);
+        }
+    }
+    let skipped: Vec<usize> = cropped_faces
+        .iter()
+        .filter(|crop| crop.duplicate_of.is_some())
+        .map(|crop| crop.index)
+        .collect();
+    
+    tracing::info!(
+        "Successfully cropped {} of {} faces",
+        cropped_faces.iter().filter(|c| c.image.is_some()).count(),
+        cropped_faces.len()
+    );
+    
+    let response = CropResponse {
+        cropped_faces,
+        skipped,
+    };
+    
+    Ok(ok_json(response, &format, version))
+}
+
+/// Builds the `?format=zip` crop response.
+/// 
+/// Each successful crop is stored as `face_<index>.jpg`, keeping the index
+/// of the face in the request; failed and skipped crops get no entry.
+/// `manifest.json` lists every requested face with its entry name, the reason
+/// it failed or the face it duplicates.
+fn crop_archive_response<'a>(
+    crops: impl Iterator<Item = (usize, &'a Face, Option<usize>, Option<Result<DynamicImage>>)>,
+) -> Result<HttpResponse> {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    
+    let zip_failed = |e: &dyn std::fmt::Display| {
+        tracing::error!("Failed to build crop archive: {}", e);
+        FaceDetectionError::InternalError
+    };
+    
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let mut manifest = Vec::new();
+    
+    for (index, face, duplicate_of, outcome) in crops {
+        let Some(outcome) = outcome else {
+            manifest.push(CropManifestEntry { index, face: face.clone(), file: None, error: None, duplicate_of });
+            continue;
+        };
+        let jpeg = outcome.and_then(|cropped_img| {
+            crate::detection::encode_jpeg(&cropped_img, crate::detection::DEFAULT_JPEG_QUALITY)
+        });
+        let (file, error) = match jpeg {
+            Ok(jpeg) => {
+                let name = format!("face_{}.jpg", index);
+                archive.start_file(name.as_str(), options).map_err(|e| zip_failed(&e))?;
+                archive.write_all(&jpeg).map_err(|e| zip_failed(&e))?;
+                (Some(name), None)
             }
             Err(e) => {
                 tracing::warn!("Failed to crop face {}: {}", index + 1, e);
-                // Skip this face and continue with others
-                continue;
+                (None, Some(e.to_string()))
+            }
+        };
+        manifest.push(CropManifestEntry { index, face: face.clone(), file, error, duplicate_of });
+    }
+    
+    tracing::info!(
+        "Archived {} of {} face crops",
+        manifest.iter().filter(|entry| entry.file.is_some()).count(),
+        manifest.len()
+    );
+    
+    let manifest = serde_json::to_vec_pretty(&manifest).map_err(|e| zip_failed(&e))?;
+    archive.start_file("manifest.json", options).map_err(|e| zip_failed(&e))?;
+    archive.write_all(&manifest).map_err(|e| zip_failed(&e))?;
+    let bytes = archive.finish().map_err(|e| zip_failed(&e))?.into_inner();
+    
+    Ok(HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header(ContentDisposition::attachment("faces.zip"))
+        .body(bytes))
+}
+
+/// For each face, the index of an earlier kept face it overlaps by more than
+/// `iou_threshold`, or `None` if the face is kept itself.
+fn overlapping_duplicates(faces: &[Face], iou_threshold: f32) -> Vec<Option<usize>> {
+    let mut kept: Vec<usize> = Vec::new();
+    faces
+        .iter()
+        .enumerate()
+        .map(|(index, face)| {
+            let duplicate_of = kept.iter().copied().find(|&other| face.iou(&faces[other]) > iou_threshold);
+            if duplicate_of.is_none() {
+                kept.push(index);
             }
+            duplicate_of
+        })
+        .collect()
+}
+
+/// Margin added around detected faces before cropping, as a fraction of
+/// the face's longer side.
+pub const DEFAULT_CROP_MARGIN: f32 = 0.2;
+
+/// Largest crop margin accepted.
+pub const MAX_CROP_MARGIN: f32 = 2.0;
+
+/// Primary face endpoint.
+/// 
+/// Detects faces in a base64 encoded image and returns a single crop of the
+/// dominant one, the largest face with ties going to the more confident,
+/// e.g. for profile photos. The crop is grown on every side by `margin`
+/// times the face's longer side ([`DEFAULT_CROP_MARGIN`] by default) and,
+/// with `square` set, made square around the face. Responds with 422 if no
+/// face is found.
+#[post("/api/primary-face")]
+pub async fn primary_face(
+    req: HttpRequest,
+    request: web::Json<PrimaryFaceRequest>,
+    format: web::Query<ResponseFormatQuery>,
+    version: EnvelopeVersion,
+    detector: web::Data<FaceDetector>,
+    backend: web::Data<dyn AsyncDetector>,
+) -> Result<HttpResponse> {
+    let margin = crop_margin(request.margin)?;
+    
+    let image_bytes = crate::detection::decode_base64_image(&request.image_data)?;
+    let img = decode_cached(&req, &image_bytes)?;
+    
+    let _slot = acquire_detection_slot(&req).await?;
+    let detection_result = detector.detect_with(backend.get_ref(), &img, false).await?;
+    let face = detection_result
+        .largest_face()
+        .cloned()
+        .ok_or(FaceDetectionError::NoFacesDetected)?;
+    
+    let mut crop = with_margin(&face, margin, &img);
+    if request.square {
+        crop.bbox = crop.bbox.squared(img.width(), img.height());
+    }
+    tracing::info!("Cropping primary face at ({}, {}) size {}x{}",
+        crop.bbox.x, crop.bbox.y, crop.bbox.width, crop.bbox.height);
+    let image = crate::detection::image_to_base64(&crate::detection::crop_face(&img, &crop)?)?;
+    
+    let response = PrimaryFaceResponse {
+        face,
+        crop: crop.bbox,
+        image,
+    };
+    
+    Ok(ok_json(response, &format, version))
+}
+
+/// Detect-and-crop endpoint.
+/// 
+/// Detects faces in a base64 encoded image and crops the selected ones in
+/// the same round trip, so a UI letting users pick faces does not have to
+/// send the image twice. `indices` selects faces by their position in the
+/// detection result (all faces when omitted); out-of-range indices and more
+/// indices than the configured crop face limit are rejected, and repeated
+/// indices are cropped once. Each crop is grown by `margin` as in
+/// `/api/primary-face`.
+#[post("/api/detect-and-crop")]
+pub async fn detect_and_crop(
+    req: HttpRequest,
+    request: web::Json<DetectAndCropRequest>,
+    format: web::Query<ResponseFormatQuery>,
+    version: EnvelopeVersion,
+    detector: web::Data<FaceDetector>,
+    backend: web::Data<dyn AsyncDetector>,
+) -> Result<HttpResponse> {
+    let margin = crop_margin(request.margin)?;
+    let max_faces = req
+        .app_data::<web::Data<AppConfig>>()
+        .map_or_else(|| AppConfig::default().max_crop_faces, |config| config.max_crop_faces);
+    if let Some(indices) = request.indices.as_ref().filter(|indices| indices.len() > max_faces) {
+        return Err(validation_error(format!(
+            "Request has {} face indices; at most {} are allowed",
+            indices.len(),
+            max_faces
+        )));
+    }
+    
+    let image_bytes = crate::detection::decode_base64_image(&request.image_data)?;
+    let img = decode_cached(&req, &image_bytes)?;
+    
+    let _slot = acquire_detection_slot(&req).await?;
+    let detection_result = detector.detect_with(backend.get_ref(), &img, false).await?;
+    
+    let indices: Vec<usize> = match &request.indices {
+        Some(indices) => {
+            let mut seen = std::collections::HashSet::new();
+            indices.iter().copied().filter(|index| seen.insert(*index)).collect()
         }
+        None => (0..detection_result.faces.len()).collect(),
+    };
+    if let Some(index) = indices.iter().find(|&&index| index >= detection_result.faces.len()) {
+        return Err(validation_error(format!(
+            "Face index {} is out of range; {} faces were detected",
+            index,
+            detection_result.faces.len()
+        )));
     }
     
-    tracing::info!("Successfully cropped {} faces", cropped_faces.len());
+    let crops = indices
+        .into_iter()
+        .map(|index| {
+            let face = &detection_result.faces[index];
+            let crop = with_margin(face, margin, &img);
+            let (image, error) = match crate::detection::crop_face(&img, &crop)
+                .and_then(|cropped_img| crate::detection::image_to_base64(&cropped_img))
+            {
+                Ok(base64_string) => (Some(base64_string), None),
+                Err(e) => {
+                    tracing::warn!("Failed to crop face {}: {}", index + 1, e);
+                    (None, Some(e.to_string()))
+                }
+            };
+            CroppedFace {
+                index,
+                face: face.clone(),
+                image,
+                error,
+                duplicate_of: None,
+                storage_key: None,
+            }
+        })
+        .collect();
     
-    let response = CropResponse {
-        cropped_faces,
+    let response = DetectAndCropResponse {
+        detection_result,
+        crops,
     };
     
-    Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+    Ok(ok_json(response, &format, version))
 }
 
-/// Validates that a file is a valid image.
+/// Smart crop endpoint.
 /// 
-/// # Arguments
+/// Detects faces in a base64 encoded image and crops it to the smallest
+/// region containing all of them, grown by `padding` pixels on every side
+/// and clamped to the image. Responds with 422 if no face is found.
+#[post("/api/smart-crop")]
+pub async fn smart_crop(
+    req: HttpRequest,
+    request: web::Json<SmartCropRequest>,
+    format: web::Query<ResponseFormatQuery>,
+    version: EnvelopeVersion,
+    detector: web::Data<FaceDetector>,
+    backend: web::Data<dyn AsyncDetector>,
+) -> Result<HttpResponse> {
+    let image_bytes = crate::detection::decode_base64_image(&request.image_data)?;
+    let img = decode_cached(&req, &image_bytes)?;
+    
+    let _slot = acquire_detection_slot(&req).await?;
+    let detection_result = detector.detect_with(backend.get_ref(), &img, false).await?;
+    let region = crate::detection::bounding_region(&detection_result.faces, request.padding, img.width(), img.height())
+        .ok_or(FaceDetectionError::NoFacesDetected)?;
+    
+    tracing::info!("Smart crop of {} faces at ({}, {}) size {}x{}", detection_result.faces.len(),
+        region.bbox.x, region.bbox.y, region.bbox.width, region.bbox.height);
+    let image = crate::detection::image_to_base64(&crate::detection::crop_face(&img, &region)?)?;
+    
+    let response = SmartCropResponse {
+        faces: detection_result.faces,
+        crop: region.bbox,
+        image,
+    };
+    
+    Ok(ok_json(response, &format, version))
+}
+
+/// Resolves a requested crop margin, defaulting to [`DEFAULT_CROP_MARGIN`].
+fn crop_margin(margin: Option<f32>) -> Result<f32> {
+    let margin = margin.unwrap_or(DEFAULT_CROP_MARGIN);
+    if !(0.0..=MAX_CROP_MARGIN).contains(&margin) {
+        return Err(validation_error(format!(
+            "Margin {} must be between 0 and {}",
+            margin, MAX_CROP_MARGIN
+        )));
+    }
+    Ok(margin)
+}
+
+/// Returns `face` grown on every side by `margin` times its longer side,
+/// clamped to the image.
+fn with_margin(face: &Face, margin: f32, img: &DynamicImage) -> Face {
+    let pixels = (margin * face.bbox.width.max(face.bbox.height)).round() as i32;
+    face.expand(pixels, img.width(), img.height())
+}
+
+/// Face presence endpoint.
 /// 
-/// * `filepath` - Path to the file to validate
+/// Accepts raw image bytes as the request body and returns only whether a
+/// face was found, for callers that need a fast yes/no decision. The body is
+/// held to the same size cap, header check and detection slot as uploads.
+#[post("/api/has-face")]
+pub async fn has_face(
+    req: HttpRequest,
+    mut payload: web::Payload,
+    format: web::Query<ResponseFormatQuery>,
+    version: EnvelopeVersion,
+    config: web::Data<AppConfig>,
+    detector: web::Data<FaceDetector>,
+    backend: web::Data<dyn AsyncDetector>,
+) -> Result<HttpResponse> {
+    use futures_util::TryStreamExt;
+    
+    check_declared_content_length(&req, config.max_file_size)?;
+    let idle_timeout = std::time::Duration::from_secs(config.upload_idle_timeout_secs);
+    
+    // Cap the bytes actually received, since chunked bodies declare no Content-Length
+    let mut body = Vec::new();
+    while let Some(chunk) = with_idle_timeout(idle_timeout, payload.try_next()).await?
+        .map_err(|_| FaceDetectionError::InvalidImageData)? {
+        push_limited(&mut body, &chunk, config.max_file_size)?;
+    }
+    if body.is_empty() {
+        return Err(validation_error("Request body is empty"));
+    }
+    
+    // Reject unsupported and oversized images from the header before decoding
+    check_image_header(&body, &config.dimension_limits())?;
+    
+    let _slot = acquire_detection_slot(&req).await?;
+    
+    let img = decode_cached(&req, &body)?;
+    let result = detector.detect_with(backend.get_ref(), &img, false).await?;
+    let best = result.faces.iter().map(|face| face.confidence).reduce(f32::max);
+    let response = HasFaceResponse {
+        has_face: best.is_some(),
+        max_confidence: best.unwrap_or(0.0),
+    };
+    
+    Ok(ok_json(response, &format, version))
+}
+
+/// Batch detection endpoint.
 /// 
-/// # Returns
+/// Detects faces in several base64 encoded images. Each image gets its own
+/// detection timeout, so a slow or broken image is reported in its own slot
+/// and marks the response `partial` without holding back the others.
+#[post("/api/detect/batch")]
+pub async fn detect_batch(
+    req: HttpRequest,
+    request: web::Json<BatchRequest>,
+    format: web::Query<ResponseFormatQuery>,
+    version: EnvelopeVersion,
+    detector: web::Data<FaceDetector>,
+    config: web::Data<AppConfig>,
+) -> Result<HttpResponse> {
+    let images = request.into_inner().images;
+    tracing::info!("Received batch detection request for {} images", images.len());
+    
+    if images.is_empty() || images.len() > crate::batch::MAX_BATCH_SIZE {
+        return Err(validation_error(format!(
+            "Batch must contain between 1 and {} images",
+            crate::batch::MAX_BATCH_SIZE
+        )));
+    }
+    
+    // The whole batch shares one detection slot
+    let _slot = acquire_detection_slot(&req).await?;
+    let detector = detector.into_inner();
+    let timeout = std::time::Duration::from_secs(config.detection_timeout_secs);
+    let limits = config.dimension_limits();
+    let response = crate::batch::detect_batch(images, timeout, move |image_data: String| {
+        let bytes = crate::detection::decode_base64_image(&image_data)?;
+        check_image_header(&bytes, &limits)?;
+        let img = crate::detection::decode_image(&bytes)?;
+        detector.detect_image(&img)
+    })
+    .await;
+    
+    Ok(ok_json(response, &format, version))
+}
+
+/// Image validation endpoint.
 /// 
-/// Ok(()) if the file is a valid image, error otherwise.
-fn validate_image_file(filepath: &str) -> Result<()> {
-    match image::open(filepath) {
-        Ok(_) => {
-            tracing::info!("Image validation successful for: {}", filepath);
-            Ok(())
+/// Checks an image's format, dimensions and size against the configured
+/// limits without running detection. Accepts either a multipart form with an
+/// `image` field or a JSON body with base64 `imageData`.
+#[post("/api/validate")]
+pub async fn validate_image(
+    req: HttpRequest,
+    payload: web::Payload,
+    format: web::Query<ResponseFormatQuery>,
+    version: EnvelopeVersion,
+    config: web::Data<AppConfig>,
+) -> Result<HttpResponse> {
+    use futures_util::TryStreamExt;
+    
+    // Bodies may carry multipart or base64 overhead, and oversized images are
+    // reported as a reason rather than an error, so allow some headroom
+    let hard_limit = config.max_file_size.saturating_mul(2);
+    let is_multipart = req
+        .headers()
+        .get(actix_web::http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+    
+    let image_bytes = if is_multipart {
+        let mut multipart = actix_multipart::Multipart::new(req.headers(), payload);
+        let mut image = None;
+        while let Some(mut field) = multipart.try_next().await
+            .map_err(|_| FaceDetectionError::MultipartError)? {
+            if field.content_disposition().get_name() != Some("image") {
+                continue;
+            }
+            let mut bytes = Vec::new();
+            while let Some(chunk) = field.try_next().await
+                .map_err(|_| FaceDetectionError::MultipartError)? {
+                push_limited(&mut bytes, &chunk, hard_limit)?;
+            }
+            image = Some(bytes);
         }
-        Err(e) => {
-            tracing::error!("Image validation failed for {}: {}", filepath, e);
-            Err(FaceDetectionError::ImageProcessing { source: e })
+        image.ok_or(FaceDetectionError::NoFileUploaded)?
+    } else {
+        let mut payload = payload;
+        let mut body = Vec::new();
+        while let Some(chunk) = payload.try_next().await
+            .map_err(|_| FaceDetectionError::InvalidImageData)? {
+            push_limited(&mut body, &chunk, hard_limit)?;
         }
+        let request: ValidateRequest = serde_json::from_slice(&body)
+            .map_err(|e| validation_error(format!("Invalid validate request: {}", e)))?;
+        crate::detection::decode_base64_image(&request.image_data)?
+    };
+    
+    let _slot = acquire_detection_slot(&req).await?;
+    let response = validation_report(&image_bytes, &config);
+    tracing::info!(
+        "Validated {} byte image: valid={} reasons={:?}",
+        response.size_bytes,
+        response.valid,
+        response.reasons
+    );
+    
+    Ok(ok_json(response, &format, version))
+}
+
+/// Appends `chunk` to `buffer`, failing once the total exceeds `limit`.
+fn push_limited(buffer: &mut Vec<u8>, chunk: &[u8], limit: usize) -> Result<()> {
+    if buffer.len() + chunk.len() > limit {
+        return Err(FaceDetectionError::FileTooLarge {
+            size: buffer.len() + chunk.len(),
+            max_size: limit,
+        });
     }
+    buffer.extend_from_slice(chunk);
+    Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use actix_web::{test, web, App};
+/// Checks encoded image bytes against the upload limits without decoding pixels.
+fn validation_report(bytes: &[u8], config: &AppConfig) -> ValidateResponse {
+    let reason = |e: FaceDetectionError| match e {
+        FaceDetectionError::Validation { message } => message,
+        other => other.to_string(),
+    };
+    let mut report = ValidateResponse {
+        size_bytes: bytes.len(),
+        ..ValidateResponse::default()
+    };
+    
+    if bytes.is_empty() {
+        report.reasons.push("Image is empty".to_string());
+        return report;
+    }
+    
+    if bytes.len() > config.max_file_size {
+        report.reasons.push(format!(
+            "Image size {} bytes exceeds the maximum of {} bytes",
+            bytes.len(),
+            config.max_file_size
+        ));
+    }
+    
+    match crate::detection::sniff_image_format(bytes) {
+        Ok(image_format) => {
+            report.format = Some(crate::detection::format_name(image_format));
+            match crate::detection::probe_bytes_dimensions(bytes, image_format) {
+                Ok((width, height)) => {
+                    report.width = Some(width);
+                    report.height = Some(height);
+                    if let Err(e) = crate::detection::check_dimensions(width, height, &config.dimension_limits()) {
+                        report.reasons.push(reason(e));
+                    }
+                }
+                Err(e) => report.reasons.push(format!("Unreadable image header: {}", e)),
+            }
+        }
+        Err(e) => report.reasons.push(reason(e)),
+    }
+    
+    report.valid = report.reasons.is_empty();
+    report
+}
 
-    #[actix_web::test]
-    async fn test_health_endpoint() {
-        let app = test::init_service(
-            App::new().service(health_check)
-        ).await;
+/// Face embedding endpoint.
+/// 
+/// Accepts an image and face coordinates, crops each face and returns one
+/// feature vector per face from the configured [`Embedder`].
+#[post("/api/embed")]
+pub async fn embed_faces(
+    req: HttpRequest,
+    request: web::Json<EmbedRequest>,
+    format: web::Query<ResponseFormatQuery>,
+    version: EnvelopeVersion,
+    embedder: web::Data<dyn Embedder>,
+) -> Result<HttpResponse> {
+    tracing::info!("Received embed request for {} faces", request.faces.len());
+    
+    let _slot = acquire_detection_slot(&req).await?;
+    let image_bytes = crate::detection::decode_base64_image(&request.image_data)?;
+    let img = image::load_from_memory(&image_bytes)
+        .map_err(decode_error)?;
+    
+    let embeddings = request
+        .faces
+        .iter()
+        .map(|face| {
+            let cropped_img = crate::detection::crop_face(&img, face)?;
+            embedder.embed(&cropped_img)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    
+    Ok(ok_json(EmbedResponse { embeddings }, &format, version))
+}
 
-        let req = test::TestRequest::get()
-            .uri("/api/health")
-            .to_request();
-        
-        let resp = test::call_service(&app, req).await;
-        assert!(resp.status().is_success());
-        
-        let body: serde_json::Value = test::read_body_json(resp).await;
-        assert_eq!(body["success"], true);
-        assert_eq!(body["data"]["status"], "healthy");
-    }
-}
\ No newline at end of file
+/// Similarity two faces need by default to share a group in `/api/group`.
+pub const DEFAULT_GROUP_THRESHOLD: f32 = 0.9;
+
+/// Face grouping endpoint.
+/// 
+/// Accepts several images, or one image with several faces, and groups the
+/// faces by identity. Each face is cropped and embedded with the configured
+/// [`Embedder`]; faces the configured [`FaceMatcher`] ([`CosineMatcher`]
+/// when none is registered) scores at least `threshold` alike
+/// ([`DEFAULT_GROUP_THRESHOLD`] by default) share a group, as do faces
+/// linked through a chain of such matches. Images sent without `faces` are
+/// run through detection first. Supplied faces are validated like
+/// `/api/crop`, each image header is checked against the dimension limits
+/// before decoding, and requests with more faces in total than the crop
+/// limit are rejected.
+#[post("/api/group")]
+pub async fn group_faces(
+    req: HttpRequest,
+    request: web::Json<GroupRequest>,
+    format: web::Query<ResponseFormatQuery>,
+    version: EnvelopeVersion,
+    detector: web::Data<FaceDetector>,
+    backend: web::Data<dyn AsyncDetector>,
+    embedder: web::Data<dyn Embedder>,
+) -> Result<HttpResponse> {
+    tracing::info!("Received group request for {} images", request.images.len());
+    
+    if request.images.is_empty() {
+        return Err(validation_error("Group request has no images"));
+    }
+    let threshold = request.threshold.unwrap_or(DEFAULT_GROUP_THRESHOLD);
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(validation_error(format!("Threshold {} must be between 0 and 1", threshold)));
+    }
+    let (max_faces, limits) = req.app_data::<web::Data<AppConfig>>().map_or_else(
+        || (AppConfig::default().max_crop_faces, AppConfig::default().dimension_limits()),
+        |config| (config.max_crop_faces, config.dimension_limits()),
+    );
+    let matcher = req
+        .app_data::<web::Data<dyn FaceMatcher>>()
+        .map_or(&CosineMatcher as &dyn FaceMatcher, |matcher| matcher.get_ref());
+    
+    let mut faces = Vec::with_capacity(request.images.len());
+    let mut refs = Vec::new();
+    let mut embeddings = Vec::new();
+    for (image_index, image) in request.images.iter().enumerate() {
+        if let Some(image_faces) = &image.faces {
+            validate_supplied_faces(image_faces, max_faces, &limits)?;
+        }
+        let image_bytes = crate::detection::decode_base64_image(&image.image_data)?;
+        check_image_header(&image_bytes, &limits)?;
+        let img = decode_cached(&req, &image_bytes)?;
+        let image_faces = match &image.faces {
+            Some(image_faces) => image_faces.clone(),
+            None => {
+                let _slot = acquire_detection_slot(&req).await?;
+                detector.detect_with(backend.get_ref(), &img, false).await?.faces
+            }
+        };
+        if refs.len() + image_faces.len() > max_faces {
+            return Err(validation_error(format!(
+                "Group request has more than {} faces",
+                max_faces
+            )));
+        }
+        
+        for (face_index, face) in image_faces.iter().enumerate() {
+            let cropped_img = crate::detection::crop_face(&img, face)?;
+            embeddings.push(embedder.embed(&cropped_img)?);
+            refs.push(FaceRef { image: image_index, face: face_index });
+        }
+        faces.push(image_faces);
+    }
+    
+    let groups: Vec<Vec<FaceRef>> = crate::matcher::group_faces(&embeddings, matcher, threshold)
+        .into_iter()
+        .map(|group| group.into_iter().map(|index| refs[index]).collect())
+        .collect();
+    tracing::info!("Grouped {} faces into {} groups", refs.len(), groups.len());
+    
+    Ok(ok_json(GroupResponse { faces, groups }, &format, version))
+}
+
+/// Rejects crop requests with too many faces or out-of-range face fields.
+/// 
+/// Runs before the image is decoded, so oversized requests cost nothing.
+/// Boxes merely outside the image are still reported per face.
+fn validate_crop_request(request: &CropRequest, max_faces: usize, limits: &DimensionLimits) -> Result<()> {
+    validate_supplied_faces(&request.faces, max_faces, limits)
+}
+
+/// Rejects caller-supplied faces that are too many or have out-of-range
+/// fields, before any image work is done with them.
+fn validate_supplied_faces(faces: &[Face], max_faces: usize, limits: &DimensionLimits) -> Result<()> {
+    if faces.len() > max_faces {
+        return Err(validation_error(format!(
+            "Request has {} faces; at most {} are allowed",
+            faces.len(),
+            max_faces
+        )));
+    }
+    
+    for (index, face) in faces.iter().enumerate() {
+        let bbox = &face.bbox;
+        if [bbox.x, bbox.y, bbox.width, bbox.height].iter().any(|&v| !v.is_finite() || v < 0.0) {
+            return Err(validation_error(format!(
+                "Face {} box coordinates must be finite and not negative",
+                index
+            )));
+        }
+        if bbox.right() > limits.max_width as f32 || bbox.bottom() > limits.max_height as f32 {
+            return Err(validation_error(format!(
+                "Face {} extends beyond the maximum image size of {}x{}",
+                index, limits.max_width, limits.max_height
+            )));
+        }
+        if !(0.0..=1.0).contains(&face.confidence) {
+            return Err(validation_error(format!(
+                "Face {} confidence {} must be between 0 and 1",
+                index, face.confidence
+            )));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Summary of a detection result sent as response headers.
+struct DetectionSummaryHeaders {
+    face_count: usize,
+    image_width: u32,
+    image_height: u32,
+    processing_time_ms: u64,
+}
+
+impl From<&DetectionResult> for DetectionSummaryHeaders {
+    fn from(result: &DetectionResult) -> Self {
+        Self {
+            face_count: result.total_faces,
+            image_width: result.image_width,
+            image_height: result.image_height,
+            processing_time_ms: result.processing_time_ms,
+        }
+    }
+}
+
+impl DetectionSummaryHeaders {
+    /// Adds the `X-Face-Count`, `X-Image-Width`, `X-Image-Height` and
+    /// `X-Processing-Time-Ms` headers to `response`.
+    /// 
+    /// All values are plain decimal integers, so they are always ASCII.
+    fn apply(self, mut response: HttpResponse) -> HttpResponse {
+        let headers = response.headers_mut();
+        headers.insert(HeaderName::from_static("x-face-count"), HeaderValue::from(self.face_count));
+        headers.insert(HeaderName::from_static("x-image-width"), HeaderValue::from(self.image_width));
+        headers.insert(HeaderName::from_static("x-image-height"), HeaderValue::from(self.image_height));
+        headers.insert(HeaderName::from_static("x-processing-time-ms"), HeaderValue::from(self.processing_time_ms));
+        response
+    }
+}
+
+/// Takes a detection slot from the registered [`DetectionLimiter`], if any.
+/// 
+/// The slot is held until the returned permit is dropped.
+async fn acquire_detection_slot(req: &HttpRequest) -> Result<Option<OwnedSemaphorePermit>> {
+    match req.app_data::<web::Data<DetectionLimiter>>() {
+        Some(limiter) => limiter.acquire().await.map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Parses an optional `rrggbb` padding color, defaulting to black.
+fn parse_pad_color(hex: Option<&str>) -> Result<image::Rgb<u8>> {
+    hex.map_or(Ok(ResizeOptions::default().pad_color), crate::detection::parse_hex_color)
+}
+
+/// Reads the envelope version a client asked for from its `Accept` header.
+impl actix_web::FromRequest for EnvelopeVersion {
+    type Error = actix_web::Error;
+    type Future = std::future::Ready<std::result::Result<Self, Self::Error>>;
+    
+    fn from_request(req: &HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        let accept = req.headers().get(header::ACCEPT).and_then(|value| value.to_str().ok());
+        std::future::ready(Ok(accept.map_or_else(EnvelopeVersion::default, EnvelopeVersion::from_accept)))
+    }
+}
+
+/// Wraps `data` in a successful `ApiResponse` and builds a 200 JSON response.
+/// 
+/// The types serialize as camelCase; `?case=snake` re-serializes the same
+/// value through [`to_snake_case_keys`] for clients that expect snake_case.
+/// `?meta=false` leaves out the `metadata` block. `version` selects the
+/// envelope shape and content type; v1 bodies are unchanged.
+fn ok_json<T: serde::Serialize>(data: T, format: &ResponseFormatQuery, version: EnvelopeVersion) -> HttpResponse {
+    let mut body = ApiResponse::success(data);
+    if !format.meta {
+        body = body.without_metadata();
+    }
+    
+    if format.case == KeyCase::Camel && version == EnvelopeVersion::V1 {
+        return HttpResponse::Ok().json(body);
+    }
+    
+    match serde_json::to_value(&body) {
+        Ok(value) => {
+            let value = version.reshape(value);
+            let value = match format.case {
+                KeyCase::Camel => value,
+                KeyCase::Snake => to_snake_case_keys(value),
+            };
+            HttpResponse::Ok().content_type(version.media_type()).json(value)
+        }
+        Err(e) => {
+            tracing::error!("Failed to serialize response: {}", e);
+            actix_web::ResponseError::error_response(&FaceDetectionError::InternalError)
+        }
+    }
+}
+
+/// Like [`ok_json`], but streams `detectionResult.faces` instead of
+/// rendering the whole body at once.
+/// 
+/// Used for results with more than [`STREAM_FACE_THRESHOLD`] faces. Honors
+/// `?case=`, `?meta=` and the envelope version the same way.
+fn ok_json_streamed(
+    mut data: DetectionResponse,
+    format: &ResponseFormatQuery,
+    version: EnvelopeVersion,
+) -> Result<HttpResponse> {
+    let faces = std::mem::take(&mut data.detection_result.faces);
+    let mut body = ApiResponse::success(data);
+    if !format.meta {
+        body = body.without_metadata();
+    }
+    
+    let envelope = serde_json::to_value(&body).map_err(|e| {
+        tracing::error!("Failed to serialize response: {}", e);
+        FaceDetectionError::InternalError
+    })?;
+    let envelope = version.reshape(envelope);
+    
+    let result_key = match format.case {
+        KeyCase::Camel => "detectionResult",
+        KeyCase::Snake => "detection_result",
+    };
+    let path: &[&str] = match version {
+        EnvelopeVersion::V1 => &["data", result_key, "faces"],
+        EnvelopeVersion::V2 => &[result_key, "faces"],
+    };
+    
+    let mut response = HttpResponse::Ok();
+    response.content_type(version.media_type());
+    Ok(match format.case {
+        KeyCase::Camel => response.streaming(stream_array_at(envelope, path, faces, |face| face)?),
+        KeyCase::Snake => {
+            response.streaming(stream_array_at(to_snake_case_keys(envelope), path, faces, to_snake_case_keys)?)
+        }
+    })
+}
+
+/// Builds the CORS middleware for the configured methods.
+/// 
+/// Any origin and request header is allowed, and the configured response
+/// headers are exposed to scripts. Preflight requests are not answered by
+/// the middleware but reach [`not_found`], which replies to `OPTIONS` on
+/// every known route.
+pub fn cors_middleware(config: &AppConfig) -> Cors {
+    let cors = Cors::default()
+        .allow_any_origin()
+        .allowed_methods(config.cors_allowed_methods.iter().map(String::as_str))
+        .allow_any_header()
+        .max_age(config.cors_max_age_secs)
+        .disable_preflight();
+    if config.cors_expose_headers.is_empty() {
+        return cors;
+    }
+    cors.expose_headers(config.cors_expose_headers.iter().map(String::as_str))
+}
+
+/// Fallback handler for requests that match no route.
+/// 
+/// `OPTIONS` on a known route is answered with 204 and the CORS preflight
+/// headers. Other methods get a 405 if the path belongs to a known route
+/// registered for another method, otherwise a 404, both with the standard
+/// JSON error envelope.
+pub async fn not_found(req: HttpRequest) -> HttpResponse {
+    if req.method() == Method::OPTIONS && req.resource_map().has_resource(req.path()) {
+        return preflight_response(&req);
+    }
+    
+    if req.resource_map().has_resource(req.path()) {
+        tracing::warn!("Method {} not allowed for {}", req.method(), req.path());
+        return HttpResponse::MethodNotAllowed().json(ApiResponse::<()>::error("Method Not Allowed"));
+    }
+    
+    tracing::warn!("No route for {} {}", req.method(), req.path());
+    HttpResponse::NotFound().json(ApiResponse::<()>::error("Not Found"))
+}
+
+/// Builds the 204 reply to an `OPTIONS` request on a known route.
+/// 
+/// Lists the configured methods, echoes the requested headers and lets
+/// browsers cache the answer; `Access-Control-Allow-Origin` is added by the
+/// CORS middleware.
+fn preflight_response(req: &HttpRequest) -> HttpResponse {
+    let default_config;
+    let config = match req.app_data::<web::Data<AppConfig>>() {
+        Some(config) => config.get_ref(),
+        None => {
+            default_config = AppConfig::default();
+            &default_config
+        }
+    };
+    
+    let mut methods = config.cors_allowed_methods.clone();
+    if !methods.iter().any(|method| method == Method::OPTIONS.as_str()) {
+        methods.push(Method::OPTIONS.to_string());
+    }
+    let methods = methods.join(", ");
+    
+    let mut response = HttpResponse::NoContent();
+    response
+        .insert_header((header::ALLOW, methods.clone()))
+        .insert_header((header::ACCESS_CONTROL_ALLOW_METHODS, methods))
+        .insert_header((header::ACCESS_CONTROL_MAX_AGE, config.cors_max_age_secs.to_string()));
+    if let Some(requested) = req.headers().get(header::ACCESS_CONTROL_REQUEST_HEADERS) {
+        response.insert_header((header::ACCESS_CONTROL_ALLOW_HEADERS, requested.clone()));
+    }
+    response.finish()
+}
+
+/// JSON extractor settings for the JSON endpoints.
+/// 
+/// Bodies are limited to `limit` bytes, and bodies that are not
+/// `application/json` are answered with 415 in the usual error envelope
+/// instead of actix's plain-text error.
+pub fn json_config(limit: usize) -> web::JsonConfig {
+    web::JsonConfig::default().limit(limit).error_handler(|err, req| match err {
+        actix_web::error::JsonPayloadError::ContentType => unsupported_media_type(req, "application/json").into(),
+        err => err.into(),
+    })
+}
+
+/// Rejects requests whose `Content-Type` is not `expected`, such as JSON
+/// posted to the multipart upload endpoint.
+fn require_content_type(req: &HttpRequest, expected: &'static str) -> Result<()> {
+    let essence = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(str::trim);
+    
+    match essence {
+        Some(essence) if essence.eq_ignore_ascii_case(expected) => Ok(()),
+        _ => Err(unsupported_media_type(req, expected)),
+    }
+}
+
+/// Builds the 415 error for a request that should have been `expected`.
+fn unsupported_media_type(req: &HttpRequest, expected: &'static str) -> FaceDetectionError {
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .map_or_else(|| "none".to_string(), |value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+    tracing::warn!("Rejecting {} request with content type {}", req.path(), content_type);
+    FaceDetectionError::UnsupportedMediaType { content_type, expected }
+}
+
+/// Rejects requests whose declared `Content-Length` exceeds the upload limit.
+/// 
+/// Requests without the header (e.g. chunked uploads) pass through; the
+/// upload handler still caps the bytes it actually receives.
+fn check_declared_content_length(req: &HttpRequest, max_size: usize) -> Result<()> {
+    let declared = req
+        .headers()
+        .get(actix_web::http::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    
+    match declared {
+        Some(size) if size > max_size => {
+            tracing::warn!("Rejecting upload with declared size {} bytes", size);
+            Err(FaceDetectionError::FileTooLarge { size, max_size })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Runs `f` inside `span`, returning its output and the elapsed milliseconds.
+fn timed<T>(span: tracing::Span, f: impl FnOnce() -> T) -> (T, f64) {
+    let start = std::time::Instant::now();
+    let output = span.in_scope(f);
+    (output, start.elapsed().as_secs_f64() * 1000.0)
+}
+
+/// Awaits the next piece of an upload, failing if it takes longer than `idle`.
+async fn with_idle_timeout<F: std::future::Future>(
+    idle: std::time::Duration,
+    next: F,
+) -> Result<F::Output> {
+    tokio::time::timeout(idle, next)
+        .await
+        .map_err(|_| FaceDetectionError::UploadTimeout { idle_secs: idle.as_secs() })
+}
+
+/// Decodes image bytes, going through the shared decode cache when one is registered.
+fn decode_cached(req: &HttpRequest, bytes: &[u8]) -> Result<Arc<DynamicImage>> {
+    let Some(cache) = req.app_data::<web::Data<DecodeCache>>() else {
+        return crate::detection::decode_image(bytes).map(Arc::new);
+    };
+    
+    let (image, hit) = cache.get_or_decode(bytes, crate::detection::decode_image)?;
+    if let Some(stats) = req.app_data::<web::Data<RuntimeStats>>() {
+        stats.record_cache_lookup(hit);
+    }
+    Ok(image)
+}
+
+/// Checks that encoded image bytes are in a supported format and within
+/// `limits`, reading only the header so oversized images are rejected before
+/// any pixel data is decoded.
+fn check_image_header(bytes: &[u8], limits: &DimensionLimits) -> Result<()> {
+    let format = crate::detection::sniff_image_format(bytes)?;
+    let (width, height) = crate::detection::probe_bytes_dimensions(bytes, format)?;
+    crate::detection::check_dimensions(width, height, limits)
+}
+
+/// Maximum length in bytes of a sanitized filename.
+const MAX_FILENAME_LEN: usize = 255;
+
+/// Reduces a client-supplied filename to a safe, printable base name.
+/// 
+/// Control characters (including NUL) are removed, any directory part is
+/// dropped, and leading dots and surrounding whitespace are trimmed so the
+/// result can't name a parent directory or hidden file. Names that end up
+/// empty become `"upload"`. The result is still only meant for logging and
+/// display; disk paths always use generated names.
+pub fn sanitize_filename(filename: &str) -> String {
+    let printable: String = filename.chars().filter(|c| !c.is_control()).collect();
+    let base = printable.rsplit(['/', '\\']).next().unwrap_or_default();
+    let base = base.trim().trim_start_matches('.').trim_start();
+    
+    let mut end = base.len().min(MAX_FILENAME_LEN);
+    while !base.is_char_boundary(end) {
+        end -= 1;
+    }
+    
+    match &base[..end] {
+        "" => "upload".to_string(),
+        name => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App};
+
+    /// The default backend, running a default detector on the blocking pool.
+    fn blocking_backend() -> web::Data<dyn AsyncDetector> {
+        let backend = crate::backend::BlockingDetector::new(FaceDetector::new().unwrap());
+        web::Data::from(Arc::new(backend) as Arc<dyn AsyncDetector>)
+    }
+
+    #[actix_web::test]
+    async fn test_health_endpoint() {
+        let app = test::init_service(
+            App::new().service(health_check)
+        ).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/health")
+            .to_request();
+        
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["success"], true);
+        assert_eq!(body["data"]["status"], "healthy");
+    }
+
+    #[actix_web::test]
+    async fn test_health_endpoint_without_metadata() {
+        let app = test::init_service(
+            App::new().service(health_check)
+        ).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/health")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(body["metadata"]["version"].is_string());
+
+        let req = test::TestRequest::get()
+            .uri("/api/health?meta=false")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["success"], true);
+        assert_eq!(body["data"]["status"], "healthy");
+        assert!(body.get("metadata").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_has_face_endpoint() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(FaceDetector::new().unwrap()))
+                .app_data(blocking_backend())
+                .app_data(web::Data::new(AppConfig::default()))
+                .service(has_face)
+        ).await;
+
+        for (size, expected) in [(500, true), (50, false)] {
+            let mut bytes = Vec::new();
+            image::DynamicImage::new_rgb8(size, size)
+                .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+                .unwrap();
+            
+            let req = test::TestRequest::post()
+                .uri("/api/has-face")
+                .insert_header(("Content-Type", "application/octet-stream"))
+                .set_payload(bytes)
+                .to_request();
+            let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+            
+            assert_eq!(body["data"]["hasFace"], expected, "{}px image", size);
+            if expected {
+                assert_eq!(body["data"]["maxConfidence"], 0.95);
+            } else {
+                assert_eq!(body["data"]["maxConfidence"], 0.0);
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_detect_batch_checks_each_image_header() {
+        use base64::Engine;
+        
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(FaceDetector::new().unwrap()))
+                .app_data(web::Data::new(AppConfig::default()))
+                .service(detect_batch)
+        ).await;
+        
+        let png = |width, height| {
+            let mut bytes = Vec::new();
+            image::DynamicImage::new_luma8(width, height)
+                .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+                .unwrap();
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        };
+        let req = test::TestRequest::post()
+            .uri("/api/detect/batch")
+            .set_json(serde_json::json!({ "images": [png(500, 500), png(50000, 1)] }))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        
+        let items = body["data"]["items"].as_array().unwrap();
+        assert_eq!(items[0]["status"], "ok");
+        assert_eq!(items[1]["status"], "failed");
+        assert!(items[1]["error"].as_str().unwrap().contains("width 50000"), "{}", items[1]);
+    }
+
+    #[actix_web::test]
+    async fn test_has_face_applies_upload_limits() {
+        let config = AppConfig {
+            max_file_size: 1024,
+            ..AppConfig::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(FaceDetector::new().unwrap()))
+                .app_data(blocking_backend())
+                .app_data(web::Data::new(config))
+                .service(has_face)
+        ).await;
+        
+        let req = test::TestRequest::post()
+            .uri("/api/has-face")
+            .set_payload(vec![0u8; 2048])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 413);
+        
+        // A tiny PNG whose header declares a 50000px wide image
+        let mut png = Vec::new();
+        image::DynamicImage::new_luma8(50000, 1)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+        assert!(png.len() <= 1024, "{} bytes", png.len());
+        
+        let req = test::TestRequest::post()
+            .uri("/api/has-face")
+            .set_payload(png)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body["details"].as_str().unwrap().contains("width 50000"), "{}", body);
+    }
+
+    #[actix_web::test]
+    async fn test_formats_endpoint_matches_sniffer() {
+        let app = test::init_service(
+            App::new().service(supported_formats)
+        ).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/formats")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        
+        let inputs: Vec<&str> = body["data"]["inputFormats"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f.as_str().unwrap())
+            .collect();
+        assert!(inputs.contains(&"jpeg"));
+        assert!(inputs.contains(&"png"));
+        assert_eq!(body["data"]["outputFormats"], serde_json::json!(["jpeg"]));
+        
+        let expected: Vec<String> = crate::detection::SUPPORTED_INPUT_FORMATS
+            .iter()
+            .copied()
+            .map(crate::detection::format_name)
+            .collect();
+        assert_eq!(inputs, expected);
+    }
+
+    #[actix_web::test]
+    async fn test_sanitize_filename_strips_paths_and_control_chars() {
+        assert_eq!(sanitize_filename("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_filename("..\\..\\windows\\win.ini"), "win.ini");
+        assert_eq!(sanitize_filename("face\0.jpg"), "face.jpg");
+        assert_eq!(sanitize_filename("evil.jpg\0/../../x"), "x");
+        assert_eq!(sanitize_filename("photo\r\n\u{1b}[31m.png"), "photo[31m.png");
+        assert_eq!(sanitize_filename("  .hidden.jpg "), "hidden.jpg");
+        assert_eq!(sanitize_filename("wajah-ñ.jpg"), "wajah-ñ.jpg");
+        for empty in ["", "..", "../", "\0\0", "dir/"] {
+            assert_eq!(sanitize_filename(empty), "upload");
+        }
+        
+        let long = "é".repeat(200);
+        let sanitized = sanitize_filename(&long);
+        assert!(sanitized.len() <= MAX_FILENAME_LEN);
+        assert!(long.starts_with(&sanitized));
+    }
+
+    #[actix_web::test]
+    async fn test_debug_config_absent_when_disabled() {
+        let config = AppConfig::default();
+        let app = test::init_service(
+            App::new()
+                .configure(|cfg| configure_debug_routes(cfg, &config))
+                .default_service(web::to(not_found))
+        ).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/debug/config")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_debug_config_returns_redacted_config_when_enabled() {
+        let config = AppConfig {
+            enable_debug_endpoints: true,
+            ..AppConfig::default()
+        };
+        let stats = web::Data::new(RuntimeStats::new());
+        stats.record_request();
+        stats.record_faces(2);
+        
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config.clone()))
+                .app_data(web::Data::new(FaceDetector::new().unwrap()))
+                .app_data(blocking_backend())
+                .app_data(stats)
+                .configure(|cfg| configure_debug_routes(cfg, &config))
+        ).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/debug/config")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        
+        assert_eq!(body["data"]["config"], config.redacted());
+        assert_eq!(body["data"]["config"]["enableDebugEndpoints"], true);
+        assert_eq!(body["data"]["detector"]["minDimension"], 200);
+        assert_eq!(body["data"]["stats"]["requestsServed"], 1);
+        assert_eq!(body["data"]["stats"]["facesDetected"], 2);
+    }
+
+    #[actix_web::test]
+    async fn test_readiness_follows_self_test() {
+        let passed = crate::selftest::run_self_test(&FaceDetector::new().unwrap());
+        let failed = SelfTestReport {
+            passed: false,
+            faces: 0,
+            elapsed_ms: 1.0,
+            error: Some("Invalid image data".to_string()),
+        };
+        
+        for (report, status) in [(Some(passed), 200), (Some(failed), 503), (None, 200)] {
+            let mut app = App::new().service(readiness);
+            if let Some(report) = report {
+                app = app.app_data(web::Data::new(report));
+            }
+            let app = test::init_service(app).await;
+            
+            let req = test::TestRequest::get().uri("/api/ready").to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), status);
+            
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(body["success"], status == 200);
+            if status == 200 {
+                assert_eq!(body["data"]["ready"], true);
+            } else {
+                assert_eq!(body["error"], "Service not ready");
+            }
+        }
+    }
+
+    /// Config enabling local scans confined to `root`.
+    fn scan_config(root: &Path) -> AppConfig {
+        AppConfig {
+            enable_local_scan: true,
+            scan_root: root.to_str().unwrap().to_string(),
+            ..AppConfig::default()
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_scan_streams_one_line_per_image() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("batch");
+        std::fs::create_dir(&dir).unwrap();
+        DynamicImage::new_rgb8(500, 500).save(dir.join("a.png")).unwrap();
+        DynamicImage::new_rgb8(100, 100).save(dir.join("b.jpg")).unwrap();
+        DynamicImage::new_luma8(50000, 1).save(dir.join("c.png")).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not an image").unwrap();
+        
+        let config = scan_config(root.path());
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config.clone()))
+                .app_data(web::Data::new(FaceDetector::new().unwrap()))
+                .configure(|cfg| configure_scan_routes(cfg, &config))
+        ).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/scan")
+            .set_json(serde_json::json!({ "dir": "batch" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("content-type").unwrap(), "application/x-ndjson");
+        
+        let body = test::read_body(resp).await;
+        let lines: Vec<serde_json::Value> = std::str::from_utf8(&body)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["file"], "a.png");
+        assert_eq!(lines[0]["result"]["totalFaces"], 2);
+        assert_eq!(lines[1]["file"], "b.jpg");
+        assert_eq!(lines[1]["result"]["totalFaces"], 0);
+        // Oversized images are rejected from their header, not decoded
+        assert_eq!(lines[2]["file"], "c.png");
+        assert!(lines[2]["error"].as_str().unwrap().contains("width 50000"), "{}", lines[2]);
+    }
+
+    #[actix_web::test]
+    async fn test_scan_rejects_path_outside_root() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        DynamicImage::new_rgb8(500, 500).save(outside.path().join("a.png")).unwrap();
+        
+        let config = scan_config(root.path());
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config.clone()))
+                .app_data(web::Data::new(FaceDetector::new().unwrap()))
+                .configure(|cfg| configure_scan_routes(cfg, &config))
+        ).await;
+
+        let escape = format!("../{}", outside.path().file_name().unwrap().to_str().unwrap());
+        for dir in [outside.path().to_str().unwrap(), escape.as_str()] {
+            let req = test::TestRequest::post()
+                .uri("/api/scan")
+                .set_json(serde_json::json!({ "dir": dir }))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 403, "{} should be rejected", dir);
+            
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(body["error"], "Path not allowed");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_scan_route_absent_unless_enabled() {
+        let config = AppConfig::default();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config.clone()))
+                .app_data(web::Data::new(FaceDetector::new().unwrap()))
+                .configure(|cfg| configure_scan_routes(cfg, &config))
+        ).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/scan")
+            .set_json(serde_json::json!({ "dir": "." }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_route_returns_json_404() {
+        let app = test::init_service(
+            App::new()
+                .service(health_check)
+                .default_service(web::to(not_found))
+        ).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/does-not-exist")
+            .to_request();
+        
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+        
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["success"], false);
+        assert_eq!(body["error"], "Not Found");
+        assert!(body["metadata"].is_object());
+    }
+
+    #[actix_web::test]
+    async fn test_wrong_method_returns_json_405() {
+        let app = test::init_service(
+            App::new()
+                .service(health_check)
+                .default_service(web::to(not_found))
+        ).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/health")
+            .to_request();
+        
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 405);
+        
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["success"], false);
+        assert_eq!(body["error"], "Method Not Allowed");
+    }
+
+    #[actix_web::test]
+    async fn test_with_idle_timeout_aborts_stalled_upload() {
+        let stalled = std::future::pending::<()>();
+        let result = with_idle_timeout(std::time::Duration::from_millis(10), stalled).await;
+        assert!(matches!(result, Err(FaceDetectionError::UploadTimeout { .. })));
+    }
+
+    #[actix_web::test]
+    async fn test_crop_request_face_fields_are_bounded() {
+        use crate::types::Face;
+        
+        let limits = AppConfig::default().dimension_limits();
+        let request = |face: Face| CropRequest {
+            image_data: String::new(),
+            faces: vec![face],
+            dedupe_overlapping: false,
+            output_size: None,
+        };
+        
+        assert!(validate_crop_request(&request(Face::new(10, 10, 20, 20, 0.5)), 100, &limits).is_ok());
+        assert!(validate_crop_request(&request(Face::new(u32::MAX - 5, 0, 20, 20, 0.5)), 100, &limits).is_err());
+        assert!(validate_crop_request(&request(Face::new(0, 16380, 10, 10, 0.5)), 100, &limits).is_err());
+        assert!(validate_crop_request(&request(Face::new(10, 10, 20, 20, 1.5)), 100, &limits).is_err());
+        assert!(validate_crop_request(&request(Face::new(10, 10, 20, 20, f32::NAN)), 100, &limits).is_err());
+        
+        let with_box = |x: f32, y: f32, width: f32, height: f32| Face {
+            bbox: crate::types::BoundingBox { x, y, width, height },
+            ..Face::new(0, 0, 0, 0, 0.5)
+        };
+        assert!(validate_crop_request(&request(with_box(-5.0, 10.0, 20.0, 20.0)), 100, &limits).is_err());
+        assert!(validate_crop_request(&request(with_box(10.0, 10.0, -20.0, 20.0)), 100, &limits).is_err());
+        assert!(validate_crop_request(&request(with_box(10.0, 10.0, 20.0, f32::NAN)), 100, &limits).is_err());
+        assert!(validate_crop_request(&request(with_box(10.5, 10.0, 20.25, 20.0)), 100, &limits).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_streamed_response_carries_all_faces() {
+        use crate::types::{DetectionResult, Face};
+        
+        let faces: Vec<Face> = (0..5000).map(|i| Face::new(i % 100 * 10, i / 100 * 10, 8, 8, 0.9)).collect();
+        let response = DetectionResponse {
+            original_image: String::new(),
+            processed_image: String::new(),
+            detection_result: DetectionResult::new(faces, 12).with_image_dimensions(1000, 500),
+            pad_offset: None,
+            upload_id: "crowd".to_string(),
+            image_hash: String::new(),
+            phash: format!("{:016x}", 0),
+            duplicate_of: None,
+            retained_path: None,
+        };
+        
+        let cases = [
+            (KeyCase::Camel, "detectionResult", "totalFaces"),
+            (KeyCase::Snake, "detection_result", "total_faces"),
+        ];
+        for (case, result_key, total_key) in cases {
+            let format = ResponseFormatQuery { case, ..ResponseFormatQuery::default() };
+            let resp = ok_json_streamed(response.clone(), &format, EnvelopeVersion::V1).unwrap();
+            assert_eq!(resp.status(), 200);
+            assert_eq!(resp.headers().get("content-type").unwrap(), "application/json");
+            
+            let body = actix_web::body::to_bytes(resp.into_body()).await.unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["success"], true);
+            assert!(body["metadata"].is_object());
+            
+            let result = &body["data"][result_key];
+            assert_eq!(result["faces"].as_array().unwrap().len(), 5000);
+            assert_eq!(result["faces"][4999], serde_json::json!({ "x": 990, "y": 490, "width": 8, "height": 8, "confidence": 0.9 }));
+            assert_eq!(result[total_key], 5000);
+        }
+        
+        let resp = ok_json_streamed(response, &ResponseFormatQuery::default(), EnvelopeVersion::V2).unwrap();
+        assert_eq!(resp.headers().get("content-type").unwrap(), crate::types::ENVELOPE_V2_MEDIA_TYPE);
+        let body = actix_web::body::to_bytes(resp.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["detectionResult"]["faces"].as_array().unwrap().len(), 5000);
+        assert_eq!(body["uploadId"], "crowd");
+    }
+}
@@ -0,0 +1,292 @@
+//! Detection backend hooks.
+//!
+//! [`Detector`] is the synchronous backend interface, implemented by the
+//! built-in [`FaceDetector`]. Remote inference servers are naturally async
+//! and implement [`AsyncDetector`] instead; [`BlockingDetector`] adapts any
+//! sync detector to it by running detection on the blocking pool.
+//!
+//! The server always registers one as `web::Data<dyn AsyncDetector>`,
+//! wrapping the shared `FaceDetector` in a `BlockingDetector` unless another
+//! backend is configured. Backends only find candidates: handlers pass them
+//! through [`FaceDetector::detect_with`], so the detector's filters apply
+//! whichever backend is in use.
+//!
+//! [`BreakerDetector`] guards any backend with a [`CircuitBreaker`], so
+//! calls fail fast while the backend is down.
+//!
+//! [`BenchDetector`] skips detection altogether and is registered in its
+//! place when `BENCH_MODE` is set, to load test the HTTP layer on its own.
+
+use crate::breaker::CircuitBreaker;
+use crate::detector::FaceDetector;
+use crate::error::{FaceDetectionError, Result};
+use crate::types::Face;
+use async_trait::async_trait;
+use image::DynamicImage;
+use std::sync::Arc;
+
+/// Finds faces in an image, synchronously.
+pub trait Detector: Send + Sync {
+    /// Returns the faces found in `image`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if detection fails.
+    fn detect(&self, image: &DynamicImage) -> Result<Vec<Face>>;
+
+    /// Identifies the model behind the detections, e.g. the model file's
+    /// name or hash, so stored results record what produced them.
+    fn model_version(&self) -> &str;
+}
+
+/// The built-in backend; its candidates are left unfiltered, see
+/// [`FaceDetector::detect_raw`].
+impl Detector for FaceDetector {
+    fn detect(&self, image: &DynamicImage) -> Result<Vec<Face>> {
+        self.detect_raw(image)
+    }
+
+    fn model_version(&self) -> &str {
+        Self::MODEL_VERSION
+    }
+}
+
+/// Finds faces in an image without blocking the calling task.
+#[async_trait]
+pub trait AsyncDetector: Send + Sync {
+    /// Returns the faces found in `image`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if detection fails or the backend cannot be reached.
+    async fn detect(&self, image: Arc<DynamicImage>) -> Result<Vec<Face>>;
+
+    /// Identifies the model behind the detections; see
+    /// [`Detector::model_version`].
+    fn model_version(&self) -> &str;
+}
+
+/// Runs a sync [`Detector`] on the blocking pool as an [`AsyncDetector`].
+#[derive(Debug)]
+pub struct BlockingDetector<D> {
+    /// The wrapped detector, shared with in-flight blocking tasks.
+    inner: Arc<D>,
+}
+
+impl<D> BlockingDetector<D> {
+    /// Wraps `detector`.
+    pub fn new(detector: D) -> Self {
+        Self {
+            inner: Arc::new(detector),
+        }
+    }
+}
+
+#[async_trait]
+impl<D: Detector + 'static> AsyncDetector for BlockingDetector<D> {
+    async fn detect(&self, image: Arc<DynamicImage>) -> Result<Vec<Face>> {
+        let inner = Arc::clone(&self.inner);
+        tokio::task::spawn_blocking(move || inner.detect(&image))
+            .await
+            .map_err(|e| {
+                tracing::error!("Blocking detection task failed: {}", e);
+                FaceDetectionError::InternalError
+            })?
+    }
+
+    fn model_version(&self) -> &str {
+        self.inner.model_version()
+    }
+}
+
+/// Runs another backend through a [`CircuitBreaker`].
+pub struct BreakerDetector {
+    /// The guarded backend.
+    inner: Arc<dyn AsyncDetector>,
+    /// Breaker counting the guarded backend's failures.
+    breaker: CircuitBreaker,
+}
+
+impl BreakerDetector {
+    /// Guards `inner` with `breaker`.
+    pub fn new(inner: Arc<dyn AsyncDetector>, breaker: CircuitBreaker) -> Self {
+        Self { inner, breaker }
+    }
+}
+
+#[async_trait]
+impl AsyncDetector for BreakerDetector {
+    async fn detect(&self, image: Arc<DynamicImage>) -> Result<Vec<Face>> {
+        self.breaker.call(self.inner.detect(image)).await
+    }
+
+    fn model_version(&self) -> &str {
+        self.inner.model_version()
+    }
+}
+
+/// Model version reported by [`BenchDetector`].
+pub const BENCH_MODEL_VERSION: &str = concat!("bench-", env!("CARGO_PKG_VERSION"));
+
+/// Returns a canned face list instantly, whatever the image.
+///
+/// Only meant for benchmarking serialization, encoding and network overhead
+/// in isolation; results have nothing to do with the uploaded images.
+#[derive(Debug, Clone)]
+pub struct BenchDetector {
+    /// Faces returned for every image.
+    faces: Vec<Face>,
+}
+
+impl BenchDetector {
+    /// Creates a detector returning `faces` for every image.
+    pub fn new(faces: Vec<Face>) -> Self {
+        Self { faces }
+    }
+}
+
+impl Default for BenchDetector {
+    fn default() -> Self {
+        Self::new(vec![
+            Face::new(40, 40, 80, 80, 0.95),
+            Face::new(160, 40, 80, 80, 0.9),
+        ])
+    }
+}
+
+#[async_trait]
+impl AsyncDetector for BenchDetector {
+    async fn detect(&self, _image: Arc<DynamicImage>) -> Result<Vec<Face>> {
+        Ok(self.faces.clone())
+    }
+
+    fn model_version(&self) -> &str {
+        BENCH_MODEL_VERSION
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_blocking_adapter_matches_sync_detector() {
+        let detector = FaceDetector::builder().mock_confidences(vec![0.95, 0.3]).build().unwrap();
+        let image = Arc::new(DynamicImage::new_rgb8(500, 500));
+        let backend = BlockingDetector::new(detector.clone());
+
+        // The adapter returns raw candidates, the sub-threshold one included
+        let candidates = backend.detect(Arc::clone(&image)).await.unwrap();
+        assert_eq!(candidates.len(), 2);
+
+        let expected = detector.detect_image_with_rejections(&image).unwrap();
+        let result = detector.detect_with(&backend, &image, true).await.unwrap();
+
+        let boxes = |faces: &[Face]| faces.iter().map(|face| face.bbox).collect::<Vec<_>>();
+        assert_eq!(result.faces.len(), 1);
+        assert_eq!(boxes(&result.faces), boxes(&expected.faces));
+        assert_eq!(result.rejected.unwrap().len(), 1);
+        assert_eq!(result.model_version, expected.model_version);
+    }
+
+    #[tokio::test]
+    async fn test_detector_filters_apply_to_async_backend_faces() {
+        let backend = BenchDetector::new(vec![
+            Face::new(0, 0, 40, 40, 0.9),
+            Face::new(0, 0, 40, 40, 0.9),
+            Face::new(100, 100, 40, 40, 0.2),
+            Face::new(200, 100, 200, 10, 0.9),
+        ]);
+        let detector = FaceDetector::builder().aspect_ratio_range(0.5, 2.0).build().unwrap();
+        let image = Arc::new(DynamicImage::new_rgb8(500, 500));
+
+        let result = detector.detect_with(&backend, &image, true).await.unwrap();
+
+        assert_eq!(result.total_faces, 1);
+        assert_eq!(result.faces[0].bbox, Face::new(0, 0, 40, 40, 0.9).bbox);
+        assert!(result.faces[0].truncated);
+        assert_eq!(result.rejected.unwrap().len(), 3);
+        assert_eq!(result.model_version, BENCH_MODEL_VERSION);
+    }
+
+    #[test]
+    fn test_backends_report_model_version() {
+        let detector = FaceDetector::new().unwrap();
+        assert!(!Detector::model_version(&detector).is_empty());
+        assert!(FaceDetector::MODEL_VERSION.starts_with("mock-"));
+        assert_eq!(BlockingDetector::new(detector).model_version(), crate::detector::MOCK_MODEL_VERSION);
+        assert_eq!(BenchDetector::default().model_version(), BENCH_MODEL_VERSION);
+    }
+
+    #[tokio::test]
+    async fn test_bench_detector_returns_canned_faces_instantly() {
+        let canned = vec![Face::new(1, 2, 3, 4, 0.5)];
+        let detector = BenchDetector::new(canned.clone());
+        let image = Arc::new(DynamicImage::new_rgb8(4000, 4000));
+
+        let start = std::time::Instant::now();
+        let faces = detector.detect(image).await.unwrap();
+
+        assert!(start.elapsed() < std::time::Duration::from_millis(50));
+        assert_eq!(faces.len(), 1);
+        assert_eq!(faces[0].bbox, canned[0].bbox);
+        assert_eq!(faces[0].confidence, 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_blocking_adapter_passes_errors_through() {
+        struct Failing;
+        impl Detector for Failing {
+            fn detect(&self, _image: &DynamicImage) -> Result<Vec<Face>> {
+                Err(FaceDetectionError::DetectionFailed)
+            }
+
+            fn model_version(&self) -> &str {
+                "failing"
+            }
+        }
+
+        let result = BlockingDetector::new(Failing)
+            .detect(Arc::new(DynamicImage::new_rgb8(10, 10)))
+            .await;
+        assert!(matches!(result, Err(FaceDetectionError::DetectionFailed)));
+    }
+
+    #[tokio::test]
+    async fn test_breaker_short_circuits_failing_backend() {
+        use actix_web::ResponseError;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct Failing(AtomicUsize);
+
+        #[async_trait]
+        impl AsyncDetector for Failing {
+            async fn detect(&self, _image: Arc<DynamicImage>) -> Result<Vec<Face>> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Err(FaceDetectionError::DetectionFailed)
+            }
+
+            fn model_version(&self) -> &str {
+                "failing"
+            }
+        }
+
+        let failing = Arc::new(Failing(AtomicUsize::new(0)));
+        let breaker = CircuitBreaker::new(2, std::time::Duration::from_secs(60));
+        let backend = BreakerDetector::new(failing.clone(), breaker);
+        let image = Arc::new(DynamicImage::new_rgb8(500, 500));
+
+        for _ in 0..2 {
+            let result = backend.detect(Arc::clone(&image)).await;
+            assert!(matches!(result, Err(FaceDetectionError::DetectionFailed)));
+        }
+
+        // The pipeline surfaces the open breaker; the backend is no longer invoked
+        let detector = FaceDetector::new().unwrap();
+        let error = detector.detect_with(&backend, &image, false).await.unwrap_err();
+        assert_eq!(error.error_response().status(), 503);
+        assert!(error.error_response().headers().contains_key("retry-after"));
+        assert_eq!(failing.0.load(Ordering::SeqCst), 2);
+        assert_eq!(backend.model_version(), "failing");
+    }
+}
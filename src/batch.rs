@@ -0,0 +1,114 @@
+//! Batch face detection with per-item timeouts.
+//!
+//! Every image in a batch runs on its own blocking task under its own copy
+//! of the detection timeout, so one pathological image only costs its own
+//! slot: it is reported as timed out while the rest of the batch completes.
+
+use crate::error::Result;
+use crate::types::{BatchItem, BatchItemStatus, BatchResponse, DetectionResult};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Maximum number of images accepted in one batch.
+pub const MAX_BATCH_SIZE: usize = 32;
+
+/// Runs `detect` on every item concurrently, each bounded by `timeout`.
+///
+/// Items keep their request order in the response. A timed-out detection
+/// cannot be cancelled and keeps running on the blocking pool; only its
+/// result is discarded.
+pub async fn detect_batch<T, F>(items: Vec<T>, timeout: Duration, detect: F) -> BatchResponse
+where
+    T: Send + 'static,
+    F: Fn(T) -> Result<DetectionResult> + Send + Sync + 'static,
+{
+    let detect = Arc::new(detect);
+
+    // Start every item before awaiting any, so they run side by side
+    let tasks: Vec<_> = items
+        .into_iter()
+        .map(|item| {
+            let detect = Arc::clone(&detect);
+            tokio::spawn(async move {
+                let work = tokio::task::spawn_blocking(move || detect(item));
+                tokio::time::timeout(timeout, work).await
+            })
+        })
+        .collect();
+
+    let mut batch_items = Vec::with_capacity(tasks.len());
+    for (index, task) in tasks.into_iter().enumerate() {
+        let (status, result, error) = match task.await {
+            Ok(Ok(Ok(Ok(result)))) => (BatchItemStatus::Ok, Some(result), None),
+            Ok(Ok(Ok(Err(e)))) => (BatchItemStatus::Failed, None, Some(e.to_string())),
+            Ok(Err(_elapsed)) => (
+                BatchItemStatus::TimedOut,
+                None,
+                Some(format!("Detection timed out after {:?}", timeout)),
+            ),
+            Ok(Ok(Err(join_error))) | Err(join_error) => {
+                tracing::error!("Batch item {} panicked: {}", index, join_error);
+                (BatchItemStatus::Failed, None, Some("Internal server error".to_string()))
+            }
+        };
+
+        if status != BatchItemStatus::Ok {
+            tracing::warn!("Batch item {} did not complete: {:?}", index, error);
+        }
+        batch_items.push(BatchItem { index, status, result, error });
+    }
+
+    let partial = batch_items.iter().any(|item| item.status != BatchItemStatus::Ok);
+    BatchResponse { items: batch_items, partial }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::FaceDetectionError;
+    use crate::types::Face;
+
+    /// Mock detector that stalls on the image named "slow" and rejects "bad".
+    fn mock_detect(name: &'static str) -> Result<DetectionResult> {
+        match name {
+            "slow" => {
+                std::thread::sleep(Duration::from_millis(500));
+                Ok(DetectionResult::new(Vec::new(), 500))
+            }
+            "bad" => Err(FaceDetectionError::InvalidImageData),
+            _ => Ok(DetectionResult::new(vec![Face::new(1, 2, 3, 4, 0.9)], 1)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_item_times_out_while_others_complete() {
+        let started = std::time::Instant::now();
+        let response = detect_batch(
+            vec!["a", "slow", "b", "bad"],
+            Duration::from_millis(100),
+            mock_detect,
+        )
+        .await;
+
+        assert!(started.elapsed() < Duration::from_millis(400));
+        assert!(response.partial);
+
+        let statuses: Vec<_> = response.items.iter().map(|item| item.status).collect();
+        assert_eq!(
+            statuses,
+            [BatchItemStatus::Ok, BatchItemStatus::TimedOut, BatchItemStatus::Ok, BatchItemStatus::Failed]
+        );
+        assert_eq!(response.items[0].result.as_ref().unwrap().total_faces, 1);
+        assert!(response.items[1].result.is_none());
+        assert!(response.items[1].error.as_ref().unwrap().contains("timed out"));
+        assert!(response.items.iter().enumerate().all(|(i, item)| item.index == i));
+    }
+
+    #[tokio::test]
+    async fn test_complete_batch_is_not_partial() {
+        let response = detect_batch(vec!["a", "b"], Duration::from_secs(5), mock_detect).await;
+
+        assert!(!response.partial);
+        assert!(response.items.iter().all(|item| item.status == BatchItemStatus::Ok));
+    }
+}
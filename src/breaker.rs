@@ -0,0 +1,252 @@
+//! Circuit breaker for detection backend calls.
+//!
+//! When the detection backend is a remote inference service that goes down,
+//! requests should fail fast instead of piling up behind timeouts. The
+//! breaker opens after a run of consecutive backend failures, rejects calls
+//! with `BackendUnavailable` (503 with `Retry-After`) during a cooldown, then
+//! lets a single trial call through: success closes it again, failure
+//! reopens it for another cooldown.
+//!
+//! The server wraps whichever detection backend is configured in a
+//! [`BreakerDetector`](crate::backend::BreakerDetector), so the breaker does
+//! not depend on the backend implementation.
+
+use crate::error::{FaceDetectionError, Result};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// State of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Calls pass through; failures are being counted.
+    Closed,
+    /// Calls are rejected until the cooldown ends.
+    Open,
+    /// The cooldown ended; one trial call is allowed through.
+    HalfOpen,
+}
+
+/// Mutable breaker state guarded by the mutex.
+#[derive(Debug)]
+struct Inner {
+    /// Consecutive backend failures while closed.
+    consecutive_failures: u32,
+    /// When the breaker opened, if it is open or half-open.
+    opened_at: Option<Instant>,
+    /// Whether a half-open trial call is in flight.
+    trial_in_flight: bool,
+}
+
+/// Opens after consecutive backend failures and fails fast while open.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    /// Consecutive failures that open the breaker.
+    failure_threshold: u32,
+    /// How long the breaker stays open before allowing a trial call.
+    cooldown: Duration,
+    /// Current state.
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    /// Creates a closed breaker that opens after `failure_threshold`
+    /// consecutive failures and stays open for `cooldown`.
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            inner: Mutex::new(Inner {
+                consecutive_failures: 0,
+                opened_at: None,
+                trial_in_flight: false,
+            }),
+        }
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> BreakerState {
+        let inner = self.lock();
+        match inner.opened_at {
+            None => BreakerState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.cooldown => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Awaits `backend` unless the breaker is open, recording the outcome.
+    ///
+    /// Only errors that indicate the backend itself is unhealthy (see
+    /// [`FaceDetectionError::is_backend_failure`]) count towards opening the
+    /// breaker; bad input does not. A call dropped before it completes
+    /// records no outcome, but frees the trial slot if it held it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BackendUnavailable` without polling `backend` while the
+    /// breaker is open, otherwise whatever `backend` returns.
+    pub async fn call<T>(&self, backend: impl Future<Output = Result<T>>) -> Result<T> {
+        let is_trial = self.acquire()?;
+        let mut admission = Admission { breaker: self, is_trial, completed: false };
+        let result = backend.await;
+        admission.completed = true;
+        self.record(result.as_ref().is_err_and(FaceDetectionError::is_backend_failure));
+        result
+    }
+
+    /// Admits a call, returning whether it is the half-open trial, or
+    /// rejects it with the time left until the next trial.
+    fn acquire(&self) -> Result<bool> {
+        let mut inner = self.lock();
+        let Some(opened_at) = inner.opened_at else {
+            return Ok(false);
+        };
+
+        let elapsed = opened_at.elapsed();
+        if elapsed >= self.cooldown && !inner.trial_in_flight {
+            inner.trial_in_flight = true;
+            return Ok(true);
+        }
+
+        let remaining = self.cooldown.saturating_sub(elapsed);
+        Err(FaceDetectionError::BackendUnavailable {
+            retry_after_secs: remaining.as_secs_f64().ceil().max(1.0) as u64,
+        })
+    }
+
+    /// Updates the state after an admitted call.
+    fn record(&self, failed: bool) {
+        let mut inner = self.lock();
+        let was_trial = std::mem::take(&mut inner.trial_in_flight);
+
+        if !failed {
+            inner.consecutive_failures = 0;
+            inner.opened_at = None;
+            return;
+        }
+
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        if was_trial || inner.consecutive_failures >= self.failure_threshold {
+            tracing::warn!(
+                "Detection backend failed {} times in a row; rejecting calls for {:?}",
+                inner.consecutive_failures,
+                self.cooldown
+            );
+            inner.opened_at = Some(Instant::now());
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// An admitted call; frees the trial slot if dropped before completing, so
+/// a cancelled trial does not keep the breaker open forever.
+struct Admission<'a> {
+    /// Breaker that admitted the call.
+    breaker: &'a CircuitBreaker,
+    /// Whether the call is the half-open trial.
+    is_trial: bool,
+    /// Whether the call ran to completion.
+    completed: bool,
+}
+
+impl Drop for Admission<'_> {
+    fn drop(&mut self) {
+        if self.is_trial && !self.completed {
+            self.breaker.lock().trial_in_flight = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::ResponseError;
+    use std::cell::Cell;
+
+    async fn fail() -> Result<()> {
+        Err(FaceDetectionError::DetectionFailed)
+    }
+
+    async fn succeed() -> Result<()> {
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_opens_after_threshold_and_short_circuits() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(30));
+        let calls = Cell::new(0);
+        let backend = || async {
+            calls.set(calls.get() + 1);
+            fail().await
+        };
+
+        for _ in 0..3 {
+            assert!(matches!(breaker.call(backend()).await, Err(FaceDetectionError::DetectionFailed)));
+        }
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        let error = breaker.call(backend()).await.unwrap_err();
+        assert!(matches!(error, FaceDetectionError::BackendUnavailable { retry_after_secs: 1..=30 }));
+        assert_eq!(calls.get(), 3);
+
+        let response = error.error_response();
+        assert_eq!(response.status(), 503);
+        assert!(response.headers().contains_key("retry-after"));
+    }
+
+    #[tokio::test]
+    async fn test_success_resets_failure_count() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(30));
+
+        assert!(breaker.call(fail()).await.is_err());
+        assert!(breaker.call(succeed()).await.is_ok());
+        assert!(breaker.call(fail()).await.is_err());
+
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_do_not_open_breaker() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(30));
+
+        let result: Result<()> = breaker.call(async { Err(FaceDetectionError::InvalidImageData) }).await;
+        assert!(result.is_err());
+
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_half_open_trial_closes_or_reopens() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(20));
+
+        assert!(breaker.call(fail()).await.is_err());
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+
+        // A failed trial reopens the breaker for another cooldown
+        assert!(matches!(breaker.call(fail()).await, Err(FaceDetectionError::DetectionFailed)));
+        assert!(matches!(breaker.call(fail()).await, Err(FaceDetectionError::BackendUnavailable { .. })));
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(breaker.call(succeed()).await.is_ok());
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_trial_frees_the_trial_slot() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(20));
+
+        assert!(breaker.call(fail()).await.is_err());
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        // The trial never completes and is dropped, like a disconnected request
+        let trial = breaker.call(std::future::pending::<Result<()>>());
+        assert!(tokio::time::timeout(Duration::from_millis(10), trial).await.is_err());
+
+        assert!(breaker.call(succeed()).await.is_ok());
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
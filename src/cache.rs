@@ -0,0 +1,243 @@
+//! Shared cache of decoded images.
+//!
+//! Clients often upload an image for detection and then send the same bytes
+//! back to crop the detected faces. [`DecodeCache`] keys decoded images by
+//! the SHA-256 of their encoded bytes so the second request reuses the
+//! decoded buffer instead of decoding it again. A collision would hand one
+//! client another client's image, so the key must be collision resistant. It is shared with handlers as
+//! `web::Data<DecodeCache>` and bounded by both entry count and decoded size.
+
+use crate::error::Result;
+use image::DynamicImage;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Identifies encoded image bytes by their SHA-256 digest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct CacheKey([u8; 32]);
+
+impl CacheKey {
+    fn of(bytes: &[u8]) -> Self {
+        Self(Sha256::digest(bytes).into())
+    }
+}
+
+/// A cached decoded image.
+#[derive(Debug)]
+struct CacheEntry {
+    /// The decoded image.
+    image: Arc<DynamicImage>,
+    /// Size of the decoded pixel buffer in bytes.
+    size: usize,
+    /// Tick of the most recent lookup, for least-recently-used eviction.
+    last_used: u64,
+}
+
+/// Mutable cache state guarded by the mutex.
+#[derive(Debug, Default)]
+struct CacheState {
+    /// Cached images by key.
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Sum of the sizes of all cached images.
+    total_size: usize,
+    /// Monotonic lookup counter.
+    tick: u64,
+}
+
+/// Bounded least-recently-used cache of decoded images.
+#[derive(Debug)]
+pub struct DecodeCache {
+    /// Cached entries.
+    state: Mutex<CacheState>,
+    /// Maximum number of cached images; `0` disables caching.
+    max_entries: usize,
+    /// Maximum total size of cached pixel buffers in bytes.
+    max_bytes: usize,
+    /// Number of times the decode function was invoked.
+    decodes: AtomicU64,
+}
+
+impl DecodeCache {
+    /// Creates an empty cache holding at most `max_entries` images whose
+    /// decoded buffers total at most `max_bytes`.
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            state: Mutex::new(CacheState::default()),
+            max_entries,
+            max_bytes,
+            decodes: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the decoded image for `bytes`, calling `decode` only on a miss.
+    ///
+    /// The second element is `true` when the image came from the cache.
+    /// Images larger than the whole cache are decoded but not stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from `decode`; failures are not cached.
+    pub fn get_or_decode<F>(&self, bytes: &[u8], decode: F) -> Result<(Arc<DynamicImage>, bool)>
+    where
+        F: FnOnce(&[u8]) -> Result<DynamicImage>,
+    {
+        let key = CacheKey::of(bytes);
+
+        if let Some(image) = self.lookup(key) {
+            return Ok((image, true));
+        }
+
+        // Decode without holding the lock; a concurrent miss may decode the same bytes too
+        self.decodes.fetch_add(1, Ordering::Relaxed);
+        let image = Arc::new(decode(bytes)?);
+        self.insert(key, Arc::clone(&image));
+
+        Ok((image, false))
+    }
+
+    /// Returns how many times a decode function was invoked.
+    pub fn decode_count(&self) -> u64 {
+        self.decodes.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of cached images.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Returns `true` if no images are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lookup(&self, key: CacheKey) -> Option<Arc<DynamicImage>> {
+        let mut state = self.lock();
+        state.tick += 1;
+        let tick = state.tick;
+
+        state.entries.get_mut(&key).map(|entry| {
+            entry.last_used = tick;
+            Arc::clone(&entry.image)
+        })
+    }
+
+    fn insert(&self, key: CacheKey, image: Arc<DynamicImage>) {
+        let size = image.as_bytes().len();
+        if self.max_entries == 0 || size > self.max_bytes {
+            return;
+        }
+
+        let mut state = self.lock();
+        state.tick += 1;
+        let last_used = state.tick;
+
+        if let Some(previous) = state.entries.insert(key, CacheEntry { image, size, last_used }) {
+            state.total_size -= previous.size;
+        }
+        state.total_size += size;
+
+        while state.entries.len() > self.max_entries || state.total_size > self.max_bytes {
+            let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key)
+            else {
+                break;
+            };
+            if let Some(evicted) = state.entries.remove(&oldest) {
+                state.total_size -= evicted.size;
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        // The state stays consistent even if a holder panicked
+        self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    fn decode_solid(bytes: &[u8]) -> Result<DynamicImage> {
+        Ok(DynamicImage::ImageRgb8(RgbImage::new(bytes.len() as u32, 1)))
+    }
+
+    #[test]
+    fn test_hit_skips_decode() {
+        let cache = DecodeCache::new(4, 1024);
+
+        let (first, hit) = cache.get_or_decode(b"abc", decode_solid).unwrap();
+        assert!(!hit);
+        let (second, hit) = cache.get_or_decode(b"abc", decode_solid).unwrap();
+        assert!(hit);
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.decode_count(), 1);
+    }
+
+    #[test]
+    fn test_key_is_content_digest() {
+        let key = CacheKey::of(b"abc");
+
+        assert_eq!(key.0[..4], [0xba, 0x78, 0x16, 0xbf]);
+        assert_ne!(CacheKey::of(b"abd"), key);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used_entry() {
+        let cache = DecodeCache::new(2, 1024);
+
+        cache.get_or_decode(b"a", decode_solid).unwrap();
+        cache.get_or_decode(b"bb", decode_solid).unwrap();
+        cache.get_or_decode(b"a", decode_solid).unwrap();
+        cache.get_or_decode(b"ccc", decode_solid).unwrap();
+        assert_eq!(cache.len(), 2);
+
+        // "bb" was least recently used and must be decoded again
+        assert!(cache.get_or_decode(b"a", decode_solid).unwrap().1);
+        assert!(!cache.get_or_decode(b"bb", decode_solid).unwrap().1);
+        assert_eq!(cache.decode_count(), 4);
+    }
+
+    #[test]
+    fn test_respects_size_bound() {
+        // Each byte of input decodes to 3 bytes of RGB pixels
+        let cache = DecodeCache::new(10, 12);
+
+        cache.get_or_decode(b"aa", decode_solid).unwrap();
+        cache.get_or_decode(b"bb", decode_solid).unwrap();
+        cache.get_or_decode(b"cc", decode_solid).unwrap();
+        assert_eq!(cache.len(), 2);
+
+        cache.get_or_decode(b"toolarge", decode_solid).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.get_or_decode(b"toolarge", decode_solid).unwrap().1);
+    }
+
+    #[test]
+    fn test_zero_entries_disables_caching() {
+        let cache = DecodeCache::new(0, 1024);
+
+        cache.get_or_decode(b"abc", decode_solid).unwrap();
+        cache.get_or_decode(b"abc", decode_solid).unwrap();
+
+        assert!(cache.is_empty());
+        assert_eq!(cache.decode_count(), 2);
+    }
+
+    #[test]
+    fn test_failed_decode_is_not_cached() {
+        let cache = DecodeCache::new(4, 1024);
+
+        let failed = cache.get_or_decode(b"abc", |_| Err(crate::error::FaceDetectionError::InvalidImageData));
+        assert!(failed.is_err());
+        assert!(cache.is_empty());
+        assert!(!cache.get_or_decode(b"abc", decode_solid).unwrap().1);
+    }
+}
@@ -0,0 +1,850 @@
+//! Application configuration for the face detection service.
+//! 
+//! Settings are read from environment variables with sensible defaults,
+//! and shared with request handlers as `web::Data<AppConfig>`.
+
+use crate::breaker::CircuitBreaker;
+use crate::detection::DimensionLimits;
+use crate::detector::{DetectorConfig, FaceDetector};
+use crate::error::{config_error, Result};
+use crate::types::CoordinateFormat;
+use serde_json::Value;
+use std::env;
+use tracing::Level;
+
+/// Application configuration.
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    /// Server port.
+    pub port: u16,
+    /// Log level.
+    pub log_level: Level,
+    /// Maximum file upload size in bytes.
+    pub max_file_size: usize,
+    /// Seconds an upload may stall without receiving data before it is aborted.
+    pub upload_idle_timeout_secs: u64,
+    /// Number of HTTP worker threads.
+    pub workers: usize,
+    /// Keep-alive duration for idle client connections, in seconds.
+    pub keep_alive_secs: u64,
+    /// PEM certificate chain served over HTTPS; TLS is off unless set
+    /// together with `tls_key_path`.
+    pub tls_cert_path: Option<String>,
+    /// PEM private key for `tls_cert_path`.
+    pub tls_key_path: Option<String>,
+    /// HTTP methods allowed for cross-origin requests.
+    pub cors_allowed_methods: Vec<String>,
+    /// Response headers readable by cross-origin scripts.
+    pub cors_expose_headers: Vec<String>,
+    /// Seconds browsers may cache a CORS preflight response.
+    pub cors_max_age_secs: usize,
+    /// Whether support endpoints under `/api/debug` are served.
+    pub enable_debug_endpoints: bool,
+    /// Whether a summary of each request is logged at debug level.
+    pub log_requests: bool,
+    /// Fraction of successful requests given an access log line; errors
+    /// are always logged.
+    pub log_sample_rate: f64,
+    /// Whether `POST /api/scan` may run detection over server-local directories.
+    pub enable_local_scan: bool,
+    /// Directory that local scans are confined to.
+    pub scan_root: String,
+    /// Whether detection is replaced by canned results for load testing.
+    pub bench_mode: bool,
+    /// Decimal places detected face confidences are rounded to.
+    pub confidence_precision: u32,
+    /// Whether detected box coordinates are rounded to whole pixels.
+    pub coordinate_format: CoordinateFormat,
+    /// Backend persisted blobs are stored in.
+    pub storage_backend: StorageBackend,
+    /// Directory used by the local storage backend.
+    pub storage_dir: String,
+    /// Bucket used by the S3 storage backend.
+    pub s3_bucket: Option<String>,
+    /// Maximum accepted image width in pixels.
+    pub max_image_width: u32,
+    /// Maximum accepted image height in pixels.
+    pub max_image_height: u32,
+    /// Maximum accepted image size in megapixels.
+    pub max_megapixels: f64,
+    /// Seconds a detection may take, including fetching remote images.
+    pub detection_timeout_secs: u64,
+    /// Times a remote image fetch is retried after a transient failure.
+    pub fetch_max_retries: u32,
+    /// Delay before the first fetch retry in milliseconds, doubled per retry.
+    pub fetch_backoff_ms: u64,
+    /// Maximum number of decoded images kept in the decode cache; `0` disables it.
+    pub decode_cache_max_entries: usize,
+    /// Maximum total size of decoded images in the decode cache, in bytes.
+    pub decode_cache_max_bytes: usize,
+    /// Consecutive detection backend failures that open the circuit breaker; `0` disables it.
+    pub breaker_failure_threshold: u32,
+    /// Seconds the circuit breaker stays open before trying the backend again.
+    pub breaker_cooldown_secs: u64,
+    /// Whether detection runs on a grayscale copy of each image.
+    pub grayscale_detection: bool,
+    /// Whether detection retries on rotated copies when no face is found.
+    pub auto_orient: bool,
+    /// JSON file with detection parameters applied at startup.
+    pub detector_config_path: Option<String>,
+    /// Longest side images are downscaled to before detection; `0` disables downscaling.
+    pub max_detection_dimension: u32,
+    /// Images of at most this many megapixels are detected at native resolution.
+    pub downscale_min_megapixels: f64,
+    /// Longest side annotated images are downscaled to in responses; `0` disables the cap.
+    /// Crops are always returned at native resolution.
+    pub max_response_image_dimension: u32,
+    /// Uploads whose perceptual hashes differ in fewer bits than this are duplicates; `0` disables the check.
+    pub duplicate_hash_threshold: u32,
+    /// Number of recent upload hashes remembered for duplicate detection.
+    pub duplicate_history_size: usize,
+    /// Maximum number of faces accepted in one crop request.
+    pub max_crop_faces: usize,
+    /// Maximum number of detections running at once; `0` means unlimited.
+    pub max_concurrent_detections: usize,
+    /// Milliseconds a request waits for a free detection slot before it is shed with 503.
+    pub detection_queue_timeout_ms: u64,
+    /// Whether processed uploads are kept in storage under `retained/` for review.
+    pub retain_uploads: bool,
+    /// Seconds retained uploads are kept before the sweeper deletes them; `0` keeps them forever.
+    pub retained_upload_ttl_secs: u64,
+}
+
+/// Where persisted blobs are stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StorageBackend {
+    /// A directory on the local filesystem (default).
+    #[default]
+    Local,
+    /// An S3 bucket; requires the `s3` feature.
+    S3,
+}
+
+/// Substrings marking a config key as secret, matched case-insensitively.
+const SECRET_KEY_MARKERS: &[&str] = &["key", "secret", "token", "password"];
+
+/// Placeholder substituted for secret config values.
+const REDACTED: &str = "[REDACTED]";
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            port: 8080,
+            log_level: Level::INFO,
+            max_file_size: 10 * 1024 * 1024, // 10MB
+            upload_idle_timeout_secs: 30,
+            workers: std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get),
+            keep_alive_secs: 5,
+            tls_cert_path: None,
+            tls_key_path: None,
+            cors_allowed_methods: vec!["GET".to_string(), "POST".to_string()],
+            cors_expose_headers: ["X-Face-Count", "X-Image-Width", "X-Image-Height", "X-Processing-Time-Ms", "X-Request-Id"]
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            cors_max_age_secs: 3600,
+            enable_debug_endpoints: false,
+            log_requests: false,
+            log_sample_rate: 1.0,
+            enable_local_scan: false,
+            scan_root: "scan".to_string(),
+            bench_mode: false,
+            confidence_precision: crate::types::DEFAULT_CONFIDENCE_PRECISION,
+            coordinate_format: CoordinateFormat::Integer,
+            storage_backend: StorageBackend::Local,
+            storage_dir: "storage".to_string(),
+            s3_bucket: None,
+            max_image_width: 16384,
+            max_image_height: 16384,
+            max_megapixels: 50.0,
+            detection_timeout_secs: 30,
+            fetch_max_retries: 2,
+            fetch_backoff_ms: 200,
+            decode_cache_max_entries: 16,
+            decode_cache_max_bytes: 256 * 1024 * 1024, // 256MB
+            breaker_failure_threshold: 5,
+            breaker_cooldown_secs: 30,
+            grayscale_detection: false,
+            auto_orient: false,
+            detector_config_path: None,
+            max_detection_dimension: 0,
+            downscale_min_megapixels: 0.0,
+            max_response_image_dimension: 0,
+            duplicate_hash_threshold: 6,
+            duplicate_history_size: 64,
+            max_crop_faces: 100,
+            max_concurrent_detections: 32,
+            detection_queue_timeout_ms: 100,
+            retain_uploads: false,
+            retained_upload_ttl_secs: 7 * 24 * 3600, // 7 days
+        }
+    }
+}
+
+impl AppConfig {
+    /// Loads configuration from environment variables.
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        
+        // Parse port
+        if let Ok(port_str) = env::var("PORT") {
+            if let Ok(port) = port_str.parse::<u16>() {
+                config.port = port;
+            }
+        }
+        
+        // Parse log level
+        if let Ok(log_level_str) = env::var("RUST_LOG") {
+            match log_level_str.to_lowercase().as_str() {
+                "error" => config.log_level = Level::ERROR,
+                "warn" => config.log_level = Level::WARN,
+                "info" => config.log_level = Level::INFO,
+                "debug" => config.log_level = Level::DEBUG,
+                "trace" => config.log_level = Level::TRACE,
+                _ => {}
+            }
+        }
+        
+        // Parse max file size
+        if let Ok(size_str) = env::var("MAX_FILE_SIZE") {
+            if let Ok(size) = size_str.parse::<usize>() {
+                config.max_file_size = size;
+            }
+        }
+        
+        // Parse upload idle timeout
+        if let Ok(timeout_str) = env::var("UPLOAD_IDLE_TIMEOUT_SECS") {
+            if let Ok(timeout) = timeout_str.parse::<u64>() {
+                config.upload_idle_timeout_secs = timeout;
+            }
+        }
+        
+        // Parse worker count
+        if let Ok(workers_str) = env::var("WORKERS") {
+            if let Ok(workers) = workers_str.parse::<usize>() {
+                config.workers = workers;
+            }
+        }
+        
+        // Parse keep-alive
+        if let Ok(keep_alive_str) = env::var("KEEP_ALIVE_SECS") {
+            if let Ok(keep_alive) = keep_alive_str.parse::<u64>() {
+                config.keep_alive_secs = keep_alive;
+            }
+        }
+        
+        // Parse TLS settings
+        if let Ok(cert_path) = env::var("TLS_CERT_PATH") {
+            config.tls_cert_path = Some(cert_path).filter(|path| !path.is_empty());
+        }
+        if let Ok(key_path) = env::var("TLS_KEY_PATH") {
+            config.tls_key_path = Some(key_path).filter(|path| !path.is_empty());
+        }
+        
+        // Parse CORS methods
+        if let Ok(methods_str) = env::var("CORS_ALLOWED_METHODS") {
+            config.cors_allowed_methods = methods_str
+                .split(',')
+                .map(|method| method.trim().to_uppercase())
+                .filter(|method| !method.is_empty())
+                .collect();
+        }
+        
+        // Parse CORS exposed headers and preflight cache duration
+        if let Ok(headers_str) = env::var("CORS_EXPOSE_HEADERS") {
+            config.cors_expose_headers = headers_str
+                .split(',')
+                .map(|header| header.trim().to_string())
+                .filter(|header| !header.is_empty())
+                .collect();
+        }
+        if let Ok(max_age_str) = env::var("CORS_MAX_AGE_SECS") {
+            if let Ok(max_age) = max_age_str.parse::<usize>() {
+                config.cors_max_age_secs = max_age;
+            }
+        }
+        
+        // Parse debug endpoints flag
+        if let Ok(flag_str) = env::var("ENABLE_DEBUG_ENDPOINTS") {
+            config.enable_debug_endpoints = matches!(flag_str.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        // Parse request logging flag
+        if let Ok(flag_str) = env::var("LOG_REQUESTS") {
+            config.log_requests = matches!(flag_str.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        // Parse access log sample rate
+        if let Ok(rate_str) = env::var("LOG_SAMPLE_RATE") {
+            if let Ok(rate) = rate_str.parse::<f64>() {
+                config.log_sample_rate = rate;
+            }
+        }
+        
+        // Parse local scan settings
+        if let Ok(flag_str) = env::var("ENABLE_LOCAL_SCAN") {
+            config.enable_local_scan = matches!(flag_str.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        if let Ok(scan_root) = env::var("SCAN_ROOT") {
+            config.scan_root = scan_root;
+        }
+        
+        // Parse benchmark mode
+        if let Ok(flag_str) = env::var("BENCH_MODE") {
+            config.bench_mode = matches!(flag_str.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        // Parse confidence precision
+        if let Ok(precision_str) = env::var("CONFIDENCE_PRECISION") {
+            if let Ok(precision) = precision_str.parse::<u32>() {
+                config.confidence_precision = precision;
+            }
+        }
+        
+        // Parse coordinate format
+        if let Ok(format_str) = env::var("COORDINATE_FORMAT") {
+            match format_str.to_lowercase().as_str() {
+                "integer" | "int" => config.coordinate_format = CoordinateFormat::Integer,
+                "float" => config.coordinate_format = CoordinateFormat::Float,
+                _ => {}
+            }
+        }
+        
+        // Parse storage settings
+        if let Ok(backend_str) = env::var("STORAGE_BACKEND") {
+            match backend_str.to_lowercase().as_str() {
+                "local" => config.storage_backend = StorageBackend::Local,
+                "s3" => config.storage_backend = StorageBackend::S3,
+                _ => {}
+            }
+        }
+        if let Ok(storage_dir) = env::var("STORAGE_DIR") {
+            config.storage_dir = storage_dir;
+        }
+        if let Ok(bucket) = env::var("S3_BUCKET") {
+            config.s3_bucket = Some(bucket);
+        }
+        
+        // Parse image dimension limits
+        if let Ok(width_str) = env::var("MAX_IMAGE_WIDTH") {
+            if let Ok(width) = width_str.parse::<u32>() {
+                config.max_image_width = width;
+            }
+        }
+        if let Ok(height_str) = env::var("MAX_IMAGE_HEIGHT") {
+            if let Ok(height) = height_str.parse::<u32>() {
+                config.max_image_height = height;
+            }
+        }
+        if let Ok(megapixels_str) = env::var("MAX_MEGAPIXELS") {
+            if let Ok(megapixels) = megapixels_str.parse::<f64>() {
+                config.max_megapixels = megapixels;
+            }
+        }
+        
+        // Parse detection timeout and fetch retry settings
+        if let Ok(timeout_str) = env::var("DETECTION_TIMEOUT_SECS") {
+            if let Ok(timeout) = timeout_str.parse::<u64>() {
+                config.detection_timeout_secs = timeout;
+            }
+        }
+        if let Ok(retries_str) = env::var("FETCH_MAX_RETRIES") {
+            if let Ok(retries) = retries_str.parse::<u32>() {
+                config.fetch_max_retries = retries;
+            }
+        }
+        if let Ok(backoff_str) = env::var("FETCH_BACKOFF_MS") {
+            if let Ok(backoff) = backoff_str.parse::<u64>() {
+                config.fetch_backoff_ms = backoff;
+            }
+        }
+        
+        // Parse decode cache bounds
+        if let Ok(entries_str) = env::var("DECODE_CACHE_MAX_ENTRIES") {
+            if let Ok(entries) = entries_str.parse::<usize>() {
+                config.decode_cache_max_entries = entries;
+            }
+        }
+        if let Ok(size_str) = env::var("DECODE_CACHE_MAX_BYTES") {
+            if let Ok(size) = size_str.parse::<usize>() {
+                config.decode_cache_max_bytes = size;
+            }
+        }
+        
+        // Parse detection circuit breaker settings
+        if let Ok(threshold_str) = env::var("BREAKER_FAILURE_THRESHOLD") {
+            if let Ok(threshold) = threshold_str.parse::<u32>() {
+                config.breaker_failure_threshold = threshold;
+            }
+        }
+        if let Ok(cooldown_str) = env::var("BREAKER_COOLDOWN_SECS") {
+            if let Ok(cooldown) = cooldown_str.parse::<u64>() {
+                config.breaker_cooldown_secs = cooldown;
+            }
+        }
+        
+        // Parse grayscale detection flag
+        if let Ok(grayscale_str) = env::var("GRAYSCALE_DETECTION") {
+            config.grayscale_detection = matches!(grayscale_str.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        // Parse auto-orientation flag
+        if let Ok(auto_orient_str) = env::var("AUTO_ORIENT") {
+            config.auto_orient = matches!(auto_orient_str.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        // Parse detector parameter file
+        if let Ok(path) = env::var("DETECTOR_CONFIG_PATH") {
+            config.detector_config_path = Some(path).filter(|path| !path.is_empty());
+        }
+        
+        // Parse detection downscale policy
+        if let Ok(dimension_str) = env::var("MAX_DETECTION_DIMENSION") {
+            if let Ok(dimension) = dimension_str.parse::<u32>() {
+                config.max_detection_dimension = dimension;
+            }
+        }
+        if let Ok(megapixels_str) = env::var("DOWNSCALE_MIN_MEGAPIXELS") {
+            if let Ok(megapixels) = megapixels_str.parse::<f64>() {
+                config.downscale_min_megapixels = megapixels;
+            }
+        }
+        
+        // Parse response image size cap
+        if let Ok(dimension_str) = env::var("MAX_RESPONSE_IMAGE_DIMENSION") {
+            if let Ok(dimension) = dimension_str.parse::<u32>() {
+                config.max_response_image_dimension = dimension;
+            }
+        }
+        
+        // Parse duplicate upload detection settings
+        if let Ok(threshold_str) = env::var("DUPLICATE_HASH_THRESHOLD") {
+            if let Ok(threshold) = threshold_str.parse::<u32>() {
+                config.duplicate_hash_threshold = threshold;
+            }
+        }
+        if let Ok(history_str) = env::var("DUPLICATE_HISTORY_SIZE") {
+            if let Ok(history) = history_str.parse::<usize>() {
+                config.duplicate_history_size = history;
+            }
+        }
+        
+        // Parse crop request face limit
+        if let Ok(faces_str) = env::var("MAX_CROP_FACES") {
+            if let Ok(faces) = faces_str.parse::<usize>() {
+                config.max_crop_faces = faces;
+            }
+        }
+        
+        // Parse detection concurrency limits
+        if let Ok(max_str) = env::var("MAX_CONCURRENT_DETECTIONS") {
+            if let Ok(max) = max_str.parse::<usize>() {
+                config.max_concurrent_detections = max;
+            }
+        }
+        if let Ok(timeout_str) = env::var("DETECTION_QUEUE_TIMEOUT_MS") {
+            if let Ok(timeout) = timeout_str.parse::<u64>() {
+                config.detection_queue_timeout_ms = timeout;
+            }
+        }
+        
+        // Parse upload retention settings
+        if let Ok(flag_str) = env::var("RETAIN_UPLOADS") {
+            config.retain_uploads = matches!(flag_str.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        if let Ok(ttl_str) = env::var("RETAINED_UPLOAD_TTL_SECS") {
+            if let Ok(ttl) = ttl_str.parse::<u64>() {
+                config.retained_upload_ttl_secs = ttl;
+            }
+        }
+        
+        config
+    }
+
+    /// Builds the face detector, guarded by a circuit breaker unless disabled.
+    /// 
+    /// Parameters from the file at `detector_config_path`, if set, are
+    /// applied on top of the defaults.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns a `Configuration` error if the detector config file cannot be
+    /// loaded or the detector settings are invalid.
+    pub fn build_detector(&self) -> Result<FaceDetector> {
+        let mut builder = FaceDetector::builder()
+            .grayscale_detection(self.grayscale_detection)
+            .auto_orient(self.auto_orient)
+            .max_detection_dimension(self.max_detection_dimension)
+            .downscale_min_megapixels(self.downscale_min_megapixels)
+            .confidence_precision(self.confidence_precision)
+            .coordinate_format(self.coordinate_format);
+        if let Some(path) = &self.detector_config_path {
+            let detector_config = DetectorConfig::load(std::path::Path::new(path))?;
+            tracing::info!("Loaded detector config from {}: {:?}", path, detector_config);
+            builder = builder.with_detector_config(&detector_config);
+        }
+        builder.build()
+    }
+
+    /// Returns the circuit breaker guarding the detection backend, or `None`
+    /// if `breaker_failure_threshold` is zero.
+    pub fn build_breaker(&self) -> Option<CircuitBreaker> {
+        (self.breaker_failure_threshold > 0).then(|| {
+            CircuitBreaker::new(
+                self.breaker_failure_threshold,
+                std::time::Duration::from_secs(self.breaker_cooldown_secs),
+            )
+        })
+    }
+
+    /// Returns the image dimension limits.
+    pub fn dimension_limits(&self) -> DimensionLimits {
+        DimensionLimits {
+            max_width: self.max_image_width,
+            max_height: self.max_image_height,
+            max_megapixels: self.max_megapixels,
+        }
+    }
+
+    /// Returns the configuration as JSON with secret values redacted.
+    pub fn redacted(&self) -> Value {
+        // Built in parts, each small enough for the default `json!` recursion limit
+        let server = serde_json::json!({
+            "port": self.port,
+            "logLevel": self.log_level.to_string(),
+            "maxFileSize": self.max_file_size,
+            "uploadIdleTimeoutSecs": self.upload_idle_timeout_secs,
+            "workers": self.workers,
+            "keepAliveSecs": self.keep_alive_secs,
+            "tlsCertPath": self.tls_cert_path,
+            "tlsKeyPath": self.tls_key_path,
+            "corsAllowedMethods": self.cors_allowed_methods,
+            "corsExposeHeaders": self.cors_expose_headers,
+            "corsMaxAgeSecs": self.cors_max_age_secs,
+            "enableDebugEndpoints": self.enable_debug_endpoints,
+            "logRequests": self.log_requests,
+            "logSampleRate": self.log_sample_rate,
+            "enableLocalScan": self.enable_local_scan,
+            "scanRoot": self.scan_root,
+            "benchMode": self.bench_mode,
+        });
+        let storage = serde_json::json!({
+            "storageBackend": format!("{:?}", self.storage_backend).to_lowercase(),
+            "storageDir": self.storage_dir,
+            "s3Bucket": self.s3_bucket,
+            "maxImageWidth": self.max_image_width,
+            "maxImageHeight": self.max_image_height,
+            "maxMegapixels": self.max_megapixels,
+            "detectionTimeoutSecs": self.detection_timeout_secs,
+            "fetchMaxRetries": self.fetch_max_retries,
+            "fetchBackoffMs": self.fetch_backoff_ms,
+            "decodeCacheMaxEntries": self.decode_cache_max_entries,
+            "decodeCacheMaxBytes": self.decode_cache_max_bytes,
+            "breakerFailureThreshold": self.breaker_failure_threshold,
+            "breakerCooldownSecs": self.breaker_cooldown_secs,
+        });
+        let detection = serde_json::json!({
+            "confidencePrecision": self.confidence_precision,
+            "coordinateFormat": self.coordinate_format,
+            "grayscaleDetection": self.grayscale_detection,
+            "autoOrient": self.auto_orient,
+            "detectorConfigPath": self.detector_config_path,
+            "maxDetectionDimension": self.max_detection_dimension,
+            "downscaleMinMegapixels": self.downscale_min_megapixels,
+            "maxResponseImageDimension": self.max_response_image_dimension,
+            "duplicateHashThreshold": self.duplicate_hash_threshold,
+            "duplicateHistorySize": self.duplicate_history_size,
+            "maxCropFaces": self.max_crop_faces,
+            "maxConcurrentDetections": self.max_concurrent_detections,
+            "detectionQueueTimeoutMs": self.detection_queue_timeout_ms,
+            "retainUploads": self.retain_uploads,
+            "retainedUploadTtlSecs": self.retained_upload_ttl_secs,
+        });
+        let mut settings = serde_json::Map::new();
+        for part in [server, storage, detection] {
+            if let Value::Object(part) = part {
+                settings.extend(part);
+            }
+        }
+        let mut value = Value::Object(settings);
+        redact_secrets(&mut value);
+        value
+    }
+
+    /// Validates the configuration.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns a `Configuration` error describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        if self.workers < 1 {
+            return Err(config_error("WORKERS must be at least 1"));
+        }
+        
+        if let Some(method) = self
+            .cors_allowed_methods
+            .iter()
+            .find(|method| actix_web::http::Method::from_bytes(method.as_bytes()).is_err())
+        {
+            return Err(config_error(format!("CORS_ALLOWED_METHODS contains invalid method {}", method)));
+        }
+        
+        if let Some(header) = self
+            .cors_expose_headers
+            .iter()
+            .find(|header| actix_web::http::header::HeaderName::from_bytes(header.as_bytes()).is_err())
+        {
+            return Err(config_error(format!("CORS_EXPOSE_HEADERS contains invalid header {}", header)));
+        }
+        
+        if !(0.0..=1.0).contains(&self.log_sample_rate) {
+            return Err(config_error("LOG_SAMPLE_RATE must be between 0.0 and 1.0"));
+        }
+        
+        if self.max_crop_faces < 1 {
+            return Err(config_error("MAX_CROP_FACES must be at least 1"));
+        }
+        
+        if self.storage_backend == StorageBackend::S3 && self.s3_bucket.is_none() {
+            return Err(config_error("S3_BUCKET is required when STORAGE_BACKEND=s3"));
+        }
+        
+        if self.tls_cert_path.is_some() != self.tls_key_path.is_some() {
+            return Err(config_error("TLS_CERT_PATH and TLS_KEY_PATH must be set together"));
+        }
+        
+        Ok(())
+    }
+}
+
+/// Replaces the values of secret-looking keys in a JSON object with a placeholder.
+pub fn redact_secrets(value: &mut Value) {
+    if let Value::Object(map) = value {
+        for (key, field) in map.iter_mut() {
+            let key = key.to_lowercase();
+            if SECRET_KEY_MARKERS.iter().any(|marker| key.contains(marker)) {
+                *field = Value::String(REDACTED.to_string());
+            } else {
+                redact_secrets(field);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_config_default() {
+        let config = AppConfig::default();
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.max_file_size, 10 * 1024 * 1024);
+        assert_eq!(config.upload_idle_timeout_secs, 30);
+    }
+
+    #[test]
+    fn test_app_config_from_env() {
+        std::env::set_var("PORT", "3000");
+        std::env::set_var("RUST_LOG", "debug");
+        
+        let config = AppConfig::from_env();
+        assert_eq!(config.port, 3000);
+        assert_eq!(config.log_level, Level::DEBUG);
+        
+        std::env::remove_var("PORT");
+        std::env::remove_var("RUST_LOG");
+    }
+
+    #[test]
+    fn test_app_config_upload_idle_timeout_from_env() {
+        std::env::set_var("UPLOAD_IDLE_TIMEOUT_SECS", "5");
+        
+        let config = AppConfig::from_env();
+        assert_eq!(config.upload_idle_timeout_secs, 5);
+        
+        std::env::remove_var("UPLOAD_IDLE_TIMEOUT_SECS");
+    }
+
+    #[test]
+    fn test_app_config_server_tuning_from_env() {
+        std::env::set_var("WORKERS", "3");
+        std::env::set_var("KEEP_ALIVE_SECS", "75");
+        
+        let config = AppConfig::from_env();
+        assert_eq!(config.workers, 3);
+        assert_eq!(config.keep_alive_secs, 75);
+        assert!(config.validate().is_ok());
+        
+        std::env::remove_var("WORKERS");
+        std::env::remove_var("KEEP_ALIVE_SECS");
+    }
+
+    #[test]
+    fn test_app_config_validation_rejects_zero_workers() {
+        let config = AppConfig {
+            workers: 0,
+            ..AppConfig::default()
+        };
+        assert!(config.validate().is_err());
+        
+        let default = AppConfig::default();
+        assert!(default.workers >= 1);
+        assert_eq!(default.keep_alive_secs, 5);
+        assert!(default.validate().is_ok());
+    }
+
+    #[test]
+    fn test_cors_settings_from_env() {
+        std::env::set_var("CORS_EXPOSE_HEADERS", "X-Face-Count, X-Trace");
+        std::env::set_var("CORS_MAX_AGE_SECS", "120");
+        
+        let config = AppConfig::from_env();
+        assert_eq!(config.cors_expose_headers, ["X-Face-Count", "X-Trace"]);
+        assert_eq!(config.cors_max_age_secs, 120);
+        assert!(config.validate().is_ok());
+        
+        std::env::remove_var("CORS_EXPOSE_HEADERS");
+        std::env::remove_var("CORS_MAX_AGE_SECS");
+        
+        let invalid = AppConfig {
+            cors_expose_headers: vec!["X Face".to_string()],
+            ..AppConfig::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_debug_endpoints_flag_from_env() {
+        assert!(!AppConfig::default().enable_debug_endpoints);
+        
+        std::env::set_var("ENABLE_DEBUG_ENDPOINTS", "true");
+        assert!(AppConfig::from_env().enable_debug_endpoints);
+        std::env::remove_var("ENABLE_DEBUG_ENDPOINTS");
+    }
+
+    #[test]
+    fn test_confidence_precision_from_env() {
+        assert_eq!(AppConfig::default().confidence_precision, 3);
+        
+        std::env::set_var("CONFIDENCE_PRECISION", "5");
+        assert_eq!(AppConfig::from_env().confidence_precision, 5);
+        std::env::remove_var("CONFIDENCE_PRECISION");
+    }
+
+    #[test]
+    fn test_coordinate_format_from_env() {
+        assert_eq!(AppConfig::default().coordinate_format, CoordinateFormat::Integer);
+        
+        std::env::set_var("COORDINATE_FORMAT", "Float");
+        assert_eq!(AppConfig::from_env().coordinate_format, CoordinateFormat::Float);
+        std::env::remove_var("COORDINATE_FORMAT");
+    }
+
+    #[test]
+    fn test_storage_settings_from_env() {
+        let default = AppConfig::default();
+        assert_eq!(default.storage_backend, StorageBackend::Local);
+        assert_eq!(default.storage_dir, "storage");
+        
+        let s3 = AppConfig {
+            storage_backend: StorageBackend::S3,
+            ..AppConfig::default()
+        };
+        assert!(s3.validate().is_err());
+        
+        let s3 = AppConfig {
+            s3_bucket: Some("faces".to_string()),
+            ..s3
+        };
+        assert!(s3.validate().is_ok());
+    }
+
+    #[test]
+    fn test_tls_paths_must_be_set_together() {
+        assert!(AppConfig::default().tls_cert_path.is_none());
+        
+        let cert_only = AppConfig {
+            tls_cert_path: Some("cert.pem".to_string()),
+            ..AppConfig::default()
+        };
+        assert!(cert_only.validate().is_err());
+        
+        let both = AppConfig {
+            tls_key_path: Some("key.pem".to_string()),
+            ..cert_only
+        };
+        assert!(both.validate().is_ok());
+    }
+
+    #[test]
+    fn test_build_detector_loads_detector_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("detector.json");
+        std::fs::write(&path, r#"{ "confidence_threshold": 0.8, "max_faces": 5 }"#).unwrap();
+        
+        let config = AppConfig {
+            detector_config_path: Some(path.to_string_lossy().into_owned()),
+            ..AppConfig::default()
+        };
+        let detector = config.build_detector().unwrap();
+        assert_eq!(detector.confidence_threshold(), 0.8);
+        assert_eq!(detector.max_faces(), Some(5));
+        
+        std::fs::write(&path, "not json").unwrap();
+        assert!(config.build_detector().is_err());
+        
+        let missing = AppConfig {
+            detector_config_path: Some(dir.path().join("missing.json").to_string_lossy().into_owned()),
+            ..AppConfig::default()
+        };
+        assert!(missing.build_detector().is_err());
+    }
+
+    #[test]
+    fn test_log_sample_rate_must_be_a_fraction() {
+        assert_eq!(AppConfig::default().log_sample_rate, 1.0);
+        
+        for (rate, valid) in [(0.0, true), (0.5, true), (1.5, false), (-0.1, false)] {
+            let config = AppConfig {
+                log_sample_rate: rate,
+                ..AppConfig::default()
+            };
+            assert_eq!(config.validate().is_ok(), valid, "rate {}", rate);
+        }
+    }
+
+    #[test]
+    fn test_dimension_limits_from_env() {
+        std::env::set_var("MAX_IMAGE_WIDTH", "4096");
+        std::env::set_var("MAX_MEGAPIXELS", "12.5");
+        
+        let limits = AppConfig::from_env().dimension_limits();
+        assert_eq!(limits.max_width, 4096);
+        assert_eq!(limits.max_height, 16384);
+        assert_eq!(limits.max_megapixels, 12.5);
+        
+        std::env::remove_var("MAX_IMAGE_WIDTH");
+        std::env::remove_var("MAX_MEGAPIXELS");
+    }
+
+    #[test]
+    fn test_redact_secrets() {
+        let mut value = serde_json::json!({
+            "port": 8080,
+            "apiKey": "hunter2",
+            "storage": { "accessToken": "abc", "bucket": "faces" },
+        });
+        redact_secrets(&mut value);
+        
+        assert_eq!(value["port"], 8080);
+        assert_eq!(value["apiKey"], REDACTED);
+        assert_eq!(value["storage"]["accessToken"], REDACTED);
+        assert_eq!(value["storage"]["bucket"], "faces");
+        
+        let config = AppConfig::default().redacted();
+        assert_eq!(config["port"], 8080);
+        assert_eq!(config["keepAliveSecs"], 5);
+    }
+}
@@ -0,0 +1,92 @@
+//! Near-duplicate detection for recent uploads.
+//!
+//! Each upload's perceptual hash is remembered in a small ring buffer. A new
+//! upload whose hash is within the configured Hamming distance of a recent
+//! one is reported as a duplicate of it. Shared with handlers as
+//! `web::Data<RecentUploads>`.
+
+use crate::detection::hamming_distance;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Ring buffer of recently uploaded image hashes.
+#[derive(Debug)]
+pub struct RecentUploads {
+    /// `(hash, upload id)` pairs, oldest first.
+    entries: Mutex<VecDeque<(u64, String)>>,
+    /// Number of uploads remembered.
+    capacity: usize,
+    /// Hashes closer than this many bits are duplicates.
+    threshold: u32,
+}
+
+impl RecentUploads {
+    /// Creates an empty buffer remembering `capacity` uploads, treating hashes
+    /// that differ in fewer than `threshold` bits as duplicates.
+    pub fn new(capacity: usize, threshold: u32) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            threshold,
+        }
+    }
+
+    /// Records an upload and returns the id of the closest recent duplicate,
+    /// preferring the newest upload on ties.
+    pub fn check_and_insert(&self, hash: u64, upload_id: &str) -> Option<String> {
+        let mut entries = self.entries.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        let duplicate_of = entries
+            .iter()
+            .rev()
+            .map(|(recent, id)| (hamming_distance(hash, *recent), id))
+            .filter(|(distance, _)| *distance < self.threshold)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, id)| id.clone());
+
+        if self.capacity > 0 {
+            if entries.len() == self.capacity {
+                entries.pop_front();
+            }
+            entries.push_back((hash, upload_id.to_string()));
+        }
+
+        duplicate_of
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_closest_recent_duplicate() {
+        let recent = RecentUploads::new(4, 5);
+
+        assert_eq!(recent.check_and_insert(0b1111_0000, "first"), None);
+        assert_eq!(recent.check_and_insert(u64::MAX, "unrelated"), None);
+        assert_eq!(recent.check_and_insert(0b1111_0011, "second").as_deref(), Some("first"));
+        assert_eq!(recent.check_and_insert(0b1111_0001, "third").as_deref(), Some("second"));
+    }
+
+    #[test]
+    fn test_threshold_is_exclusive() {
+        let recent = RecentUploads::new(4, 2);
+
+        recent.check_and_insert(0, "zero");
+        assert_eq!(recent.check_and_insert(0b11, "two bits"), None);
+        assert_eq!(recent.check_and_insert(0b100, "one bit").as_deref(), Some("zero"));
+    }
+
+    #[test]
+    fn test_forgets_oldest_upload() {
+        let recent = RecentUploads::new(2, 1);
+
+        recent.check_and_insert(1, "a");
+        recent.check_and_insert(2, "b");
+        recent.check_and_insert(3, "c");
+
+        assert_eq!(recent.check_and_insert(1, "a again"), None);
+        assert_eq!(recent.check_and_insert(3, "c again").as_deref(), Some("c"));
+    }
+}
@@ -3,15 +3,254 @@
 //! This module provides the core face detection capabilities, including
 //! detection algorithms, image processing, and result formatting.
 
-use crate::error::{FaceDetectionError, Result};
-use crate::types::{DetectionResult, Face};
-use image::DynamicImage;
+use crate::error::{decode_error, validation_error, FaceDetectionError, IoSnafu, Result};
+use crate::types::{BoundingBox, ChromaSubsampling, DetectionResult, DrawOptions, Face, OutputFormat, PadOffset, ResizeOptions};
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig, STANDARD};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+use image::{DynamicImage, GenericImageView, ImageDecoder, ImageFormat, Rgb, RgbImage, RgbaImage};
+use sha2::{Digest, Sha256};
+use snafu::ResultExt;
+use std::io::Cursor;
 use std::path::Path;
 use std::time::Instant;
 
 pub mod detector;
 pub use detector::FaceDetector;
 
+/// Input container formats accepted by the service.
+/// 
+/// This is the single source of truth for both the format sniffer and the
+/// formats advertised by `GET /api/formats`.
+pub const SUPPORTED_INPUT_FORMATS: &[ImageFormat] = &[
+    ImageFormat::Jpeg,
+    ImageFormat::Png,
+    ImageFormat::Gif,
+    ImageFormat::WebP,
+    ImageFormat::Bmp,
+    ImageFormat::Tiff,
+];
+
+/// Formats the service can encode returned images in.
+pub const SUPPORTED_OUTPUT_FORMATS: &[ImageFormat] = &[ImageFormat::Jpeg];
+
+/// Returns the lowercase public name of an image format, e.g. `"jpeg"`.
+pub fn format_name(format: ImageFormat) -> String {
+    format!("{:?}", format).to_lowercase()
+}
+
+/// Identifies the container format of encoded image bytes from their magic bytes.
+/// 
+/// # Arguments
+/// 
+/// * `bytes` - Encoded image data (only the header is inspected)
+/// 
+/// # Errors
+/// 
+/// Returns `FaceDetectionError::InvalidFileFormat` if the format is unknown or
+/// not in [`SUPPORTED_INPUT_FORMATS`].
+pub fn sniff_image_format(bytes: &[u8]) -> Result<ImageFormat> {
+    let format = image::guess_format(bytes).map_err(|_| FaceDetectionError::InvalidFileFormat {
+        format: "unknown".to_string(),
+    })?;
+    
+    if SUPPORTED_INPUT_FORMATS.contains(&format) {
+        Ok(format)
+    } else {
+        Err(FaceDetectionError::InvalidFileFormat {
+            format: format_name(format),
+        })
+    }
+}
+
+/// Decodes frame `frame` of a possibly multi-frame image.
+/// 
+/// Pages of multi-page TIFFs and frames of animated GIFs can be selected;
+/// frame 0 of any supported format decodes the image normally. TIFF pages
+/// are checked against `limits` before their pixels are decoded, since a
+/// header probe only sees the first page.
+/// 
+/// # Errors
+/// 
+/// Returns a validation error if `frame` is out of range, the format holds
+/// a single frame or the page exceeds `limits`, and `FaceDetectionError` if
+/// decoding fails.
+pub fn decode_frame(bytes: &[u8], frame: usize, limits: &DimensionLimits) -> Result<DynamicImage> {
+    let format = sniff_image_format(bytes)?;
+    
+    match format {
+        ImageFormat::Gif => decode_gif_frame(bytes, frame),
+        ImageFormat::Tiff => decode_tiff_page(bytes, frame, limits),
+        _ if frame == 0 => image::load_from_memory_with_format(bytes, format)
+            .map_err(decode_error),
+        _ => Err(frame_out_of_range(frame, 1)),
+    }
+}
+
+/// Builds the error for a frame index past the end of an image.
+fn frame_out_of_range(frame: usize, frame_count: usize) -> FaceDetectionError {
+    validation_error(format!(
+        "Frame {} is out of range: image has {} frame{}",
+        frame,
+        frame_count,
+        if frame_count == 1 { "" } else { "s" }
+    ))
+}
+
+/// Decodes one frame of an animated GIF.
+/// 
+/// Frames are decoded one at a time and dropped until the requested one, so
+/// long animations are never held in memory at once.
+fn decode_gif_frame(bytes: &[u8], frame: usize) -> Result<DynamicImage> {
+    use image::codecs::gif::GifDecoder;
+    use image::AnimationDecoder;
+    
+    let frames = GifDecoder::new(Cursor::new(bytes))
+        .map_err(decode_error)?
+        .into_frames();
+    
+    let mut frame_count = 0;
+    for decoded in frames {
+        let decoded = decoded.map_err(decode_error)?;
+        if frame_count == frame {
+            return Ok(DynamicImage::ImageRgba8(decoded.into_buffer()));
+        }
+        frame_count += 1;
+    }
+    Err(frame_out_of_range(frame, frame_count))
+}
+
+/// Decodes one page of a multi-page TIFF, rejecting pages beyond `limits`.
+fn decode_tiff_page(bytes: &[u8], page: usize, limits: &DimensionLimits) -> Result<DynamicImage> {
+    use tiff::decoder::{Decoder, DecodingResult};
+    use tiff::ColorType;
+    
+    let tiff_error = |e: tiff::TiffError| validation_error(format!("Invalid TIFF data: {}", e));
+    let mut decoder = Decoder::new(Cursor::new(bytes)).map_err(tiff_error)?;
+    
+    let mut page_count = 1;
+    while page_count <= page && decoder.more_images() {
+        decoder.next_image().map_err(tiff_error)?;
+        page_count += 1;
+    }
+    if page_count <= page {
+        return Err(frame_out_of_range(page, page_count));
+    }
+    
+    let (width, height) = decoder.dimensions().map_err(tiff_error)?;
+    check_dimensions(width, height, limits)?;
+    let color_type = decoder.colortype().map_err(tiff_error)?;
+    let buffer = match decoder.read_image().map_err(tiff_error)? {
+        DecodingResult::U8(buffer) => buffer,
+        _ => return Err(validation_error("Only 8-bit TIFF pages are supported")),
+    };
+    
+    let image = match color_type {
+        ColorType::Gray(8) => image::GrayImage::from_raw(width, height, buffer).map(DynamicImage::ImageLuma8),
+        ColorType::RGB(8) => RgbImage::from_raw(width, height, buffer).map(DynamicImage::ImageRgb8),
+        ColorType::RGBA(8) => image::RgbaImage::from_raw(width, height, buffer).map(DynamicImage::ImageRgba8),
+        other => return Err(validation_error(format!("Unsupported TIFF color type: {:?}", other))),
+    };
+    
+    image.ok_or_else(|| validation_error("TIFF page data does not match its dimensions"))
+}
+
+/// Upper bounds on the dimensions of images accepted for processing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DimensionLimits {
+    /// Maximum width in pixels.
+    pub max_width: u32,
+    /// Maximum height in pixels.
+    pub max_height: u32,
+    /// Maximum total pixel count, in megapixels.
+    pub max_megapixels: f64,
+}
+
+/// Reads an image's dimensions from its header without decoding pixel data.
+/// 
+/// # Errors
+/// 
+/// Returns `FaceDetectionError` if the file cannot be read or its header parsed.
+pub fn probe_dimensions(path: &Path) -> Result<(u32, u32)> {
+    image::io::Reader::open(path)
+        .context(IoSnafu)?
+        .with_guessed_format()
+        .context(IoSnafu)?
+        .into_dimensions()
+        .map_err(decode_error)
+}
+
+/// Reads the dimensions of encoded image bytes from their header.
+/// 
+/// # Errors
+/// 
+/// Returns `FaceDetectionError` if the header cannot be parsed as `format`.
+pub fn probe_bytes_dimensions(bytes: &[u8], format: ImageFormat) -> Result<(u32, u32)> {
+    image::io::Reader::with_format(Cursor::new(bytes), format)
+        .into_dimensions()
+        .map_err(decode_error)
+}
+
+/// Checks image dimensions against the configured limits.
+/// 
+/// Width and height are checked individually so that extreme aspect ratios
+/// (e.g. 100000x1) are rejected even when the pixel count is small.
+/// 
+/// # Errors
+/// 
+/// Returns a validation error naming the first limit exceeded.
+pub fn check_dimensions(width: u32, height: u32, limits: &DimensionLimits) -> Result<()> {
+    if width > limits.max_width {
+        return Err(validation_error(format!(
+            "Image width {} exceeds the maximum of {} pixels",
+            width, limits.max_width
+        )));
+    }
+    
+    if height > limits.max_height {
+        return Err(validation_error(format!(
+            "Image height {} exceeds the maximum of {} pixels",
+            height, limits.max_height
+        )));
+    }
+    
+    let megapixels = f64::from(width) * f64::from(height) / 1_000_000.0;
+    if megapixels > limits.max_megapixels {
+        return Err(validation_error(format!(
+            "Image size {:.1} MP exceeds the maximum of {} MP",
+            megapixels, limits.max_megapixels
+        )));
+    }
+    
+    Ok(())
+}
+
+/// Opens an image file, choosing the decoder from its content rather than
+/// its file extension.
+/// 
+/// # Errors
+/// 
+/// Returns `FaceDetectionError` if the file cannot be read, is not a supported
+/// format, or fails to decode.
+pub fn open_image(path: &Path) -> Result<DynamicImage> {
+    let bytes = std::fs::read(path).context(IoSnafu)?;
+    decode_image(&bytes)
+}
+
+/// Decodes encoded image bytes, choosing the decoder from their content.
+/// 
+/// # Errors
+/// 
+/// Returns `FaceDetectionError` if the bytes are not a supported format or
+/// fail to decode.
+pub fn decode_image(bytes: &[u8]) -> Result<DynamicImage> {
+    let format = sniff_image_format(bytes)?;
+    
+    image::load_from_memory_with_format(bytes, format)
+        .map_err(decode_error)
+}
+
 /// Performs face detection on an image file.
 /// 
 /// # Arguments
@@ -52,8 +291,33 @@ pub fn visualize_detections(
     detector.draw_bounding_boxes(original_image, faces)
 }
 
+/// Draws detected faces on a transparent canvas.
+/// 
+/// The result is an RGBA image of the given size that is fully transparent
+/// except for the boxes and labels, for clients that composite the overlay
+/// over the original image themselves.
+/// 
+/// # Arguments
+/// 
+/// * `width` - Overlay width in pixels
+/// * `height` - Overlay height in pixels
+/// * `faces` - Faces to draw
+/// * `options` - Box color, thickness and label settings
+pub fn draw_overlay(width: u32, height: u32, faces: &[Face], options: &DrawOptions) -> DynamicImage {
+    let mut overlay = DynamicImage::ImageRgba8(RgbaImage::new(width, height));
+    crate::detector::draw_faces_mut(&mut overlay, faces, options);
+    overlay
+}
+
+/// Background that transparent pixels of cropped faces are composited over.
+pub const DEFAULT_CROP_BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+
 /// Crops a detected face from an image.
 /// 
+/// Crops end up as JPEGs, which have no alpha channel, so images with one
+/// are composited over [`DEFAULT_CROP_BACKGROUND`]; see
+/// [`crop_face_with_background`].
+/// 
 /// # Arguments
 /// 
 /// * `image` - The source image
@@ -67,18 +331,297 @@ pub fn visualize_detections(
 /// 
 /// Returns `FaceDetectionError` if cropping fails or bounds are invalid.
 pub fn crop_face(image: &DynamicImage, face: &Face) -> Result<DynamicImage> {
-    let x = face.x.max(0) as u32;
-    let y = face.y.max(0) as u32;
-    let width = face.width as u32;
-    let height = face.height as u32;
+    crop_face_with_background(image, face, DEFAULT_CROP_BACKGROUND)
+}
+
+/// Crops a detected face, compositing any transparency over `background`.
+/// 
+/// Without this, encoding drops the alpha channel and exposes whatever color
+/// transparent pixels happen to store, usually black, as dark edges.
+/// 
+/// # Errors
+/// 
+/// Returns `FaceDetectionError` if cropping fails or bounds are invalid.
+pub fn crop_face_with_background(image: &DynamicImage, face: &Face, background: Rgb<u8>) -> Result<DynamicImage> {
+    let (img_width, img_height) = image.dimensions();
+    let bbox = face.bbox;
+    
+    if img_width == 0 || img_height == 0 {
+        return Err(FaceDetectionError::InvalidImageData);
+    }
+    
+    if bbox.width <= 0.0 || bbox.height <= 0.0 {
+        return Err(validation_error("Face box has zero width or height"));
+    }
+    
+    if bbox.x >= img_width as f32 || bbox.y >= img_height as f32 {
+        return Err(validation_error(format!(
+            "Face box at ({}, {}) lies outside the {}x{} image",
+            bbox.x, bbox.y, img_width, img_height
+        )));
+    }
     
     // Ensure crop bounds are within image dimensions
-    let (img_width, img_height) = image.dimensions();
-    let crop_width = width.min(img_width - x);
-    let crop_height = height.min(img_height - y);
+    let (left, top, right, bottom) = bbox.pixel_bounds(img_width, img_height);
+    
+    let cropped = image.crop_imm(left, top, right - left, bottom - top);
+    Ok(flatten_alpha(&cropped, background))
+}
+
+/// Returns the smallest region containing every face, grown by `padding`
+/// pixels on each side and clamped to an `img_width` x `img_height` image.
+/// 
+/// The region takes the highest confidence among the faces and is marked
+/// truncated if any of them is.
+/// 
+/// # Returns
+/// 
+/// The enclosing region, or `None` when there are no faces.
+pub fn bounding_region(faces: &[Face], padding: u32, img_width: u32, img_height: u32) -> Option<Face> {
+    let first = faces.first()?;
+    let (mut left, mut top) = (first.bbox.x, first.bbox.y);
+    let (mut right, mut bottom) = (first.bbox.right(), first.bbox.bottom());
+    for face in &faces[1..] {
+        left = left.min(face.bbox.x);
+        top = top.min(face.bbox.y);
+        right = right.max(face.bbox.right());
+        bottom = bottom.max(face.bbox.bottom());
+    }
+    
+    let union = BoundingBox { x: left, y: top, width: right - left, height: bottom - top };
+    Some(Face {
+        bbox: union.expand(i32::try_from(padding).unwrap_or(i32::MAX), img_width, img_height),
+        confidence: faces.iter().map(|face| face.confidence).fold(f32::MIN, f32::max),
+        truncated: faces.iter().any(|face| face.truncated),
+        landmarks: Vec::new(),
+    })
+}
+
+/// Draws a filled circle of `radius` pixels at every landmark of `faces`.
+/// 
+/// Faces without landmarks are skipped, and circles are clipped to the image.
+pub fn draw_landmarks(image: &mut DynamicImage, faces: &[Face], radius: u32, color: Rgb<u8>) {
+    let Rgb([r, g, b]) = color;
+    let radius = i32::try_from(radius).unwrap_or(i32::MAX);
+    for landmark in faces.iter().flat_map(|face| &face.landmarks) {
+        let center = (landmark.x.round() as i32, landmark.y.round() as i32);
+        imageproc::drawing::draw_filled_circle_mut(image, center, radius, image::Rgba([r, g, b, 255]));
+    }
+}
+
+/// Composites an image with an alpha channel over an opaque `background`.
+/// 
+/// Images without alpha are returned unchanged.
+pub fn flatten_alpha(image: &DynamicImage, background: Rgb<u8>) -> DynamicImage {
+    if !image.color().has_alpha() {
+        return image.clone();
+    }
+    
+    let rgba = image.to_rgba8();
+    DynamicImage::ImageRgb8(RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let alpha = f32::from(a) / 255.0;
+        let blend = |channel: u8, bg: u8| (f32::from(channel) * alpha + f32::from(bg) * (1.0 - alpha)).round() as u8;
+        Rgb([blend(r, background[0]), blend(g, background[1]), blend(b, background[2])])
+    }))
+}
+
+/// Largest number of pixels sampled along each side by [`skin_ratio`].
+const SKIN_SAMPLES_PER_SIDE: u32 = 64;
+
+/// Returns the fraction of pixels inside `bbox` that look like skin.
+/// 
+/// Uses the RGB skin-color rule of Kovač et al.; large boxes are sampled on
+/// a grid of at most 64x64 pixels. The box is clamped to the image, and an
+/// empty box has a ratio of zero.
+pub fn skin_ratio(image: &DynamicImage, bbox: &BoundingBox) -> f32 {
+    let (left, top, right, bottom) = bbox.pixel_bounds(image.width(), image.height());
+    if right == left || bottom == top {
+        return 0.0;
+    }
+    
+    let step_x = (right - left).div_ceil(SKIN_SAMPLES_PER_SIDE);
+    let step_y = (bottom - top).div_ceil(SKIN_SAMPLES_PER_SIDE);
+    let (mut skin, mut total) = (0u32, 0u32);
+    for y in (top..bottom).step_by(step_y as usize) {
+        for x in (left..right).step_by(step_x as usize) {
+            let [r, g, b, _] = image.get_pixel(x, y).0;
+            total += 1;
+            skin += u32::from(is_skin_tone(r, g, b));
+        }
+    }
+    
+    skin as f32 / total as f32
+}
+
+/// Whether an RGB color falls in the range of skin tones under daylight.
+fn is_skin_tone(r: u8, g: u8, b: u8) -> bool {
+    let spread = r.max(g).max(b) - r.min(g).min(b);
+    r > 95 && g > 40 && b > 20 && spread > 15 && r.abs_diff(g) > 15 && r > g && r > b
+}
+
+/// Computes the 64-bit difference hash (dHash) of an image.
+/// 
+/// The image is shrunk to 9x8 grayscale and each bit records whether a pixel
+/// is darker than its right neighbour, so re-encoded, resized or lightly
+/// edited copies hash to nearby values.
+pub fn dhash(image: &DynamicImage) -> u64 {
+    let small = image
+        .resize_exact(9, 8, image::imageops::FilterType::Triangle)
+        .to_luma8();
     
-    let cropped = image.crop_imm(x, y, crop_width, crop_height);
-    Ok(cropped)
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let brighter_right = small.get_pixel(x, y).0[0] < small.get_pixel(x + 1, y).0[0];
+            hash = (hash << 1) | u64::from(brighter_right);
+        }
+    }
+    hash
+}
+
+/// Computes the SHA-256 of encoded image bytes as lowercase hex.
+/// 
+/// Unlike [`dhash`], this identifies the exact bytes: any re-encode changes it.
+pub fn image_content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Number of differing bits between two perceptual hashes.
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Pads an image to a square, centering it on a solid background.
+/// 
+/// Nothing is resampled, so only `options.pad_color` is used.
+/// 
+/// # Arguments
+/// 
+/// * `image` - The image to pad
+/// * `options` - Resize options supplying the border color
+/// 
+/// # Returns
+/// 
+/// The square image and the padding added above and left of the original.
+pub fn pad_to_square(image: &DynamicImage, options: &ResizeOptions) -> (DynamicImage, PadOffset) {
+    let (width, height) = image.dimensions();
+    let side = width.max(height);
+    letterbox(image, side, side, options.pad_color)
+}
+
+/// Resizes an image to exactly `width`x`height`.
+/// 
+/// With `options.preserve_aspect`, the image is scaled to fit and centered
+/// on `options.pad_color`; otherwise it is stretched.
+/// 
+/// # Returns
+/// 
+/// The resized image and the padding added above and left of the content.
+pub fn fit_to(image: &DynamicImage, width: u32, height: u32, options: &ResizeOptions) -> (DynamicImage, PadOffset) {
+    if !options.preserve_aspect {
+        return (image.resize_exact(width, height, options.filter), PadOffset::default());
+    }
+    
+    let scaled = image.resize(width, height, options.filter);
+    letterbox(&scaled, width, height, options.pad_color)
+}
+
+/// Downscales an image whose longer side exceeds `max_dimension`, keeping
+/// its aspect ratio.
+/// 
+/// Returns `None` if the image already fits or `max_dimension` is zero.
+pub fn downscale_to_fit(image: &DynamicImage, max_dimension: u32) -> Option<DynamicImage> {
+    let (width, height) = image.dimensions();
+    if max_dimension == 0 || width.max(height) <= max_dimension {
+        return None;
+    }
+    Some(image.resize(max_dimension, max_dimension, image::imageops::FilterType::Triangle))
+}
+
+/// Centers `image` on a `width`x`height` canvas of `background`.
+fn letterbox(image: &DynamicImage, width: u32, height: u32, background: Rgb<u8>) -> (DynamicImage, PadOffset) {
+    let offset = PadOffset {
+        top: height.saturating_sub(image.height()) / 2,
+        left: width.saturating_sub(image.width()) / 2,
+    };
+    
+    let mut canvas = RgbImage::from_pixel(width, height, background);
+    image::imageops::overlay(
+        &mut canvas,
+        &image.to_rgb8(),
+        i64::from(offset.left),
+        i64::from(offset.top),
+    );
+    
+    (DynamicImage::ImageRgb8(canvas), offset)
+}
+
+/// Clockwise rotations, in degrees, tried in order by auto-orientation.
+pub const AUTO_ORIENT_ROTATIONS: [u16; 3] = [90, 180, 270];
+
+/// Rotates an image clockwise by a multiple of 90 degrees.
+/// 
+/// Any other angle returns an unrotated copy.
+pub fn rotate_clockwise(image: &DynamicImage, degrees: u16) -> DynamicImage {
+    match degrees % 360 {
+        90 => image.rotate90(),
+        180 => image.rotate180(),
+        270 => image.rotate270(),
+        _ => image.clone(),
+    }
+}
+
+/// Maps a box found in a rotated copy of an image back onto the original.
+/// 
+/// # Arguments
+/// 
+/// * `bbox` - Box in the coordinates of the image rotated clockwise by `degrees`
+/// * `degrees` - Rotation applied with [`rotate_clockwise`]
+/// * `width` - Width of the original, unrotated image
+/// * `height` - Height of the original, unrotated image
+pub fn unrotate_box(bbox: BoundingBox, degrees: u16, width: u32, height: u32) -> BoundingBox {
+    let (width, height) = (width as f32, height as f32);
+    match degrees % 360 {
+        90 => BoundingBox {
+            x: bbox.y,
+            y: (height - bbox.right()).max(0.0),
+            width: bbox.height,
+            height: bbox.width,
+        },
+        180 => BoundingBox {
+            x: (width - bbox.right()).max(0.0),
+            y: (height - bbox.bottom()).max(0.0),
+            ..bbox
+        },
+        270 => BoundingBox {
+            x: (width - bbox.bottom()).max(0.0),
+            y: bbox.x,
+            width: bbox.height,
+            height: bbox.width,
+        },
+        _ => bbox,
+    }
+}
+
+/// Parses a `rrggbb` hex color, with or without a leading `#`.
+/// 
+/// # Errors
+/// 
+/// Returns a validation error if the string is not six hex digits.
+pub fn parse_hex_color(hex: &str) -> Result<Rgb<u8>> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    let invalid = || validation_error(format!("Invalid color '{}': expected rrggbb hex", hex));
+    
+    if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| invalid());
+    Ok(Rgb([channel(0)?, channel(2)?, channel(4)?]))
 }
 
 /// Converts an image to base64 encoded string.
@@ -95,24 +638,255 @@ pub fn crop_face(image: &DynamicImage, face: &Face) -> Result<DynamicImage> {
 /// 
 /// Returns `FaceDetectionError` if encoding fails.
 pub fn image_to_base64(image: &DynamicImage) -> Result<String> {
-    use std::io::Cursor;
-    
+    image_to_base64_with_quality(image, DEFAULT_JPEG_QUALITY)
+}
+
+/// Converts an image to a base64 JPEG data URI at the given quality.
+/// 
+/// # Errors
+/// 
+/// Returns `FaceDetectionError` if encoding fails.
+pub fn image_to_base64_with_quality(image: &DynamicImage, quality: u8) -> Result<String> {
+    Ok(jpeg_bytes_to_base64(&encode_jpeg(image, quality)?))
+}
+
+/// JPEG quality used when none is requested.
+pub const DEFAULT_JPEG_QUALITY: u8 = 85;
+
+/// Encodes an image as JPEG.
+/// 
+/// # Arguments
+/// 
+/// * `image` - The image to encode
+/// * `quality` - JPEG quality, 1 to 100
+/// 
+/// # Errors
+/// 
+/// Returns `FaceDetectionError` if encoding fails.
+pub fn encode_jpeg(image: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
     let mut buffer = Vec::new();
     let mut cursor = Cursor::new(&mut buffer);
     
-    image.write_to(&mut cursor, image::ImageOutputFormat::Jpeg(85))
+    image.write_to(&mut cursor, image::ImageOutputFormat::Jpeg(quality))
         .map_err(|_| FaceDetectionError::ImageProcessing {
-            source: image::ImageError::IoError(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to encode image"
-            ))
+            source: image::ImageError::IoError(std::io::Error::other("Failed to encode image"))
         })?;
     
-    Ok(format!("data:image/jpeg;base64,{}", base64_encode(&buffer)))
+    Ok(buffer)
+}
+
+/// Encodes an image in the given output format.
+/// 
+/// JPEGs go through `jpeg-encoder`, which supports progressive scans and
+/// selectable chroma subsampling. Should it fail (e.g. for images wider or
+/// taller than 65535 pixels), the image is encoded with the `image` crate's
+/// baseline encoder at the same quality instead.
+/// 
+/// # Errors
+/// 
+/// Returns `FaceDetectionError` if encoding fails.
+pub fn encode_image(image: &DynamicImage, format: &OutputFormat) -> Result<Vec<u8>> {
+    let OutputFormat::Jpeg { quality, progressive, subsampling } = *format;
+    encode_jpeg_advanced(image, quality, progressive, subsampling).or_else(|e| {
+        tracing::warn!("Advanced JPEG encoding failed, using baseline encoder: {}", e);
+        encode_jpeg(image, quality)
+    })
+}
+
+/// Encodes an image as JPEG with `jpeg-encoder`.
+fn encode_jpeg_advanced(
+    image: &DynamicImage,
+    quality: u8,
+    progressive: bool,
+    subsampling: ChromaSubsampling,
+) -> std::result::Result<Vec<u8>, String> {
+    let width = u16::try_from(image.width()).map_err(|_| "image too wide".to_string())?;
+    let height = u16::try_from(image.height()).map_err(|_| "image too tall".to_string())?;
+    
+    let mut buffer = Vec::new();
+    let mut encoder = jpeg_encoder::Encoder::new(&mut buffer, quality.clamp(1, 100));
+    encoder.set_progressive(progressive);
+    encoder.set_sampling_factor(match subsampling {
+        ChromaSubsampling::Yuv444 => jpeg_encoder::SamplingFactor::R_4_4_4,
+        ChromaSubsampling::Yuv420 => jpeg_encoder::SamplingFactor::R_4_2_0,
+    });
+    encoder
+        .encode(&image.to_rgb8(), width, height, jpeg_encoder::ColorType::Rgb)
+        .map_err(|e| e.to_string())?;
+    
+    Ok(buffer)
+}
+
+/// Encodes an image as PNG, keeping its alpha channel.
+/// 
+/// # Errors
+/// 
+/// Returns `FaceDetectionError` if encoding fails.
+pub fn encode_png(image: &DynamicImage) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    image.write_to(&mut Cursor::new(&mut buffer), image::ImageOutputFormat::Png)
+        .context(crate::error::ImageProcessingSnafu)?;
+    Ok(buffer)
+}
+
+/// Wraps encoded JPEG bytes in a base64 data URI.
+pub fn jpeg_bytes_to_base64(bytes: &[u8]) -> String {
+    format!("data:image/jpeg;base64,{}", STANDARD.encode(bytes))
+}
+
+/// Wraps encoded image bytes, unchanged, in a base64 data URI whose MIME
+/// type matches their sniffed format.
+/// 
+/// # Errors
+/// 
+/// Returns `FaceDetectionError::InvalidFileFormat` if the format is unknown or
+/// not in [`SUPPORTED_INPUT_FORMATS`].
+pub fn bytes_to_data_uri(bytes: &[u8]) -> Result<String> {
+    let format = sniff_image_format(bytes)?;
+    Ok(format!("data:{};base64,{}", format.to_mime_type(), STANDARD.encode(bytes)))
+}
+
+/// Keyword identifying embedded detection metadata in PNG `tEXt` chunks
+/// and JPEG comment segments.
+pub const METADATA_KEYWORD: &str = "FaceDetection";
+
+/// Largest payload a JPEG segment can carry after its 2-byte length.
+const JPEG_MAX_SEGMENT_PAYLOAD: usize = u16::MAX as usize - 2;
+
+/// Length of the PNG signature plus the IHDR chunk, which must come first.
+const PNG_HEADER_LEN: usize = 8 + 12 + 13;
+
+/// Embeds a detection result as JSON inside an encoded image.
+/// 
+/// PNG images get a `tEXt` chunk right after `IHDR`; JPEG images get a
+/// comment (`COM`) segment right after `SOI`. Both are keyed by
+/// [`METADATA_KEYWORD`].
+/// 
+/// # Arguments
+/// 
+/// * `bytes` - Encoded PNG or JPEG image
+/// * `result` - Detection result to embed
+/// * `format` - Format of `bytes`
+/// 
+/// # Errors
+/// 
+/// Returns `InvalidFileFormat` for other formats, and a validation error if
+/// `bytes` is malformed or the metadata does not fit in a JPEG segment.
+pub fn embed_result_metadata(
+    bytes: &[u8],
+    result: &DetectionResult,
+    format: ImageFormat,
+) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec(result)
+        .map_err(|e| validation_error(format!("Failed to serialize metadata: {}", e)))?;
+    
+    let mut payload = METADATA_KEYWORD.as_bytes().to_vec();
+    payload.push(0);
+    payload.extend_from_slice(&json);
+    
+    match format {
+        ImageFormat::Png => {
+            if bytes.len() < PNG_HEADER_LEN || !bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+                return Err(validation_error("Malformed PNG data"));
+            }
+            
+            let mut chunk_body = b"tEXt".to_vec();
+            chunk_body.extend_from_slice(&payload);
+            
+            let mut out = Vec::with_capacity(bytes.len() + chunk_body.len() + 8);
+            out.extend_from_slice(&bytes[..PNG_HEADER_LEN]);
+            out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+            out.extend_from_slice(&chunk_body);
+            out.extend_from_slice(&crc32fast::hash(&chunk_body).to_be_bytes());
+            out.extend_from_slice(&bytes[PNG_HEADER_LEN..]);
+            Ok(out)
+        }
+        ImageFormat::Jpeg => {
+            if !bytes.starts_with(&[0xFF, 0xD8]) {
+                return Err(validation_error("Malformed JPEG data"));
+            }
+            if payload.len() > JPEG_MAX_SEGMENT_PAYLOAD {
+                return Err(validation_error("Detection metadata too large for a JPEG comment"));
+            }
+            
+            let mut out = Vec::with_capacity(bytes.len() + payload.len() + 4);
+            out.extend_from_slice(&bytes[..2]);
+            out.extend_from_slice(&[0xFF, 0xFE]);
+            out.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+            out.extend_from_slice(&payload);
+            out.extend_from_slice(&bytes[2..]);
+            Ok(out)
+        }
+        other => Err(FaceDetectionError::InvalidFileFormat {
+            format: format_name(other),
+        }),
+    }
+}
+
+/// Reads a detection result previously stored by [`embed_result_metadata`].
+/// 
+/// Returns `None` if the image carries no (valid) detection metadata.
+pub fn read_result_metadata(bytes: &[u8], format: ImageFormat) -> Option<DetectionResult> {
+    let payload = match format {
+        ImageFormat::Png => png_text_chunks(bytes).find(|p| is_metadata_payload(p))?,
+        ImageFormat::Jpeg => jpeg_comments(bytes).find(|p| is_metadata_payload(p))?,
+        _ => return None,
+    };
+    
+    serde_json::from_slice(&payload[METADATA_KEYWORD.len() + 1..]).ok()
+}
+
+/// Whether a text payload starts with the metadata keyword and separator.
+fn is_metadata_payload(payload: &[u8]) -> bool {
+    payload.strip_prefix(METADATA_KEYWORD.as_bytes())
+        .is_some_and(|rest| rest.first() == Some(&0))
+}
+
+/// Iterates over the data of every `tEXt` chunk in a PNG.
+fn png_text_chunks(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut pos = 8;
+    std::iter::from_fn(move || {
+        while pos + 8 <= bytes.len() {
+            let len = u32::from_be_bytes(bytes[pos..pos + 4].try_into().ok()?) as usize;
+            let kind = &bytes[pos + 4..pos + 8];
+            let data = bytes.get(pos + 8..pos + 8 + len)?;
+            pos += 12 + len;
+            
+            if kind == b"tEXt" {
+                return Some(data);
+            }
+        }
+        None
+    })
+}
+
+/// Iterates over the data of every comment segment before a JPEG's scan data.
+fn jpeg_comments(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut pos = 2;
+    std::iter::from_fn(move || {
+        while pos + 4 <= bytes.len() && bytes[pos] == 0xFF {
+            let marker = bytes[pos + 1];
+            if marker == 0xDA || marker == 0xD9 {
+                return None;
+            }
+            
+            let len = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+            let data = bytes.get(pos + 4..pos + 2 + len)?;
+            pos += 2 + len;
+            
+            if marker == 0xFE {
+                return Some(data);
+            }
+        }
+        None
+    })
 }
 
 /// Decodes base64 image data.
 /// 
+/// ASCII whitespace (e.g. line-wrapped payloads) is ignored; any other
+/// character outside the standard alphabet or misplaced padding is rejected.
+/// 
 /// # Arguments
 /// 
 /// * `data_uri` - Base64 encoded image with data URI prefix
@@ -121,91 +895,778 @@ pub fn image_to_base64(image: &DynamicImage) -> Result<String> {
 /// 
 /// Decoded image bytes.
 /// 
+/// Both the standard and the URL-safe (`-`/`_`) alphabets are accepted, with
+/// or without `=` padding.
+/// 
 /// # Errors
 /// 
-/// Returns `FaceDetectionError` if decoding fails.
+/// Returns `FaceDetectionError::Base64Error` if decoding fails.
 pub fn decode_base64_image(data_uri: &str) -> Result<Vec<u8>> {
     let base64_data = data_uri
         .strip_prefix("data:image/jpeg;base64,")
         .or_else(|| data_uri.strip_prefix("data:image/png;base64,"))
         .unwrap_or(data_uri);
     
-    base64_decode(base64_data)
+    let compact: String = base64_data
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    
+    STANDARD_ANY_PAD.decode(&compact)
+        .or_else(|_| URL_SAFE_ANY_PAD.decode(&compact))
         .map_err(|_| FaceDetectionError::Base64Error)
 }
 
-/// Simple base64 encoding implementation.
-fn base64_encode(data: &[u8]) -> String {
-    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// Standard alphabet, padding optional.
+const STANDARD_ANY_PAD: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// URL-safe alphabet, padding optional.
+const URL_SAFE_ANY_PAD: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Extracts the ICC color profile embedded in encoded image bytes.
+/// 
+/// # Arguments
+/// 
+/// * `bytes` - Encoded image data (PNG, JPEG, WebP or TIFF)
+/// 
+/// # Returns
+/// 
+/// The raw ICC profile, or `None` if the format carries no profile.
+pub fn extract_icc_profile(bytes: &[u8]) -> Option<Vec<u8>> {
+    use image::codecs::jpeg::JpegDecoder;
+    use image::codecs::png::PngDecoder;
+    use image::codecs::tiff::TiffDecoder;
+    use image::codecs::webp::WebPDecoder;
     
-    let mut result = String::new();
-    let mut i = 0;
+    match image::guess_format(bytes).ok()? {
+        ImageFormat::Png => PngDecoder::new(Cursor::new(bytes)).ok()?.icc_profile(),
+        ImageFormat::Jpeg => JpegDecoder::new(Cursor::new(bytes)).ok()?.icc_profile(),
+        ImageFormat::WebP => WebPDecoder::new(Cursor::new(bytes)).ok()?.icc_profile(),
+        ImageFormat::Tiff => TiffDecoder::new(Cursor::new(bytes)).ok()?.icc_profile(),
+        _ => None,
+    }
+}
+
+/// Converts an image from the given ICC profile into sRGB.
+/// 
+/// Alpha is carried over unchanged. Profiles that cannot be parsed or that
+/// do not describe an RGB space are logged and the image is returned as-is.
+/// 
+/// # Arguments
+/// 
+/// * `image` - The decoded image
+/// * `icc_profile` - The ICC profile the pixel values are encoded in
+pub fn convert_to_srgb(image: &DynamicImage, icc_profile: &[u8]) -> DynamicImage {
+    use lcms2::{Flags, Intent, PixelFormat, Profile, Transform};
+    
+    let source = match Profile::new_icc(icc_profile) {
+        Ok(profile) => profile,
+        Err(e) => {
+            tracing::warn!("Ignoring unreadable ICC profile: {}", e);
+            return image.clone();
+        }
+    };
+    
+    let transform = match Transform::<[u8; 4], [u8; 4]>::new_flags(
+        &source,
+        PixelFormat::RGBA_8,
+        &Profile::new_srgb(),
+        PixelFormat::RGBA_8,
+        Intent::Perceptual,
+        Flags::COPY_ALPHA,
+    ) {
+        Ok(transform) => transform,
+        Err(e) => {
+            tracing::warn!("Ignoring unsupported ICC profile: {}", e);
+            return image.clone();
+        }
+    };
+    
+    let mut rgba = image.to_rgba8();
+    let mut pixels: Vec<[u8; 4]> = rgba.pixels().map(|p| p.0).collect();
+    transform.transform_in_place(&mut pixels);
+    for (pixel, converted) in rgba.pixels_mut().zip(pixels) {
+        pixel.0 = converted;
+    }
+    
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// Decodes image bytes and normalizes them to sRGB.
+/// 
+/// Images without an embedded ICC profile are assumed to already be sRGB
+/// and are passed through unchanged.
+/// 
+/// # Arguments
+/// 
+/// * `bytes` - Encoded image data
+/// 
+/// # Errors
+/// 
+/// Returns `FaceDetectionError` if the image cannot be decoded.
+pub fn load_image_srgb(bytes: &[u8]) -> Result<DynamicImage> {
+    let image = image::load_from_memory(bytes)
+        .map_err(decode_error)?;
+    
+    match extract_icc_profile(bytes) {
+        Some(profile) => Ok(convert_to_srgb(&image, &profile)),
+        None => Ok(image),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ResizeFilter;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_downscale_to_fit_keeps_aspect_ratio() {
+        let image = DynamicImage::new_rgb8(400, 200);
+        let small = downscale_to_fit(&image, 100).unwrap();
+        assert_eq!(small.dimensions(), (100, 50));
+        assert!(downscale_to_fit(&image, 400).is_none());
+        assert!(downscale_to_fit(&image, 0).is_none());
+    }
     
-    while i < data.len() {
-        let mut buffer = [0u8; 3];
-        let mut filled = 0;
+    #[test]
+    fn test_landmarks_drawn_at_eye_positions() {
+        use crate::types::Landmark;
+        
+        let red = Rgb([255, 0, 0]);
+        let with_landmarks = Face {
+            landmarks: vec![Landmark { x: 35.0, y: 40.0 }, Landmark { x: 65.0, y: 40.0 }],
+            ..Face::new(20, 20, 60, 60, 0.9)
+        };
+        let without_landmarks = Face::new(0, 0, 10, 10, 0.9);
+        
+        let mut image = DynamicImage::new_rgb8(100, 100);
+        draw_landmarks(&mut image, &[with_landmarks.clone(), without_landmarks], 2, red);
+        let image = image.to_rgb8();
+        assert_eq!(*image.get_pixel(35, 40), red);
+        assert_eq!(*image.get_pixel(65, 40), red);
+        assert_eq!(*image.get_pixel(37, 41), red);
+        assert_eq!(*image.get_pixel(50, 40), Rgb([0, 0, 0]));
+        // Nothing is drawn away from the landmarks, including for the face without any
+        assert!(image.enumerate_pixels().filter(|(_, _, &pixel)| pixel == red).all(|(x, y, _)| {
+            ((x as i32 - 35).abs() <= 2 || (x as i32 - 65).abs() <= 2) && (y as i32 - 40).abs() <= 2
+        }));
+        
+        // Only drawn by the annotation pipeline when asked for
+        let options = DrawOptions { show_labels: false, ..DrawOptions::default() };
+        let overlay = draw_overlay(100, 100, std::slice::from_ref(&with_landmarks), &options).to_rgba8();
+        assert_eq!(overlay.get_pixel(35, 40)[3], 0);
+        let options = DrawOptions { show_landmarks: true, ..options };
+        let overlay = draw_overlay(100, 100, &[with_landmarks], &options).to_rgba8();
+        assert_eq!(overlay.get_pixel(35, 40)[3], 255);
+    }
+
+    #[test]
+    fn test_bounding_region_spans_all_faces_with_padding() {
+        let faces = [
+            Face::new(40, 300, 50, 60, 0.8),
+            Face::new(200, 20, 30, 30, 0.95),
+            Face::new(350, 150, 80, 100, 0.7),
+        ];
+        
+        let region = bounding_region(&faces, 10, 500, 500).unwrap();
+        // x from 40, y from 20, right at 350 + 80, bottom at 300 + 60
+        assert_eq!(region.bbox, BoundingBox::new(30, 10, 410, 360));
+        assert_eq!(region.confidence, 0.95);
+        
+        // Padding past the image edges is clamped
+        let region = bounding_region(&faces, 50, 450, 400).unwrap();
+        assert_eq!(region.bbox, BoundingBox::new(0, 0, 450, 400));
+        
+        assert!(bounding_region(&[], 10, 500, 500).is_none());
+    }
+
+    fn assert_ok_or_base64_error(input: &str) {
+        match decode_base64_image(input) {
+            Ok(_) | Err(FaceDetectionError::Base64Error) => {}
+            Err(e) => panic!("unexpected error for {:?}: {}", input, e),
+        }
+    }
+
+    #[test]
+    fn test_sniffer_accepts_every_supported_input_format() {
+        let img = DynamicImage::new_rgb8(4, 4);
+        
+        for &format in SUPPORTED_INPUT_FORMATS {
+            let bytes = if format == ImageFormat::WebP {
+                // The WebP encoder is not enabled; the sniffer only needs the RIFF header.
+                b"RIFF\x1a\0\0\0WEBPVP8L\x0d\0\0\0".to_vec()
+            } else {
+                let mut bytes = Vec::new();
+                img.write_to(&mut Cursor::new(&mut bytes), format).unwrap();
+                bytes
+            };
+            
+            assert_eq!(sniff_image_format(&bytes).unwrap(), format);
+        }
+    }
+
+    #[test]
+    fn test_bytes_to_data_uri_keeps_bytes_and_format() {
+        let mut png = Vec::new();
+        DynamicImage::new_rgb8(4, 4)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        
+        let data_uri = bytes_to_data_uri(&png).unwrap();
+        assert!(data_uri.starts_with("data:image/png;base64,"));
+        assert_eq!(decode_base64_image(&data_uri).unwrap(), png);
+        assert!(bytes_to_data_uri(b"just some text").is_err());
+    }
+
+    #[test]
+    fn test_sniffer_rejects_unsupported_formats() {
+        let mut ico = Vec::new();
+        DynamicImage::new_rgb8(4, 4)
+            .write_to(&mut Cursor::new(&mut ico), ImageFormat::Ico)
+            .unwrap();
+        
+        assert!(matches!(
+            sniff_image_format(&ico),
+            Err(FaceDetectionError::InvalidFileFormat { format }) if format == "ico"
+        ));
+        assert!(matches!(
+            sniff_image_format(b"just some text"),
+            Err(FaceDetectionError::InvalidFileFormat { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pad_to_square_centers_image() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(200, 100, Rgb([255, 0, 0])));
+        let blue = ResizeOptions { pad_color: Rgb([0, 0, 255]), ..ResizeOptions::default() };
+        let (padded, offset) = pad_to_square(&img, &blue);
+        
+        assert_eq!(padded.dimensions(), (200, 200));
+        assert_eq!(offset, PadOffset { top: 50, left: 0 });
+        
+        let rgb = padded.to_rgb8();
+        assert_eq!(rgb.get_pixel(0, 0), &Rgb([0, 0, 255]));
+        assert_eq!(rgb.get_pixel(0, 50), &Rgb([255, 0, 0]));
+        assert_eq!(rgb.get_pixel(199, 149), &Rgb([255, 0, 0]));
+        assert_eq!(rgb.get_pixel(199, 150), &Rgb([0, 0, 255]));
+        
+        let (unchanged, offset) = pad_to_square(&DynamicImage::new_rgb8(64, 64), &ResizeOptions::default());
+        assert_eq!(unchanged.dimensions(), (64, 64));
+        assert_eq!(offset, PadOffset::default());
+    }
+
+    #[test]
+    fn test_fit_to_letterboxes_or_stretches() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(40, 20, Rgb([255, 0, 0])));
+        let green = ResizeOptions { pad_color: Rgb([0, 255, 0]), ..ResizeOptions::default() };
+        
+        let (fitted, offset) = fit_to(&img, 100, 100, &green);
+        assert_eq!(fitted.dimensions(), (100, 100));
+        assert_eq!(offset, PadOffset { top: 25, left: 0 });
+        assert_eq!(fitted.to_rgb8().get_pixel(50, 10), &Rgb([0, 255, 0]));
+        
+        let stretch = ResizeOptions { preserve_aspect: false, ..green };
+        let (stretched, offset) = fit_to(&img, 100, 100, &stretch);
+        assert_eq!(offset, PadOffset::default());
+        assert_eq!(stretched.to_rgb8().get_pixel(50, 10), &Rgb([255, 0, 0]));
+    }
+
+    #[test]
+    fn test_crop_composites_transparency_over_background() {
+        // Left half opaque red, right half fully transparent black, bottom row half transparent
+        let img = RgbaImage::from_fn(20, 20, |x, y| match (x < 10, y == 19) {
+            (_, true) => image::Rgba([0, 0, 0, 128]),
+            (true, false) => image::Rgba([255, 0, 0, 255]),
+            (false, false) => image::Rgba([0, 0, 0, 0]),
+        });
+        let img = DynamicImage::ImageRgba8(img);
+        let face = Face::new(0, 0, 20, 20, 0.9);
+        
+        let crop = crop_face(&img, &face).unwrap();
+        assert!(!crop.color().has_alpha());
+        let rgb = crop.to_rgb8();
+        assert_eq!(rgb.get_pixel(2, 2), &Rgb([255, 0, 0]));
+        assert_eq!(rgb.get_pixel(15, 2), &Rgb([255, 255, 255]));
+        assert_eq!(rgb.get_pixel(15, 19), &Rgb([127, 127, 127]));
+        
+        // The composited background survives JPEG encoding
+        let jpeg = image::load_from_memory(&encode_jpeg(&crop, 95).unwrap()).unwrap().to_rgb8();
+        assert!(jpeg.get_pixel(15, 5).0.iter().all(|&c| c > 240));
+        
+        let blue = crop_face_with_background(&img, &face, Rgb([0, 0, 255])).unwrap().to_rgb8();
+        assert_eq!(blue.get_pixel(15, 2), &Rgb([0, 0, 255]));
+    }
+
+    #[test]
+    fn test_skin_ratio_tells_skin_from_gray() {
+        let img = RgbImage::from_fn(200, 100, |x, _| {
+            if x < 100 { Rgb([224, 172, 140]) } else { Rgb([128, 128, 128]) }
+        });
+        let img = DynamicImage::ImageRgb8(img);
+        
+        assert_eq!(skin_ratio(&img, &BoundingBox::new(10, 10, 80, 80)), 1.0);
+        assert_eq!(skin_ratio(&img, &BoundingBox::new(110, 10, 80, 80)), 0.0);
+        let half = skin_ratio(&img, &BoundingBox::new(50, 0, 100, 100));
+        assert!((half - 0.5).abs() < 0.05, "{}", half);
+        assert_eq!(skin_ratio(&img, &BoundingBox::new(300, 0, 10, 10)), 0.0);
+    }
+
+    #[test]
+    fn test_upscaled_crop_depends_on_filter() {
+        // A checkerboard crop upscaled 8x: nearest keeps hard edges, Lanczos blends them
+        let board = RgbImage::from_fn(16, 16, |x, y| {
+            if (x + y) % 2 == 0 { Rgb([255, 255, 255]) } else { Rgb([0, 0, 0]) }
+        });
+        let crop = crop_face(&DynamicImage::ImageRgb8(board), &Face::new(4, 4, 8, 8, 0.9)).unwrap();
         
-        for j in 0..3 {
-            if i + j < data.len() {
-                buffer[j] = data[i + j];
-                filled += 1;
+        let upscale = |filter: ResizeFilter| {
+            let options = ResizeOptions { filter: filter.into(), ..ResizeOptions::default() };
+            fit_to(&crop, 64, 64, &options).0.to_rgb8()
+        };
+        let nearest = upscale(ResizeFilter::Nearest);
+        let lanczos = upscale(ResizeFilter::Lanczos);
+        
+        assert_ne!(nearest, lanczos);
+        assert!(nearest.pixels().all(|p| p.0 == [0, 0, 0] || p.0 == [255, 255, 255]));
+        assert!(lanczos.pixels().any(|p| p.0[0] > 0 && p.0[0] < 255));
+    }
+
+    #[test]
+    fn test_draw_overlay_is_transparent_except_boxes() {
+        let face = Face::new(10, 10, 30, 20, 0.9);
+        let options = DrawOptions { thickness: 2, show_labels: false, ..DrawOptions::default() };
+        let overlay = draw_overlay(60, 50, &[face], &options).to_rgba8();
+        
+        assert_eq!(overlay.dimensions(), (60, 50));
+        for (x, y) in [(10, 10), (11, 25), (39, 29), (25, 28)] {
+            assert_eq!(overlay.get_pixel(x, y).0, [0, 255, 0, 255], "no box at ({}, {})", x, y);
+        }
+        for (x, y) in [(0, 0), (25, 20), (12, 12), (59, 49), (45, 5)] {
+            assert_eq!(overlay.get_pixel(x, y).0[3], 0, "opaque pixel at ({}, {})", x, y);
+        }
+        
+        let decoded = image::load_from_memory(&encode_png(&DynamicImage::ImageRgba8(overlay.clone())).unwrap()).unwrap();
+        assert_eq!(decoded.to_rgba8(), overlay);
+    }
+
+    #[test]
+    fn test_unrotate_box_inverts_rotation() {
+        let bbox = BoundingBox::new(30, 10, 50, 20);
+        let mut img = RgbImage::new(200, 100);
+        for y in 10..30 {
+            for x in 30..80 {
+                img.put_pixel(x, y, Rgb([255, 255, 255]));
             }
         }
+        let img = DynamicImage::ImageRgb8(img);
         
-        let b1 = buffer[0] >> 2;
-        let b2 = ((buffer[0] & 0x03) << 4) | (buffer[1] >> 4);
-        let b3 = ((buffer[1] & 0x0f) << 2) | (buffer[2] >> 6);
-        let b4 = buffer[2] & 0x3f;
+        for degrees in AUTO_ORIENT_ROTATIONS {
+            // Locate the white box in the rotated image and map it back
+            let rotated = rotate_clockwise(&img, degrees).to_luma8();
+            let white: Vec<_> = rotated.enumerate_pixels().filter(|(_, _, p)| p.0[0] == 255).collect();
+            let x = white.iter().map(|(x, _, _)| *x).min().unwrap();
+            let y = white.iter().map(|(_, y, _)| *y).min().unwrap();
+            let right = white.iter().map(|(x, _, _)| *x).max().unwrap() + 1;
+            let bottom = white.iter().map(|(_, y, _)| *y).max().unwrap() + 1;
+            let found = BoundingBox::new(x, y, right - x, bottom - y);
+            
+            assert_eq!(unrotate_box(found, degrees, 200, 100), bbox, "rotation {degrees}");
+        }
+    }
+
+    /// Sum of absolute per-channel differences between two RGB images.
+    fn total_diff(a: &RgbImage, b: &RgbImage) -> u64 {
+        a.pixels()
+            .zip(b.pixels())
+            .flat_map(|(p, q)| p.0.iter().zip(q.0).map(|(x, y)| u64::from(x.abs_diff(y))))
+            .sum()
+    }
+
+    #[test]
+    fn test_full_chroma_preserves_sharp_color_edges() {
+        // One-pixel red/blue stripes carry all their detail in the chroma channels
+        let stripes = RgbImage::from_fn(64, 64, |x, _| {
+            if x % 2 == 0 { Rgb([255, 0, 0]) } else { Rgb([0, 0, 255]) }
+        });
+        let image = DynamicImage::ImageRgb8(stripes.clone());
         
-        result.push(CHARS[b1 as usize] as char);
-        result.push(CHARS[b2 as usize] as char);
+        let roundtrip = |subsampling| {
+            let format = OutputFormat::Jpeg { quality: 90, progressive: false, subsampling };
+            let bytes = encode_image(&image, &format).unwrap();
+            image::load_from_memory(&bytes).unwrap().to_rgb8()
+        };
+        let full = total_diff(&stripes, &roundtrip(ChromaSubsampling::Yuv444));
+        let half = total_diff(&stripes, &roundtrip(ChromaSubsampling::Yuv420));
         
-        if filled >= 2 {
-            result.push(CHARS[b3 as usize] as char);
-        } else {
-            result.push('=');
+        assert!(full < half, "4:4:4 diff {} should be below 4:2:0 diff {}", full, half);
+    }
+
+    #[test]
+    fn test_progressive_jpeg_is_decodable() {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(40, 30, |x, y| Rgb([x as u8 * 6, y as u8 * 8, 90])));
+        let format = OutputFormat::Jpeg {
+            quality: 80,
+            progressive: true,
+            subsampling: ChromaSubsampling::Yuv420,
+        };
+        
+        let bytes = encode_image(&image, &format).unwrap();
+        
+        // SOF2 marks a progressive frame
+        assert!(bytes.windows(2).any(|marker| marker == [0xFF, 0xC2]));
+        assert_eq!(image::load_from_memory(&bytes).unwrap().dimensions(), (40, 30));
+    }
+
+    #[test]
+    fn test_image_content_hash_is_stable_per_bytes() {
+        let hash = image_content_hash(b"abc");
+        
+        assert_eq!(hash, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(image_content_hash(b"abc"), hash);
+        assert_ne!(image_content_hash(b"abd"), hash);
+    }
+
+    #[test]
+    fn test_dhash_tolerates_resize_but_separates_different_images() {
+        let gradient = DynamicImage::ImageRgb8(RgbImage::from_fn(200, 150, |x, y| {
+            Rgb([x as u8, y as u8, 128])
+        }));
+        let resized = gradient.resize_exact(120, 90, image::imageops::FilterType::Triangle);
+        let mirrored = gradient.fliph();
+        
+        assert_eq!(hamming_distance(dhash(&gradient), dhash(&gradient)), 0);
+        assert!(hamming_distance(dhash(&gradient), dhash(&resized)) <= 4);
+        assert!(hamming_distance(dhash(&gradient), dhash(&mirrored)) > 20);
+    }
+
+    #[test]
+    fn test_dhash_of_known_images_is_stable() {
+        let flat = DynamicImage::new_luma8(64, 64);
+        let brighter_right = DynamicImage::ImageLuma8(image::GrayImage::from_fn(90, 80, |x, _| {
+            image::Luma([x as u8 * 2])
+        }));
+        let blocks = DynamicImage::ImageLuma8(image::GrayImage::from_fn(72, 64, |x, y| {
+            image::Luma([((x / 8 * 37 + y / 8 * 91) % 256) as u8])
+        }));
+        
+        assert_eq!(dhash(&flat), 0);
+        assert_eq!(dhash(&brighter_right), u64::MAX);
+        assert_eq!(dhash(&brighter_right.fliph()), 0);
+        // Pinned so any change to the hashing algorithm shows up here
+        assert_eq!(dhash(&blocks), 0xfdf7_bffd_efbf_fbef);
+    }
+
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(parse_hex_color("ff8000").unwrap(), Rgb([255, 128, 0]));
+        assert_eq!(parse_hex_color("#FFFFFF").unwrap(), Rgb([255, 255, 255]));
+        assert!(parse_hex_color("fff").is_err());
+        assert!(parse_hex_color("gg0000").is_err());
+        assert!(parse_hex_color("+f+f+f").is_err());
+    }
+
+    #[test]
+    fn test_embed_result_metadata_roundtrip() {
+        let result = DetectionResult::new(
+            vec![Face::new(10, 20, 30, 40, 0.9), Face::new(50, 60, 70, 80, 0.8)],
+            12,
+        )
+        .with_image_dimensions(200, 150);
+        let img = DynamicImage::new_rgb8(200, 150);
+        
+        for format in [ImageFormat::Png, ImageFormat::Jpeg] {
+            let mut bytes = Vec::new();
+            img.write_to(&mut Cursor::new(&mut bytes), format).unwrap();
+            assert!(read_result_metadata(&bytes, format).is_none());
+            
+            let embedded = embed_result_metadata(&bytes, &result, format).unwrap();
+            let read = read_result_metadata(&embedded, format).unwrap();
+            assert_eq!(
+                serde_json::to_value(&read).unwrap(),
+                serde_json::to_value(&result).unwrap()
+            );
+            
+            // The image must still decode after the metadata is inserted
+            let decoded = image::load_from_memory_with_format(&embedded, format).unwrap();
+            assert_eq!(decoded.dimensions(), (200, 150));
         }
+    }
+
+    #[test]
+    fn test_embed_result_metadata_rejects_other_formats() {
+        let result = DetectionResult::new(Vec::new(), 0);
+        assert!(matches!(
+            embed_result_metadata(b"GIF89a", &result, ImageFormat::Gif),
+            Err(FaceDetectionError::InvalidFileFormat { .. })
+        ));
+        assert!(embed_result_metadata(b"not a png", &result, ImageFormat::Png).is_err());
+    }
+
+    #[test]
+    fn test_check_dimensions_limits_each_axis() {
+        let limits = DimensionLimits {
+            max_width: 16384,
+            max_height: 16384,
+            max_megapixels: 50.0,
+        };
+        
+        assert!(check_dimensions(4000, 3000, &limits).is_ok());
         
-        if filled >= 3 {
-            result.push(CHARS[b4 as usize] as char);
-        } else {
-            result.push('=');
+        // 100k pixels passes the megapixel check but not the width limit
+        match check_dimensions(50000, 2, &limits) {
+            Err(FaceDetectionError::Validation { message }) => assert!(message.contains("width"), "{}", message),
+            other => panic!("expected width validation error, got {:?}", other),
+        }
+        match check_dimensions(2, 50000, &limits) {
+            Err(FaceDetectionError::Validation { message }) => assert!(message.contains("height"), "{}", message),
+            other => panic!("expected height validation error, got {:?}", other),
         }
+        match check_dimensions(10000, 10000, &limits) {
+            Err(FaceDetectionError::Validation { message }) => assert!(message.contains("MP"), "{}", message),
+            other => panic!("expected megapixel validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_probe_dimensions_reads_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("upload.jpg");
         
-        i += 3;
+        // Saved as PNG under a .jpg name, like uploads are
+        let mut bytes = Vec::new();
+        DynamicImage::new_rgb8(50000, 2)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        std::fs::write(&path, bytes).unwrap();
+        
+        assert_eq!(probe_dimensions(&path).unwrap(), (50000, 2));
     }
-    
-    result
-}
 
-/// Simple base64 decoding implementation.
-fn base64_decode(data: &str) -> Result<Vec<u8>> {
-    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
-    
-    let mut result = Vec::new();
-    let mut buffer = 0u32;
-    let mut bits = 0;
-    
-    for ch in data.chars() {
-        if ch == ' ' || ch == '\n' || ch == '\r' {
-            continue;
+    /// Encodes an 8-bit RGB TIFF with one page per size.
+    fn multi_page_tiff(sizes: &[(u32, u32)]) -> Vec<u8> {
+        use tiff::encoder::{colortype, TiffEncoder};
+        
+        let mut bytes = Vec::new();
+        let mut encoder = TiffEncoder::new(Cursor::new(&mut bytes)).unwrap();
+        for &(width, height) in sizes {
+            let data = vec![128u8; (width * height * 3) as usize];
+            encoder.write_image::<colortype::RGB8>(width, height, &data).unwrap();
+        }
+        bytes
+    }
+
+    /// Dimension limits generous enough for every test image.
+    fn test_limits() -> DimensionLimits {
+        crate::config::AppConfig::default().dimension_limits()
+    }
+
+    #[test]
+    fn test_decode_frame_selects_tiff_page() {
+        let bytes = multi_page_tiff(&[(10, 20), (30, 40), (50, 60)]);
+        
+        for (page, size) in [(0, (10, 20)), (1, (30, 40)), (2, (50, 60))] {
+            assert_eq!(decode_frame(&bytes, page, &test_limits()).unwrap().dimensions(), size);
         }
+        assert!(matches!(
+            decode_frame(&bytes, 3, &test_limits()),
+            Err(FaceDetectionError::Validation { message }) if message.contains("3 frames")
+        ));
+    }
+
+    #[test]
+    fn test_decode_frame_checks_later_tiff_pages_against_limits() {
+        let bytes = multi_page_tiff(&[(10, 20), (300, 20)]);
+        let limits = DimensionLimits {
+            max_width: 100,
+            ..test_limits()
+        };
+        
+        assert!(decode_frame(&bytes, 0, &limits).is_ok());
+        assert!(matches!(
+            decode_frame(&bytes, 1, &limits),
+            Err(FaceDetectionError::Validation { message }) if message.contains("width 300")
+        ));
+    }
+
+    #[test]
+    fn test_decode_frame_selects_gif_frame() {
+        use image::codecs::gif::GifEncoder;
+        use image::{Delay, Frame, Rgba, RgbaImage};
+        
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            for shade in [0u8, 100, 200] {
+                let buffer = RgbaImage::from_pixel(8, 8, Rgba([shade, shade, shade, 255]));
+                encoder.encode_frame(Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(10, 1))).unwrap();
+            }
+        }
+        
+        let frame = decode_frame(&bytes, 1, &test_limits()).unwrap().to_rgba8();
+        assert_eq!(frame.get_pixel(0, 0)[0], 100);
+        assert!(matches!(
+            decode_frame(&bytes, 3, &test_limits()),
+            Err(FaceDetectionError::Validation { message }) if message.contains("3 frames")
+        ));
+    }
+
+    #[test]
+    fn test_decode_frame_single_frame_formats() {
+        let mut png = Vec::new();
+        DynamicImage::new_rgb8(4, 4)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        
+        assert_eq!(decode_frame(&png, 0, &test_limits()).unwrap().dimensions(), (4, 4));
+        assert!(matches!(
+            decode_frame(&png, 1, &test_limits()),
+            Err(FaceDetectionError::Validation { message }) if message.contains("1 frame")
+        ));
+    }
+
+    #[test]
+    fn test_decode_base64_roundtrip() {
+        let bytes = b"face-detect-rust";
+        let encoded = format!("data:image/png;base64,{}", STANDARD.encode(bytes));
+        assert_eq!(decode_base64_image(&encoded).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_decode_base64_ignores_whitespace() {
+        let decoded = decode_base64_image("aGVs\nbG8g\r\nd29y bGQ=").unwrap();
+        assert_eq!(decoded, b"hello world");
+    }
+
+    #[test]
+    fn test_decode_base64_url_safe_without_padding() {
+        use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        
+        // Bytes whose encoding uses both URL-safe characters and needs padding
+        let bytes = [0xfb, 0xff, 0xbf, 0xfe, 0x10];
+        let encoded = URL_SAFE_NO_PAD.encode(bytes);
+        assert!(encoded.contains('-') && encoded.contains('_') && encoded.len() % 4 != 0);
         
-        if ch == '=' {
-            break;
+        let data_uri = format!("data:image/jpeg;base64,{}", encoded);
+        assert_eq!(decode_base64_image(&data_uri).unwrap(), bytes);
+        assert_eq!(decode_base64_image("aGVsbG8").unwrap(), b"hello");
+        assert_eq!(decode_base64_image("-_-_").unwrap(), [0xfb, 0xff, 0xbf]);
+    }
+
+    #[test]
+    fn test_decode_base64_regressions() {
+        let rejected = [
+            "====",
+            "a===",
+            "aGVsbG8=extra",
+            "aGVsbG8===",
+            "aGVs\u{0}bG8=",
+            "aGVsbG8\u{e9}=",
+            "🙂🙂🙂🙂",
+            "data:image/jpeg;base64,@@@@",
+            "-_+/",
+        ];
+        for input in rejected {
+            assert!(
+                matches!(decode_base64_image(input), Err(FaceDetectionError::Base64Error)),
+                "{:?} should be rejected",
+                input
+            );
         }
         
-        let value = CHARS.iter().position(|&c| c == ch as u8)
-            .ok_or(FaceDetectionError::Base64Error)? as u32;
+        assert_eq!(decode_base64_image("").unwrap(), Vec::<u8>::new());
+        assert_eq!(decode_base64_image("data:image/jpeg;base64,").unwrap(), Vec::<u8>::new());
+    }
+
+    fn wide_gamut_profile() -> lcms2::Profile {
+        use lcms2::{CIExyY, CIExyYTRIPLE, Profile, ToneCurve};
+        
+        let white = CIExyY { x: 0.3127, y: 0.3290, Y: 1.0 };
+        let primaries = CIExyYTRIPLE {
+            Red: CIExyY { x: 0.64, y: 0.33, Y: 1.0 },
+            Green: CIExyY { x: 0.21, y: 0.71, Y: 1.0 },
+            Blue: CIExyY { x: 0.15, y: 0.06, Y: 1.0 },
+        };
+        let curve = ToneCurve::new(2.2);
+        Profile::new_rgb(&white, &primaries, &[&curve, &curve, &curve]).unwrap()
+    }
+
+    fn encode_png_with_profile(image: &image::RgbImage, icc: &[u8]) -> Vec<u8> {
+        let mut info = png::Info::with_size(image.width(), image.height());
+        info.color_type = png::ColorType::Rgb;
+        info.bit_depth = png::BitDepth::Eight;
+        info.icc_profile = Some(icc.to_vec().into());
+        
+        let mut bytes = Vec::new();
+        let mut writer = png::Encoder::with_info(&mut bytes, info)
+            .unwrap()
+            .write_header()
+            .unwrap();
+        writer.write_image_data(image.as_raw()).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_load_image_srgb_converts_tagged_image() {
+        use lcms2::{Intent, PixelFormat, Profile, Transform};
         
-        buffer = (buffer << 6) | value;
-        bits += 6;
+        let profile = wide_gamut_profile();
+        let source = image::RgbImage::from_pixel(8, 8, image::Rgb([40, 200, 90]));
+        let bytes = encode_png_with_profile(&source, &profile.icc().unwrap());
         
-        if bits >= 8 {
-            bits -= 8;
-            result.push((buffer >> bits) as u8);
+        assert!(extract_icc_profile(&bytes).is_some());
+        
+        let transform = Transform::<[u8; 3], [u8; 3]>::new(
+            &profile,
+            PixelFormat::RGB_8,
+            &Profile::new_srgb(),
+            PixelFormat::RGB_8,
+            Intent::Perceptual,
+        )
+        .unwrap();
+        let mut expected = [[40, 200, 90]];
+        transform.transform_in_place(&mut expected);
+        
+        let converted = load_image_srgb(&bytes).unwrap().to_rgba8();
+        let pixel = converted.get_pixel(3, 3).0;
+        assert_eq!(&pixel[..3], &expected[0][..]);
+        assert_eq!(pixel[3], 255);
+        assert_ne!(&pixel[..3], &[40, 200, 90][..]);
+    }
+
+    #[test]
+    fn test_load_image_srgb_passes_through_untagged_image() {
+        let source = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(4, 4, image::Rgb([40, 200, 90])));
+        let mut bytes = Vec::new();
+        source.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png).unwrap();
+        
+        assert!(extract_icc_profile(&bytes).is_none());
+        let loaded = load_image_srgb(&bytes).unwrap();
+        assert_eq!(loaded.to_rgb8().get_pixel(0, 0).0, [40, 200, 90]);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_decode_never_panics(input in "\\PC*") {
+            assert_ok_or_base64_error(&input);
+        }
+
+        #[test]
+        fn prop_decode_near_valid_never_panics(input in "(data:image/(jpeg|png);base64,)?[A-Za-z0-9+/= \r\n\t-]{0,64}") {
+            assert_ok_or_base64_error(&input);
+        }
+
+        #[test]
+        fn prop_decode_roundtrips_encoded_bytes(bytes in proptest::collection::vec(any::<u8>(), 0..256)) {
+            let encoded = STANDARD.encode(&bytes);
+            prop_assert_eq!(decode_base64_image(&encoded).unwrap(), bytes);
         }
     }
-    
-    Ok(result)
-}
\ No newline at end of file
+}
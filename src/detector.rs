@@ -4,14 +4,47 @@
 //! implementation that simulates face detection results based on image dimensions.
 //! In production, this would be replaced with actual ML models or OpenCV integration.
 
-use crate::error::{FaceDetectionError, Result};
-use crate::types::{DetectionResult, Face};
-use image::{DynamicImage, ImageBuffer, Rgb};
-use imageproc::drawing::{draw_hollow_rect_mut, draw_text_mut};
+use crate::backend::AsyncDetector;
+use crate::detection::{rotate_clockwise, unrotate_box, AUTO_ORIENT_ROTATIONS};
+use crate::error::{config_error, FaceDetectionError, Result};
+use crate::types::{BoundingBox, ConfidenceFormat, CoordinateFormat, DetectionResult, DrawOptions, Face, RejectedFace, RejectionReason, WatermarkPosition};
+use image::{DynamicImage, ImageBuffer, Rgb, Rgba, RgbaImage};
+use imageproc::drawing::{draw_filled_rect_mut, draw_hollow_rect_mut, draw_text_mut, text_size};
 use imageproc::rect::Rect;
+use serde::Deserialize;
 use std::path::Path;
+use std::sync::Arc;
 use std::time::Instant;
 
+/// Side length of the synthetic image used by [`FaceDetector::warmup`].
+const WARMUP_IMAGE_SIZE: u32 = 64;
+
+/// Distance from the image border, in pixels, within which a face box counts
+/// as truncated.
+const TRUNCATION_MARGIN: u32 = 2;
+
+/// Height in pixels of face labels.
+const LABEL_SCALE: f32 = 20.0;
+
+/// How far above the box's top edge labels start, in pixels.
+const LABEL_OFFSET: i32 = 10;
+
+/// Radius in pixels of the dots drawn at face landmarks.
+const LANDMARK_RADIUS: u32 = 2;
+
+/// Watermark text height as a fraction of the image's shorter side.
+const WATERMARK_SCALE_RATIO: f32 = 0.04;
+
+/// Smallest watermark text height in pixels.
+const WATERMARK_MIN_SCALE: f32 = 10.0;
+
+/// Confidences assigned to the mock faces, in generation order.
+const DEFAULT_MOCK_CONFIDENCES: [f32; 3] = [0.95, 0.87, 0.92];
+
+/// Model version reported by the mock detection, tied to the crate version
+/// since the mock's behavior only changes with the code.
+pub const MOCK_MODEL_VERSION: &str = concat!("mock-", env!("CARGO_PKG_VERSION"));
+
 /// Face detector that performs mock face detection based on image dimensions.
 /// 
 /// This is a demonstration implementation that creates mock face detections
@@ -23,9 +56,367 @@ pub struct FaceDetector {
     min_dimension: u32,
     /// Confidence threshold for detections.
     confidence_threshold: f32,
+    /// Confidences assigned to the generated mock faces, in generation order.
+    mock_confidences: Vec<f32>,
+    /// Whether exact-duplicate boxes are collapsed before returning.
+    dedupe_exact: bool,
+    /// Smallest face box area, in pixels, that is reported.
+    min_face_area: u64,
+    /// Largest face box area, in pixels, that is reported.
+    max_face_area: u64,
+    /// Smallest and largest width/height ratio of a reported face box.
+    aspect_ratio_bounds: (f32, f32),
+    /// Whether the backend sees a grayscale copy of the input.
+    grayscale: bool,
+    /// Whether rotated copies are tried when no face is found.
+    auto_orient: bool,
+    /// Fraction of skin-toned pixels a mock face needs; zero disables the check.
+    min_skin_ratio: f32,
+    /// Most faces reported, keeping the most confident; unlimited when unset.
+    max_faces: Option<usize>,
+    /// IoU above which overlapping faces are merged by non-maximum
+    /// suppression; only exact duplicates are merged when unset.
+    nms_iou: Option<f32>,
+    /// Longest side, in pixels, images are downscaled to before detection;
+    /// zero disables downscaling.
+    max_detection_dimension: u32,
+    /// Images of at most this many megapixels are never downscaled.
+    downscale_min_megapixels: f64,
+    /// Decimal places result confidences are rounded to.
+    confidence_precision: u32,
+    /// Whether result boxes are rounded to whole pixels.
+    coordinate_format: CoordinateFormat,
+}
+
+/// Detection parameters loaded from a JSON file named by `DETECTOR_CONFIG_PATH`.
+/// 
+/// Every field is optional; parameters left out keep the detector's
+/// defaults. Unknown fields are rejected so typos do not go unnoticed.
+/// 
+/// ```json
+/// { "min_dimension": 150, "confidence_threshold": 0.7, "min_face_area": 1024, "max_faces": 10, "nms_iou": 0.4 }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DetectorConfig {
+    /// Minimum image dimension to consider for detection.
+    pub min_dimension: Option<u32>,
+    /// Confidence threshold for detections.
+    pub confidence_threshold: Option<f32>,
+    /// Smallest face box area, in pixels, that is reported.
+    pub min_face_area: Option<u64>,
+    /// Most faces reported.
+    pub max_faces: Option<usize>,
+    /// IoU above which overlapping faces are merged.
+    pub nms_iou: Option<f32>,
+}
+
+impl DetectorConfig {
+    /// Reads a detector config from a JSON file.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns `FaceDetectionError::Configuration` if the file cannot be read
+    /// or is not a valid config.
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| config_error(format!("Failed to read detector config {}: {}", path.display(), e)))?;
+        serde_json::from_str(&json)
+            .map_err(|e| config_error(format!("Invalid detector config {}: {}", path.display(), e)))
+    }
+}
+
+/// Builder for [`FaceDetector`] that validates its settings.
+/// 
+/// Unlike the `with_*` setters on `FaceDetector`, which clamp or accept any
+/// value, [`FaceDetectorBuilder::build`] rejects inconsistent settings.
+/// 
+/// # Example
+/// 
+/// ```no_run
+/// use face_detect_rust::detector::FaceDetector;
+/// 
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let detector = FaceDetector::builder()
+///     .confidence_threshold(0.8)
+///     .face_area_range(32 * 32, 512 * 512)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FaceDetectorBuilder {
+    /// Minimum image dimension to consider for detection.
+    min_dimension: u32,
+    /// Confidence threshold for detections.
+    confidence_threshold: f32,
+    /// Confidences assigned to the generated mock faces.
+    mock_confidences: Vec<f32>,
+    /// Whether exact-duplicate boxes are collapsed.
+    dedupe_exact: bool,
+    /// Smallest reported face box area.
+    min_face_area: u64,
+    /// Largest reported face box area.
+    max_face_area: u64,
+    /// Smallest and largest reported width/height ratio.
+    aspect_ratio_bounds: (f32, f32),
+    /// Whether the backend sees a grayscale copy of the input.
+    grayscale: bool,
+    /// Whether rotated copies are tried when no face is found.
+    auto_orient: bool,
+    /// Fraction of skin-toned pixels a mock face needs.
+    min_skin_ratio: f32,
+    /// Most faces reported.
+    max_faces: Option<usize>,
+    /// IoU above which overlapping faces are merged.
+    nms_iou: Option<f32>,
+    /// Longest side images are downscaled to before detection.
+    max_detection_dimension: u32,
+    /// Images of at most this many megapixels are never downscaled.
+    downscale_min_megapixels: f64,
+    /// Decimal places result confidences are rounded to.
+    confidence_precision: u32,
+    /// Whether result boxes are rounded to whole pixels.
+    coordinate_format: CoordinateFormat,
+}
+
+impl Default for FaceDetectorBuilder {
+    fn default() -> Self {
+        Self {
+            min_dimension: 200,
+            confidence_threshold: 0.5,
+            mock_confidences: DEFAULT_MOCK_CONFIDENCES.to_vec(),
+            dedupe_exact: true,
+            min_face_area: 0,
+            max_face_area: u64::MAX,
+            aspect_ratio_bounds: (0.0, f32::INFINITY),
+            grayscale: false,
+            auto_orient: false,
+            min_skin_ratio: 0.0,
+            max_faces: None,
+            nms_iou: None,
+            max_detection_dimension: 0,
+            downscale_min_megapixels: 0.0,
+            confidence_precision: crate::types::DEFAULT_CONFIDENCE_PRECISION,
+            coordinate_format: CoordinateFormat::Integer,
+        }
+    }
+}
+
+impl FaceDetectorBuilder {
+    /// Sets the minimum image dimension; must be positive.
+    pub fn min_dimension(mut self, min_dimension: u32) -> Self {
+        self.min_dimension = min_dimension;
+        self
+    }
+
+    /// Sets the confidence threshold; must be within `0.0..=1.0`.
+    pub fn confidence_threshold(mut self, threshold: f32) -> Self {
+        self.confidence_threshold = threshold;
+        self
+    }
+
+    /// Sets the confidences assigned to the generated mock faces.
+    pub fn mock_confidences(mut self, confidences: Vec<f32>) -> Self {
+        self.mock_confidences = confidences;
+        self
+    }
+
+    /// Sets whether exact-duplicate boxes are collapsed.
+    pub fn dedupe_exact(mut self, enabled: bool) -> Self {
+        self.dedupe_exact = enabled;
+        self
+    }
+
+    /// Sets the range of face box areas, in pixels, that are reported.
+    pub fn face_area_range(mut self, min: u64, max: u64) -> Self {
+        self.min_face_area = min;
+        self.max_face_area = max;
+        self
+    }
+
+    /// Sets the range of width/height ratios of face boxes that are reported.
+    /// 
+    /// Detectors occasionally emit implausibly wide or tall boxes; bounds such
+    /// as `0.5..=2.0` drop them. The default range accepts every box.
+    pub fn aspect_ratio_range(mut self, min: f32, max: f32) -> Self {
+        self.aspect_ratio_bounds = (min, max);
+        self
+    }
+
+    /// Sets whether detection runs on a grayscale copy of the input.
+    pub fn grayscale_detection(mut self, enabled: bool) -> Self {
+        self.grayscale = enabled;
+        self
+    }
+
+    /// Sets whether rotated copies of the input are tried when no face is found.
+    pub fn auto_orient(mut self, enabled: bool) -> Self {
+        self.auto_orient = enabled;
+        self
+    }
+
+    /// Sets the fraction of skin-toned pixels a mock face needs; must be
+    /// within `0.0..=1.0` and cannot be combined with grayscale detection.
+    pub fn min_skin_ratio(mut self, ratio: f32) -> Self {
+        self.min_skin_ratio = ratio;
+        self
+    }
+
+    /// Sets the most faces reported; must be positive.
+    pub fn max_faces(mut self, max_faces: usize) -> Self {
+        self.max_faces = Some(max_faces);
+        self
+    }
+
+    /// Merges faces overlapping by more than `iou` with non-maximum
+    /// suppression; must be within `0.0..=1.0`.
+    pub fn nms_iou(mut self, iou: f32) -> Self {
+        self.nms_iou = Some(iou);
+        self
+    }
+
+    /// Sets the longest side, in pixels, images are downscaled to before
+    /// detection; zero disables downscaling.
+    pub fn max_detection_dimension(mut self, max_dimension: u32) -> Self {
+        self.max_detection_dimension = max_dimension;
+        self
+    }
+
+    /// Sets the size, in megapixels, an image must exceed before it is
+    /// downscaled; must be zero or positive.
+    pub fn downscale_min_megapixels(mut self, megapixels: f64) -> Self {
+        self.downscale_min_megapixels = megapixels;
+        self
+    }
+
+    /// Rounds result confidences to `decimals` decimal places.
+    pub fn confidence_precision(mut self, decimals: u32) -> Self {
+        self.confidence_precision = decimals;
+        self
+    }
+
+    /// Sets whether result boxes are rounded to whole pixels or keep
+    /// sub-pixel precision.
+    pub fn coordinate_format(mut self, format: CoordinateFormat) -> Self {
+        self.coordinate_format = format;
+        self
+    }
+
+    /// Applies the parameters set in `config`, leaving the others unchanged.
+    /// 
+    /// The values are validated by [`FaceDetectorBuilder::build`] like any
+    /// other setting.
+    pub fn with_detector_config(mut self, config: &DetectorConfig) -> Self {
+        if let Some(min_dimension) = config.min_dimension {
+            self.min_dimension = min_dimension;
+        }
+        if let Some(threshold) = config.confidence_threshold {
+            self.confidence_threshold = threshold;
+        }
+        if let Some(min_face_area) = config.min_face_area {
+            self.min_face_area = min_face_area;
+        }
+        if config.max_faces.is_some() {
+            self.max_faces = config.max_faces;
+        }
+        if config.nms_iou.is_some() {
+            self.nms_iou = config.nms_iou;
+        }
+        self
+    }
+
+    /// Validates the settings and creates the detector.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns `FaceDetectionError::Configuration` if the confidence threshold
+    /// is outside `0.0..=1.0`, the minimum dimension is zero, the minimum
+    /// face area or aspect ratio exceeds the maximum, an aspect ratio bound is
+    /// NaN, the skin ratio is
+    /// outside `0.0..=1.0` or set together with grayscale detection, the face
+    /// limit is zero, the NMS IoU is outside `0.0..=1.0`, or the downscale
+    /// trigger is negative or NaN.
+    pub fn build(self) -> Result<FaceDetector> {
+        if !(0.0..=1.0).contains(&self.confidence_threshold) {
+            return Err(config_error(format!(
+                "Confidence threshold {} must be between 0 and 1",
+                self.confidence_threshold
+            )));
+        }
+        
+        if self.min_dimension == 0 {
+            return Err(config_error("Minimum dimension must be positive"));
+        }
+        
+        if self.min_face_area > self.max_face_area {
+            return Err(config_error(format!(
+                "Minimum face area {} exceeds maximum face area {}",
+                self.min_face_area, self.max_face_area
+            )));
+        }
+        
+        let (min_ratio, max_ratio) = self.aspect_ratio_bounds;
+        if min_ratio.is_nan() || max_ratio.is_nan() || min_ratio > max_ratio {
+            return Err(config_error(format!(
+                "Minimum aspect ratio {} exceeds maximum aspect ratio {}",
+                min_ratio, max_ratio
+            )));
+        }
+        
+        if !(0.0..=1.0).contains(&self.min_skin_ratio) {
+            return Err(config_error(format!(
+                "Minimum skin ratio {} must be between 0 and 1",
+                self.min_skin_ratio
+            )));
+        }
+        
+        // A grayscale copy has no skin tones left to find
+        if self.min_skin_ratio > 0.0 && self.grayscale {
+            return Err(config_error("Skin ratio check cannot be combined with grayscale detection"));
+        }
+        
+        if self.max_faces == Some(0) {
+            return Err(config_error("Maximum face count must be positive"));
+        }
+        
+        if let Some(iou) = self.nms_iou.filter(|iou| !(0.0..=1.0).contains(iou)) {
+            return Err(config_error(format!("NMS IoU {} must be between 0 and 1", iou)));
+        }
+        
+        if self.downscale_min_megapixels.is_nan() || self.downscale_min_megapixels < 0.0 {
+            return Err(config_error(format!(
+                "Downscale trigger {} megapixels must not be negative",
+                self.downscale_min_megapixels
+            )));
+        }
+        
+        Ok(FaceDetector {
+            min_dimension: self.min_dimension,
+            confidence_threshold: self.confidence_threshold,
+            mock_confidences: self.mock_confidences,
+            dedupe_exact: self.dedupe_exact,
+            min_face_area: self.min_face_area,
+            max_face_area: self.max_face_area,
+            aspect_ratio_bounds: self.aspect_ratio_bounds,
+            grayscale: self.grayscale,
+            auto_orient: self.auto_orient,
+            min_skin_ratio: self.min_skin_ratio,
+            max_faces: self.max_faces,
+            nms_iou: self.nms_iou,
+            max_detection_dimension: self.max_detection_dimension,
+            downscale_min_megapixels: self.downscale_min_megapixels,
+            confidence_precision: self.confidence_precision,
+            coordinate_format: self.coordinate_format,
+        })
+    }
 }
 
 impl FaceDetector {
+    /// Version of the model producing detections, recorded on every result
+    /// so stored results can be traced back to it.
+    pub const MODEL_VERSION: &'static str = MOCK_MODEL_VERSION;
+
     /// Creates a new face detector with default settings.
     /// 
     /// # Returns
@@ -38,10 +429,12 @@ impl FaceDetector {
     pub fn new() -> Result<Self> {
         tracing::info!("Initializing FaceDetector with mock implementation");
         
-        Ok(Self {
-            min_dimension: 200,
-            confidence_threshold: 0.5,
-        })
+        FaceDetectorBuilder::default().build()
+    }
+
+    /// Returns a builder for a detector with validated settings.
+    pub fn builder() -> FaceDetectorBuilder {
+        FaceDetectorBuilder::default()
     }
 
     /// Performs face detection on an image file.
@@ -58,30 +451,315 @@ impl FaceDetector {
     /// 
     /// Returns an error if the image cannot be loaded or processed.
     pub fn detect_faces(&self, image_path: &Path) -> Result<DetectionResult> {
-        let start_time = Instant::now();
-        
         tracing::info!("Starting face detection for: {:?}", image_path);
         
         // Load the image
-        let img = image::open(image_path)
-            .map_err(|e| FaceDetectionError::ImageProcessing { source: e })?;
+        let img = crate::detection::open_image(image_path)?;
+        
+        self.detect(&img)
+    }
+
+    /// Performs face detection on encoded image bytes.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `bytes` - Encoded image data in one of the supported input formats
+    /// 
+    /// # Returns
+    /// 
+    /// A `DetectionResult` containing detected faces and processing information.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error if the bytes cannot be decoded or the image processed.
+    pub fn detect_bytes(&self, bytes: &[u8]) -> Result<DetectionResult> {
+        let img = crate::detection::decode_image(bytes)?;
+        
+        self.detect(&img)
+    }
+
+    /// Performs face detection on an already decoded image.
+    /// 
+    /// This is the core detection method: [`FaceDetector::detect_faces`] and
+    /// [`FaceDetector::detect_bytes`] decode their input and call it, so
+    /// callers already holding a `DynamicImage` can skip decoding.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `image` - The image to analyze
+    /// 
+    /// # Returns
+    /// 
+    /// A `DetectionResult` containing detected faces and processing information.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns `FaceDetectionError::InvalidImageData` for an image with a
+    /// zero width or height, or an error if the image cannot be processed.
+    pub fn detect(&self, image: &DynamicImage) -> Result<DetectionResult> {
+        self.detect_image_recording(image, false)
+    }
+
+    /// Performs face detection on an already decoded image.
+    /// 
+    /// Same as [`FaceDetector::detect`].
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error if the image cannot be processed.
+    pub fn detect_image(&self, img: &DynamicImage) -> Result<DetectionResult> {
+        self.detect(img)
+    }
+
+    /// Like [`FaceDetector::detect_image`], but also reports the candidates
+    /// dropped by the filters in `DetectionResult::rejected`.
+    /// 
+    /// Only candidates from the unrotated image are reported; rotations tried
+    /// by auto-orientation are not.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error if the image cannot be processed.
+    pub fn detect_image_with_rejections(&self, img: &DynamicImage) -> Result<DetectionResult> {
+        self.detect_image_recording(img, true)
+    }
+
+    /// Runs detection with the built-in backend, keeping the rejected
+    /// candidates when `record` is set.
+    fn detect_image_recording(&self, img: &DynamicImage, record: bool) -> Result<DetectionResult> {
+        let start_time = Instant::now();
+        check_not_empty(img)?;
+        
+        let (mut faces, rejected) = self.filter_candidates(self.detect_raw(img)?);
+        let mut applied_rotation = None;
+        if faces.is_empty() && self.auto_orient {
+            let detect = |input: &DynamicImage| Ok(self.filter_candidates(self.detect_raw(input)?).0);
+            if let Some((degrees, rotated_faces)) = detect_rotated(img, detect)? {
+                faces = rotated_faces;
+                applied_rotation = Some(degrees);
+            }
+        }
+        
+        let result = self.finish_detection(img, faces, rejected, applied_rotation, record, start_time);
+        Ok(result.with_model_version(Self::MODEL_VERSION))
+    }
+
+    /// Runs detection with `backend` and applies this detector's filters to
+    /// the faces it returns.
+    /// 
+    /// The filters are the same whichever backend finds the candidates: the
+    /// confidence threshold, face area and aspect ratio bounds, duplicate
+    /// suppression and the face limit, with auto-orientation retrying the
+    /// backend on rotated copies when nothing passes. The result carries the
+    /// backend's model version.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns `FaceDetectionError::InvalidImageData` for an image with a
+    /// zero width or height, or the backend's error.
+    pub async fn detect_with(
+        &self,
+        backend: &dyn AsyncDetector,
+        img: &Arc<DynamicImage>,
+        record: bool,
+    ) -> Result<DetectionResult> {
+        let start_time = Instant::now();
+        check_not_empty(img)?;
         
-        // Get image dimensions
+        let (mut faces, rejected) = self.filter_candidates(backend.detect(Arc::clone(img)).await?);
+        let mut applied_rotation = None;
+        if faces.is_empty() && self.auto_orient {
+            for degrees in AUTO_ORIENT_ROTATIONS {
+                let rotated = Arc::new(rotate_clockwise(img, degrees));
+                let (rotated_faces, _) = self.filter_candidates(backend.detect(rotated).await?);
+                if !rotated_faces.is_empty() {
+                    tracing::info!("Found {} faces after rotating {} degrees", rotated_faces.len(), degrees);
+                    faces = unrotate_faces(rotated_faces, degrees, img.width(), img.height());
+                    applied_rotation = Some(degrees);
+                    break;
+                }
+            }
+        }
+        
+        let result = self.finish_detection(img, faces, rejected, applied_rotation, record, start_time);
+        Ok(result.with_model_version(backend.model_version()))
+    }
+
+    /// Runs the built-in backend on `img`, or on a grayscale copy when
+    /// grayscale detection is enabled, without filtering its candidates.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns the backend's error.
+    pub fn detect_raw(&self, img: &DynamicImage) -> Result<Vec<Face>> {
+        // Conversion keeps the dimensions, so boxes stay valid on the color image
+        if self.grayscale {
+            self.run_backend_downscaled(&DynamicImage::ImageLuma8(img.to_luma8()))
+        } else {
+            self.run_backend_downscaled(img)
+        }
+    }
+
+    /// Splits backend candidates into the faces passing the per-face filters
+    /// and the rejected ones.
+    fn filter_candidates(&self, candidates: Vec<Face>) -> (Vec<Face>, Vec<RejectedFace>) {
+        let mut rejected = Vec::new();
+        let faces = candidates
+            .into_iter()
+            .filter(|face| match self.rejection_reason(face) {
+                Some(reason) => {
+                    rejected.push(RejectedFace::new(face, reason));
+                    false
+                }
+                None => true,
+            })
+            .collect();
+        (faces, rejected)
+    }
+
+    /// Builds the result from the faces passing the per-face filters,
+    /// suppressing duplicates, applying the face limit and rounding the
+    /// faces to the configured output precision.
+    fn finish_detection(
+        &self,
+        img: &DynamicImage,
+        mut faces: Vec<Face>,
+        mut rejected: Vec<RejectedFace>,
+        applied_rotation: Option<u16>,
+        record: bool,
+        start_time: Instant,
+    ) -> DetectionResult {
         let (width, height) = img.dimensions();
-        tracing::info!("Image dimensions: {}x{}", width, height);
+        mark_truncated(&mut faces, width, height);
         
-        // Perform mock detection based on image size
-        let faces = self.perform_mock_detection(width, height);
+        let mut result = DetectionResult::new(faces, 0).with_image_dimensions(width, height);
+        result.applied_rotation = applied_rotation;
+        if self.dedupe_exact {
+            let suppressed = result.dedupe_exact();
+            rejected.extend(suppressed.iter().map(|face| RejectedFace::new(face, RejectionReason::NmsSuppressed)));
+        }
+        if let Some(iou) = self.nms_iou {
+            let suppressed = result.apply_nms(iou);
+            rejected.extend(suppressed.iter().map(|face| RejectedFace::new(face, RejectionReason::NmsSuppressed)));
+        }
+        if let Some(max_faces) = self.max_faces {
+            let dropped = result.keep_most_confident(max_faces);
+            rejected.extend(dropped.iter().map(|face| RejectedFace::new(face, RejectionReason::MaxFaces)));
+        }
+        if record {
+            result.rejected = Some(rejected);
+        }
+        result.round_for_output(self.confidence_precision, self.coordinate_format);
         
-        let processing_time = start_time.elapsed().as_millis() as u64;
+        result.processing_time_ms = start_time.elapsed().as_millis() as u64;
         
         tracing::info!(
             "Detection completed: {} faces found in {}ms", 
-            faces.len(), 
-            processing_time
+            result.total_faces, 
+            result.processing_time_ms
+        );
+        
+        result
+    }
+
+    /// Forces the detection backend to initialize before the first request.
+    /// 
+    /// Runs one detection on a small synthetic image so lazily loaded model
+    /// state is ready, keeping that cost out of the first request's latency.
+    /// The mock backend has nothing to load, so for it this is effectively a
+    /// no-op.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns the backend's error if the warmup detection fails.
+    pub fn warmup(&self) -> Result<()> {
+        let synthetic = DynamicImage::new_rgb8(WARMUP_IMAGE_SIZE, WARMUP_IMAGE_SIZE);
+        self.run_backend(&synthetic)?;
+        Ok(())
+    }
+
+    /// Returns the filter a candidate face fails, if any.
+    fn rejection_reason(&self, face: &Face) -> Option<RejectionReason> {
+        if face.confidence < self.confidence_threshold {
+            Some(RejectionReason::BelowThreshold)
+        } else if face.area() < self.min_face_area {
+            Some(RejectionReason::TooSmall)
+        } else if face.area() > self.max_face_area {
+            Some(RejectionReason::TooLarge)
+        } else if !self.has_plausible_aspect_ratio(face) {
+            Some(RejectionReason::AspectRatio)
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether a face box's width/height ratio is within the bounds.
+    fn has_plausible_aspect_ratio(&self, face: &Face) -> bool {
+        let (min, max) = self.aspect_ratio_bounds;
+        (min..=max).contains(&face.bbox.aspect_ratio())
+    }
+
+    /// Runs the detection backend on `img`, on a downscaled copy when the
+    /// image is large enough, with the boxes mapped back onto `img`.
+    /// 
+    /// Filters run afterwards, so face area limits always apply to
+    /// original-resolution boxes.
+    fn run_backend_downscaled(&self, img: &DynamicImage) -> Result<Vec<Face>> {
+        let (width, height) = img.dimensions();
+        let Some((small_width, small_height)) = self.detection_size(width, height) else {
+            return self.run_backend(img);
+        };
+        
+        tracing::debug!(
+            "Downscaling {}x{} image to {}x{} for detection",
+            width, height, small_width, small_height
         );
+        let small = img.resize_exact(small_width, small_height, image::imageops::FilterType::Triangle);
+        let (scale_x, scale_y) = (width as f32 / small_width as f32, height as f32 / small_height as f32);
+        
+        let mut faces = self.run_backend(&small)?;
+        for face in &mut faces {
+            face.bbox = BoundingBox {
+                x: face.bbox.x * scale_x,
+                y: face.bbox.y * scale_y,
+                width: face.bbox.width * scale_x,
+                height: face.bbox.height * scale_y,
+            };
+        }
+        Ok(faces)
+    }
+
+    /// Returns the size a `width` x `height` image is detected at, or `None`
+    /// if it is detected at native resolution.
+    /// 
+    /// Downscaling needs a maximum detection dimension, a longer side above
+    /// it, and more than `downscale_min_megapixels` of pixels.
+    fn detection_size(&self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let longer = width.max(height);
+        let megapixels = f64::from(width) * f64::from(height) / 1_000_000.0;
+        if self.max_detection_dimension == 0
+            || longer <= self.max_detection_dimension
+            || megapixels <= self.downscale_min_megapixels
+        {
+            return None;
+        }
         
-        Ok(DetectionResult::new(faces, processing_time))
+        let ratio = f64::from(self.max_detection_dimension) / f64::from(longer);
+        let scaled = |side: u32| ((f64::from(side) * ratio).round() as u32).max(1);
+        Some((scaled(width), scaled(height)))
+    }
+
+    /// Runs the detection backend on `img`.
+    /// 
+    /// The mock backend places faces by the image dimensions, then drops
+    /// those without enough skin tones when a minimum skin ratio is set.
+    fn run_backend(&self, img: &DynamicImage) -> Result<Vec<Face>> {
+        let (width, height) = img.dimensions();
+        let mut faces = self.perform_mock_detection(width, height);
+        if self.min_skin_ratio > 0.0 {
+            faces.retain(|face| crate::detection::skin_ratio(img, &face.bbox) >= self.min_skin_ratio);
+        }
+        Ok(faces)
     }
 
     /// Creates mock face detections based on image dimensions.
@@ -99,7 +777,7 @@ impl FaceDetector {
                 img_height / 4,
                 img_width / 4,
                 img_height / 4,
-                0.95,
+                self.mock_confidence(0),
             ));
         }
         
@@ -109,7 +787,7 @@ impl FaceDetector {
                 img_height / 3,
                 img_width / 5,
                 img_height / 5,
-                0.87,
+                self.mock_confidence(1),
             ));
         }
         
@@ -119,13 +797,24 @@ impl FaceDetector {
                 img_height * 2 / 3,
                 img_width / 6,
                 img_height / 6,
-                0.92,
+                self.mock_confidence(2),
             ));
         }
         
+        // Tiny images with a low minimum dimension would yield empty boxes
+        faces.retain(|face| face.bbox.width >= 1.0 && face.bbox.height >= 1.0);
         faces
     }
 
+    /// Returns the confidence for the mock face at `index`, falling back to
+    /// the built-in value when none was injected.
+    fn mock_confidence(&self, index: usize) -> f32 {
+        self.mock_confidences
+            .get(index)
+            .copied()
+            .unwrap_or(DEFAULT_MOCK_CONFIDENCES[index])
+    }
+
     /// Draws bounding boxes and labels on detected faces.
     /// 
     /// # Arguments
@@ -145,28 +834,7 @@ impl FaceDetector {
         original_image: &DynamicImage,
         faces: &[Face],
     ) -> Result<DynamicImage> {
-        let mut processed_image = original_image.clone();
-        
-        for (index, face) in faces.iter().enumerate() {
-            // Draw bounding box
-            let rect = Rect::at(face.x as i32, face.y as i32)
-                .of_size(face.width, face.height);
-            draw_hollow_rect_mut(&mut processed_image, rect, Rgb([0, 255, 0]));
-            
-            // Draw confidence label
-            let label = format!("Face {}: {:.1}%", index + 1, face.confidence * 100.0);
-            draw_text_mut(
-                &mut processed_image,
-                Rgb([0, 255, 0]),
-                face.x as i32,
-                face.y as i32 - 10,
-                imageproc::definitions::Scale::uniform(20.0),
-                &imageproc::definitions::Font::default(),
-                &label,
-            );
-        }
-        
-        Ok(processed_image)
+        draw_bounding_boxes_with(original_image, faces, &DrawOptions::default())
     }
 
     /// Gets the minimum dimension requirement for detection.
@@ -179,6 +847,21 @@ impl FaceDetector {
         self.confidence_threshold
     }
 
+    /// Gets the smallest face box area, in pixels, that is reported.
+    pub fn min_face_area(&self) -> u64 {
+        self.min_face_area
+    }
+
+    /// Gets the most faces reported, if limited.
+    pub fn max_faces(&self) -> Option<usize> {
+        self.max_faces
+    }
+
+    /// Gets the IoU above which overlapping faces are merged, if set.
+    pub fn nms_iou(&self) -> Option<f32> {
+        self.nms_iou
+    }
+
     /// Sets the minimum dimension requirement.
     /// 
     /// # Arguments
@@ -198,23 +881,373 @@ impl FaceDetector {
         self.confidence_threshold = threshold.max(0.0).min(1.0);
         self
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::path::PathBuf;
 
-    #[test]
-    fn test_detector_creation() {
-        let detector = FaceDetector::new();
-        assert!(detector.is_ok(), "Detector should be created successfully");
+    /// Sets whether exact-duplicate boxes are collapsed before returning.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `enabled` - Keep only the most confident of identical boxes
+    pub fn with_dedupe_exact(mut self, enabled: bool) -> Self {
+        self.dedupe_exact = enabled;
+        self
     }
 
-    #[test]
-    fn test_mock_detection_small_image() {
-        let detector = FaceDetector::new().unwrap();
-        
+    /// Sets whether detection runs on a grayscale copy of the input.
+    /// 
+    /// Only the backend sees the grayscale copy; drawing and cropping keep
+    /// using the original color image.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `enabled` - Convert the input to `Luma8` before detection
+    pub fn with_grayscale_detection(mut self, enabled: bool) -> Self {
+        self.grayscale = enabled;
+        self
+    }
+
+    /// Sets whether rotated copies of the input are tried when no face is found.
+    /// 
+    /// When the upright image yields no faces, the image is rotated by 90,
+    /// 180 and 270 degrees in turn and the first rotation that yields faces
+    /// wins. Each retry is a full backend call, so this is off by default.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `enabled` - Retry on rotated copies when nothing is found
+    pub fn with_auto_orient(mut self, enabled: bool) -> Self {
+        self.auto_orient = enabled;
+        self
+    }
+
+    /// Sets the confidences assigned to the generated mock faces.
+    /// 
+    /// Faces beyond the end of the list keep their built-in confidence.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `confidences` - Confidence for each mock face, in generation order
+    pub fn with_mock_confidences(mut self, confidences: Vec<f32>) -> Self {
+        self.mock_confidences = confidences;
+        self
+    }
+
+    /// Sets the longest side, in pixels, images are downscaled to before
+    /// detection.
+    /// 
+    /// Detection on a smaller copy is faster on huge images at some cost in
+    /// accuracy; boxes are mapped back to the original resolution. Zero, the
+    /// default, disables downscaling. Combine with
+    /// [`FaceDetector::with_downscale_min_megapixels`] to leave small and
+    /// medium images at native resolution.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `max_dimension` - Longest side of the detection copy
+    pub fn with_max_detection_dimension(mut self, max_dimension: u32) -> Self {
+        self.max_detection_dimension = max_dimension;
+        self
+    }
+
+    /// Sets the size an image must exceed before it is downscaled for
+    /// detection.
+    /// 
+    /// Only takes effect together with
+    /// [`FaceDetector::with_max_detection_dimension`]: an image is downscaled
+    /// when its longer side is above the maximum detection dimension *and*
+    /// it has more than `megapixels` of pixels. Zero, the default, downscales
+    /// every image above the maximum dimension.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `megapixels` - Size trigger, clamped to be non-negative
+    pub fn with_downscale_min_megapixels(mut self, megapixels: f64) -> Self {
+        self.downscale_min_megapixels = megapixels.max(0.0);
+        self
+    }
+
+    /// Sets the fraction of skin-toned pixels a mock face needs.
+    /// 
+    /// The mock backend places faces by image size alone; with a positive
+    /// ratio, a face is only emitted if enough of its box passes
+    /// [`skin_ratio`](crate::detection::skin_ratio), so image content
+    /// influences the result. Zero, the default, turns the check off.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `ratio` - Required skin fraction, clamped to `0.0..=1.0`
+    pub fn with_min_skin_ratio(mut self, ratio: f32) -> Self {
+        self.min_skin_ratio = ratio.clamp(0.0, 1.0);
+        self
+    }
+}
+
+/// Retries `detect` on each rotation in [`AUTO_ORIENT_ROTATIONS`] until one
+/// finds faces.
+/// 
+/// Returns the winning clockwise rotation and its faces mapped back onto
+/// `img`, or `None` if no rotation found any.
+pub(crate) fn detect_rotated(
+    img: &DynamicImage,
+    mut detect: impl FnMut(&DynamicImage) -> Result<Vec<Face>>,
+) -> Result<Option<(u16, Vec<Face>)>> {
+    let (width, height) = img.dimensions();
+    
+    for degrees in AUTO_ORIENT_ROTATIONS {
+        let faces = detect(&rotate_clockwise(img, degrees))?;
+        if !faces.is_empty() {
+            tracing::info!("Found {} faces after rotating {} degrees", faces.len(), degrees);
+            return Ok(Some((degrees, unrotate_faces(faces, degrees, width, height))));
+        }
+    }
+    
+    Ok(None)
+}
+
+/// Maps faces found on a `width`x`height` image rotated clockwise by
+/// `degrees` back onto the unrotated image.
+fn unrotate_faces(faces: Vec<Face>, degrees: u16, width: u32, height: u32) -> Vec<Face> {
+    faces
+        .into_iter()
+        .map(|face| Face {
+            bbox: unrotate_box(face.bbox, degrees, width, height),
+            ..face
+        })
+        .collect()
+}
+
+/// Rejects an image with a zero width or height.
+/// 
+/// Crafted headers can declare an empty image; detection never expects one.
+fn check_not_empty(img: &DynamicImage) -> Result<()> {
+    let (width, height) = img.dimensions();
+    tracing::info!("Image dimensions: {}x{}", width, height);
+    if width == 0 || height == 0 {
+        return Err(FaceDetectionError::InvalidImageData);
+    }
+    Ok(())
+}
+
+/// Flags faces whose box touches or crosses the border of a `width`x`height`
+/// image, within [`TRUNCATION_MARGIN`].
+pub(crate) fn mark_truncated(faces: &mut [Face], width: u32, height: u32) {
+    for face in faces {
+        face.truncated = face.bbox.is_near_border(width, height, TRUNCATION_MARGIN);
+    }
+}
+
+/// Draws bounding boxes and labels on faces using custom drawing options.
+/// 
+/// # Arguments
+/// 
+/// * `original_image` - The original image
+/// * `faces` - Faces to draw
+/// * `options` - Color, thickness and label settings
+/// 
+/// # Returns
+/// 
+/// A new image with visual annotations, always in a truecolor format so
+/// box colors are exact, also for palette PNGs. Without faces or a
+/// watermark this is an exact copy of `original_image`, in its original
+/// color type.
+/// 
+/// # Errors
+/// 
+/// Returns a validation error if `options` are out of bounds.
+pub fn draw_bounding_boxes_with(
+    original_image: &DynamicImage,
+    faces: &[Face],
+    options: &DrawOptions,
+) -> Result<DynamicImage> {
+    options.validate()?;
+    let mut processed_image = original_image.clone();
+    if faces.is_empty() && options.watermark.is_none() {
+        return Ok(processed_image);
+    }
+    draw_faces_mut(&mut processed_image, faces, options);
+    Ok(processed_image)
+}
+
+/// Draws boxes and labels for `faces` onto `image` in place.
+/// 
+/// Drawing happens on an RGBA copy so fills can be alpha-blended. Images
+/// without an alpha channel come back as RGB8, others as RGBA8, so grayscale
+/// and high bit depth images end up truecolor too. Palette PNGs never reach
+/// this point indexed: the decoder expands them to RGB8 or RGBA8.
+pub(crate) fn draw_faces_mut(image: &mut DynamicImage, faces: &[Face], options: &DrawOptions) {
+    let mut canvas = image.to_rgba8();
+    let Rgb([r, g, b]) = options.color;
+    let color = Rgba([r, g, b, 255]);
+    let scale = imageproc::definitions::Scale::uniform(LABEL_SCALE);
+    let font = imageproc::definitions::Font::default();
+    
+    for (index, face) in faces.iter().enumerate() {
+        let bbox = face.bbox;
+        
+        // Blend the fill under the outline and label
+        if let Some((fill_color, alpha)) = options.fill {
+            fill_box(&mut canvas, bbox, fill_color, alpha);
+        }
+        
+        // Draw bounding box, one inset outline per pixel of thickness, with
+        // the box clamped to the canvas so huge client boxes can't overflow
+        let (x, y, right, bottom) = bbox.pixel_bounds(canvas.width(), canvas.height());
+        let (width, height) = (right - x, bottom - y);
+        for inset in 0..options.thickness.max(1) {
+            let span = inset.saturating_mul(2);
+            if width <= span || height <= span {
+                break;
+            }
+            let (Ok(left), Ok(top)) = (i32::try_from(x.saturating_add(inset)), i32::try_from(y.saturating_add(inset))) else {
+                break;
+            };
+            let rect = Rect::at(left, top).of_size(width - span, height - span);
+            draw_hollow_rect_mut(&mut canvas, rect, color);
+        }
+        
+        // Draw confidence label
+        if options.show_labels {
+            let label = face_label(index, face.confidence, options.confidence_format);
+            let (text_width, text_height) = text_size(scale, &font, &label);
+            let (label_x, label_y) = label_origin(x, y, text_width, canvas.width(), options.thickness);
+            
+            // Size the backdrop to the rendered text so it covers every glyph
+            if let Some(Rgb([br, bg, bb])) = options.label_background {
+                let backdrop = Rect::at(label_x, label_y)
+                    .of_size(text_width.max(1) as u32, text_height.max(1) as u32);
+                draw_filled_rect_mut(&mut canvas, backdrop, Rgba([br, bg, bb, 255]));
+            }
+            
+            draw_text_mut(&mut canvas, color, label_x, label_y, scale, &font, &label);
+        }
+    }
+    
+    if options.show_landmarks {
+        let mut landmarks = DynamicImage::ImageRgba8(canvas);
+        crate::detection::draw_landmarks(&mut landmarks, faces, LANDMARK_RADIUS, options.color);
+        canvas = landmarks.into_rgba8();
+    }
+    
+    if let Some(text) = options.watermark.as_deref().filter(|text| !text.is_empty()) {
+        draw_watermark(&mut canvas, text, options.watermark_position, options.watermark_opacity);
+    }
+    
+    *image = if image.color().has_alpha() {
+        DynamicImage::ImageRgba8(canvas)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(canvas).to_rgb8())
+    };
+}
+
+/// Top-left corner of a `text_width` pixel wide label for a box at (`x`, `y`).
+/// 
+/// Labels start [`LABEL_OFFSET`] pixels above the box and are shifted left
+/// when they would run past the right edge of the image. Boxes too close to
+/// the top edge for that get their label inside the box, just below the
+/// outline, instead of having it cut off.
+fn label_origin(x: u32, y: u32, text_width: i32, image_width: u32, thickness: u32) -> (i32, i32) {
+    let label_x = (x as i32).min(image_width as i32 - text_width).max(0);
+    let label_y = if (y as i32) < LABEL_OFFSET {
+        i32::try_from(y.saturating_add(thickness.max(1))).unwrap_or(i32::MAX)
+    } else {
+        y as i32 - LABEL_OFFSET
+    };
+    (label_x, label_y)
+}
+
+/// Label drawn above the `index`th face (zero-based), e.g. `Face 1: 95.0%`.
+fn face_label(index: usize, confidence: f32, format: ConfidenceFormat) -> String {
+    format!("Face {}: {}", index + 1, format.format(confidence))
+}
+
+/// Composites `color` at `alpha` over every pixel of `bbox` inside the canvas.
+fn fill_box(canvas: &mut RgbaImage, bbox: BoundingBox, color: Rgb<u8>, alpha: f32) {
+    let alpha = alpha.clamp(0.0, 1.0);
+    let (left, top, right, bottom) = bbox.pixel_bounds(canvas.width(), canvas.height());
+    
+    for y in top..bottom {
+        for x in left..right {
+            blend_pixel(canvas, x, y, color, alpha);
+        }
+    }
+}
+
+/// Composites `color` at `alpha` over the canvas pixel at (`x`, `y`).
+fn blend_pixel(canvas: &mut RgbaImage, x: u32, y: u32, color: Rgb<u8>, alpha: f32) {
+    let Rgba([dr, dg, db, da]) = *canvas.get_pixel(x, y);
+    let dst_alpha = f32::from(da) / 255.0;
+    let out_alpha = alpha + dst_alpha * (1.0 - alpha);
+    let blend = |src: u8, dst: u8| {
+        if out_alpha == 0.0 {
+            return 0;
+        }
+        let value = (f32::from(src) * alpha + f32::from(dst) * dst_alpha * (1.0 - alpha)) / out_alpha;
+        value.round() as u8
+    };
+    let Rgb([sr, sg, sb]) = color;
+    
+    canvas.put_pixel(x, y, Rgba([
+        blend(sr, dr),
+        blend(sg, dg),
+        blend(sb, db),
+        (out_alpha * 255.0).round() as u8,
+    ]));
+}
+
+/// Stamps `text` in a corner of the canvas at `opacity`.
+/// 
+/// The text is drawn in white on a dark plate so it reads on any image, and
+/// both scale with the image's shorter side.
+fn draw_watermark(canvas: &mut RgbaImage, text: &str, position: WatermarkPosition, opacity: f32) {
+    let (width, height) = canvas.dimensions();
+    let text_height = (width.min(height) as f32 * WATERMARK_SCALE_RATIO).max(WATERMARK_MIN_SCALE);
+    let scale = imageproc::definitions::Scale::uniform(text_height);
+    let font = imageproc::definitions::Font::default();
+    let (text_width, text_height) = text_size(scale, &font, text);
+    
+    // Render the stamp on its own layer, no larger than the canvas, then
+    // blend it in at the opacity
+    let padding = (text_height.max(1) as u32 / 4).max(1);
+    let mut stamp = RgbaImage::from_pixel(
+        (text_width.max(1) as u32).saturating_add(2 * padding).min(width),
+        (text_height.max(1) as u32).saturating_add(2 * padding).min(height),
+        Rgba([0, 0, 0, 160]),
+    );
+    draw_text_mut(&mut stamp, Rgba([255, 255, 255, 255]), padding as i32, padding as i32, scale, &font, text);
+    
+    let left = match position {
+        WatermarkPosition::TopLeft | WatermarkPosition::BottomLeft => padding,
+        WatermarkPosition::TopRight | WatermarkPosition::BottomRight => width.saturating_sub(stamp.width() + padding),
+    };
+    let top = match position {
+        WatermarkPosition::TopLeft | WatermarkPosition::TopRight => padding,
+        WatermarkPosition::BottomLeft | WatermarkPosition::BottomRight => height.saturating_sub(stamp.height() + padding),
+    };
+    
+    let opacity = opacity.clamp(0.0, 1.0);
+    for (sx, sy, &Rgba([r, g, b, a])) in stamp.enumerate_pixels() {
+        let (x, y) = (left + sx, top + sy);
+        if x < width && y < height {
+            blend_pixel(canvas, x, y, Rgb([r, g, b]), f32::from(a) / 255.0 * opacity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::FaceDetectionError;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_detector_creation() {
+        let detector = FaceDetector::new();
+        assert!(detector.is_ok(), "Detector should be created successfully");
+    }
+
+    #[test]
+    fn test_mock_detection_small_image() {
+        let detector = FaceDetector::new().unwrap();
+        
         // Create a temporary test image
         let temp_dir = tempfile::tempdir().unwrap();
         let test_path = temp_dir.path().join("test.jpg");
@@ -230,6 +1263,36 @@ mod tests {
         assert_eq!(detection.total_faces, 1, "Should detect 1 face");
     }
 
+    #[test]
+    fn test_mock_reports_model_version() {
+        let detector = FaceDetector::new().unwrap();
+        let detection = detector.detect_image(&DynamicImage::new_rgb8(500, 500)).unwrap();
+        
+        assert!(!detection.model_version.is_empty());
+        assert_eq!(detection.model_version, format!("mock-{}", env!("CARGO_PKG_VERSION")));
+        let json = serde_json::to_value(&detection).unwrap();
+        assert_eq!(json["modelVersion"], MOCK_MODEL_VERSION);
+    }
+
+    #[test]
+    fn test_detection_reports_image_dimensions() {
+        let detector = FaceDetector::new().unwrap();
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let test_path = temp_dir.path().join("test.png");
+        
+        let img = DynamicImage::new_rgb8(320, 240);
+        img.save(&test_path).unwrap();
+        
+        let detection = detector.detect_faces(&test_path).unwrap();
+        assert_eq!(detection.image_width, 320);
+        assert_eq!(detection.image_height, 240);
+        
+        let json = serde_json::to_value(&detection).unwrap();
+        assert_eq!(json["imageWidth"], 320);
+        assert_eq!(json["imageHeight"], 240);
+    }
+
     #[test]
     fn test_bounding_box_drawing() {
         let detector = FaceDetector::new().unwrap();
@@ -241,6 +1304,607 @@ mod tests {
         assert!(result.is_ok(), "Should draw bounding boxes successfully");
     }
 
+    #[test]
+    fn test_bounding_box_thickness_and_color() {
+        let img = DynamicImage::new_rgb8(100, 100);
+        let faces = vec![Face::new(10, 10, 50, 50, 0.9)];
+        let options = DrawOptions {
+            color: Rgb([255, 0, 0]),
+            thickness: 3,
+            show_labels: false,
+            fill: None,
+            label_background: None,
+            ..DrawOptions::default()
+        };
+        
+        let drawn = draw_bounding_boxes_with(&img, &faces, &options).unwrap().to_rgb8();
+        assert_eq!(drawn.get_pixel(10, 30), &Rgb([255, 0, 0]));
+        assert_eq!(drawn.get_pixel(12, 30), &Rgb([255, 0, 0]));
+        assert_eq!(drawn.get_pixel(13, 30), &Rgb([0, 0, 0]));
+        assert_eq!(drawn.get_pixel(5, 5), &Rgb([0, 0, 0]));
+    }
+
+    #[test]
+    fn test_label_background_contrasts_with_light_image() {
+        let white = Rgb([255, 255, 255]);
+        let backdrop = Rgb([20, 20, 20]);
+        
+        let img = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(200, 100, white));
+        let faces = vec![Face::new(20, 40, 50, 50, 0.9)];
+        let options = DrawOptions {
+            label_background: Some(backdrop),
+            ..DrawOptions::default()
+        };
+        
+        let font = imageproc::definitions::Font::default();
+        let scale = imageproc::definitions::Scale::uniform(LABEL_SCALE);
+        let (text_width, text_height) = text_size(scale, &font, "Face 1: 90.0%");
+        let drawn = draw_bounding_boxes_with(&img, &faces, &options).unwrap().to_rgb8();
+        
+        // The white image never shows through the label: every pixel is
+        // either text or the dark backdrop around the glyphs
+        let top = 40 - LABEL_OFFSET as u32;
+        let label_pixels: Vec<_> = (top..top + text_height as u32)
+            .flat_map(|y| (20..20 + text_width as u32).map(move |x| (x, y)))
+            .map(|(x, y)| *drawn.get_pixel(x, y))
+            .collect();
+        assert!(!label_pixels.is_empty());
+        assert!(label_pixels.iter().all(|pixel| *pixel != white));
+        assert!(label_pixels.contains(&backdrop));
+        
+        // Outside the label the image is untouched
+        assert_eq!(drawn.get_pixel(20 + text_width as u32 + 5, top + 2), &white);
+        assert_eq!(drawn.get_pixel(150, 90), &white);
+    }
+
+    #[test]
+    fn test_label_near_right_edge_stays_inside_image() {
+        let white = Rgb([255, 255, 255]);
+        let backdrop = Rgb([20, 20, 20]);
+        
+        let img = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(200, 100, white));
+        let faces = vec![Face::new(170, 40, 25, 25, 0.9)];
+        let options = DrawOptions {
+            label_background: Some(backdrop),
+            ..DrawOptions::default()
+        };
+        
+        let font = imageproc::definitions::Font::default();
+        let scale = imageproc::definitions::Scale::uniform(LABEL_SCALE);
+        let (text_width, _) = text_size(scale, &font, "Face 1: 90.0%");
+        assert!(170 + text_width > 200, "label must be long enough to overflow");
+        let drawn = draw_bounding_boxes_with(&img, &faces, &options).unwrap().to_rgb8();
+        
+        // The whole label fits: it ends on the last column and starts
+        // text_width columns before it, rather than at the box's left edge
+        let row = 40 - LABEL_OFFSET as u32 + 1;
+        let left = 200 - text_width as u32;
+        let label_columns: Vec<u32> = (0..200).filter(|&x| *drawn.get_pixel(x, row) != white).collect();
+        assert_eq!(label_columns.first(), Some(&left));
+        assert_eq!(label_columns.last(), Some(&199));
+        assert_eq!(label_columns.len(), text_width as usize);
+    }
+
+    #[test]
+    fn test_label_moves_inside_box_at_top_edge() {
+        let white = Rgb([255, 255, 255]);
+        let backdrop = Rgb([20, 20, 20]);
+        
+        let img = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(200, 100, white));
+        let faces = vec![Face::new(20, 0, 150, 60, 0.9)];
+        let options = DrawOptions {
+            thickness: 2,
+            label_background: Some(backdrop),
+            ..DrawOptions::default()
+        };
+        
+        let drawn = draw_bounding_boxes_with(&img, &faces, &options).unwrap().to_rgb8();
+        
+        // The backdrop starts below the two pixel outline
+        assert!((20..60).any(|x| *drawn.get_pixel(x, 2) == backdrop));
+        assert!((22..60).all(|x| *drawn.get_pixel(x, 1) == Rgb([0, 255, 0])));
+        assert_eq!(label_origin(20, 0, 100, 200, 2), (20, 2));
+        assert_eq!(label_origin(20, 50, 100, 200, 2), (20, 50 - LABEL_OFFSET));
+    }
+
+    #[test]
+    fn test_label_confidence_formats() {
+        assert_eq!(face_label(0, 0.95, ConfidenceFormat::Percent), "Face 1: 95.0%");
+        assert_eq!(face_label(0, 0.95, ConfidenceFormat::Ratio), "Face 1: 0.95");
+        assert_eq!(face_label(0, 0.95, ConfidenceFormat::PercentNoSign), "Face 1: 95.0");
+        assert_eq!(DrawOptions::default().confidence_format, ConfidenceFormat::Percent);
+        
+        let options: DrawOptions = serde_json::from_str(r#"{"confidenceFormat": "percentNoSign"}"#).unwrap();
+        assert_eq!(options.confidence_format, ConfidenceFormat::PercentNoSign);
+    }
+
+    #[test]
+    fn test_watermark_only_in_chosen_corner() {
+        let white = Rgb([255, 255, 255]);
+        let img = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(400, 300, white));
+        let options = DrawOptions {
+            watermark: Some("demo".to_string()),
+            watermark_position: WatermarkPosition::BottomRight,
+            ..DrawOptions::default()
+        };
+        
+        let drawn = draw_bounding_boxes_with(&img, &[], &options).unwrap().to_rgb8();
+        let changed: Vec<(u32, u32)> = drawn
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| **pixel != white)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        
+        assert!(!changed.is_empty());
+        assert!(changed.iter().all(|&(x, y)| x >= 200 && y >= 150), "watermark outside its corner");
+        // Semi-transparent: the white image still shows through the plate
+        assert!(changed.iter().any(|&(x, y)| drawn.get_pixel(x, y).0[0] > 100));
+        
+        let top_left = DrawOptions { watermark_position: WatermarkPosition::TopLeft, ..options };
+        let drawn = draw_bounding_boxes_with(&img, &[], &top_left).unwrap().to_rgb8();
+        assert!(drawn.enumerate_pixels().filter(|(_, _, p)| **p != white).all(|(x, y, _)| x < 200 && y < 150));
+        assert_ne!(drawn.get_pixel(10, 10), &white);
+    }
+
+    #[test]
+    fn test_watermark_length_is_capped() {
+        let img = DynamicImage::new_rgb8(20, 10);
+        let longest = DrawOptions {
+            watermark: Some("w".repeat(crate::types::MAX_WATERMARK_LEN)),
+            ..DrawOptions::default()
+        };
+        
+        // The stamp is clipped to the tiny canvas instead of sized to the text
+        let drawn = draw_bounding_boxes_with(&img, &[], &longest).unwrap();
+        assert_eq!((drawn.width(), drawn.height()), (20, 10));
+        
+        let too_long = DrawOptions {
+            watermark: Some("w".repeat(crate::types::MAX_WATERMARK_LEN + 1)),
+            ..DrawOptions::default()
+        };
+        let result = draw_bounding_boxes_with(&img, &[], &too_long);
+        assert!(matches!(result, Err(FaceDetectionError::Validation { .. })));
+    }
+
+    #[test]
+    fn test_drawing_no_faces_returns_exact_copy() {
+        let detector = FaceDetector::new().unwrap();
+        let gray = DynamicImage::ImageLuma8(image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y) as u8])));
+        
+        let drawn = detector.draw_bounding_boxes(&gray, &[]).unwrap();
+        
+        assert_eq!(drawn, gray);
+    }
+
+    #[test]
+    fn test_drawing_on_palette_png_uses_exact_color() {
+        // A 2-bit indexed PNG whose closest palette entry to green is (0, 128, 0)
+        let mut info = png::Info::with_size(40, 40);
+        info.color_type = png::ColorType::Indexed;
+        info.bit_depth = png::BitDepth::Two;
+        info.palette = Some(vec![0, 0, 0, 0, 128, 0, 255, 255, 255, 128, 0, 0].into());
+        let mut bytes = Vec::new();
+        let mut writer = png::Encoder::with_info(&mut bytes, info)
+            .unwrap()
+            .write_header()
+            .unwrap();
+        writer.write_image_data(&[0b1010_1010; 10 * 40]).unwrap();
+        writer.finish().unwrap();
+        let img = image::load_from_memory(&bytes).unwrap();
+        
+        let faces = vec![Face::new(5, 5, 30, 30, 0.9)];
+        let options = DrawOptions {
+            color: Rgb([0, 255, 0]),
+            show_labels: false,
+            ..DrawOptions::default()
+        };
+        let drawn = draw_bounding_boxes_with(&img, &faces, &options).unwrap();
+        
+        assert_eq!(drawn.color(), image::ColorType::Rgb8);
+        let drawn = drawn.to_rgb8();
+        assert_eq!(*drawn.get_pixel(5, 20), Rgb([0, 255, 0]));
+        assert_eq!(*drawn.get_pixel(20, 5), Rgb([0, 255, 0]));
+        assert_eq!(*drawn.get_pixel(20, 20), Rgb([255, 255, 255]));
+    }
+    
+    #[test]
+    fn test_translucent_fill_blends_interior() {
+        let img = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(100, 100, Rgb([0, 0, 200])));
+        let faces = vec![Face::new(10, 10, 50, 50, 0.9)];
+        let options = DrawOptions {
+            color: Rgb([0, 255, 0]),
+            thickness: 2,
+            show_labels: false,
+            fill: Some((Rgb([255, 0, 0]), 0.5)),
+            label_background: None,
+            ..DrawOptions::default()
+        };
+        
+        let drawn = draw_bounding_boxes_with(&img, &faces, &options).unwrap();
+        assert!(!drawn.color().has_alpha());
+        let drawn = drawn.to_rgb8();
+        
+        // Interior is halfway between blue and red; outline and outside are untouched
+        assert_eq!(drawn.get_pixel(30, 30), &Rgb([128, 0, 100]));
+        assert_eq!(drawn.get_pixel(10, 30), &Rgb([0, 255, 0]));
+        assert_eq!(drawn.get_pixel(11, 30), &Rgb([0, 255, 0]));
+        assert_eq!(drawn.get_pixel(5, 5), &Rgb([0, 0, 200]));
+        assert_eq!(drawn.get_pixel(70, 30), &Rgb([0, 0, 200]));
+    }
+
+    #[test]
+    fn test_oversized_boxes_are_clamped_to_the_image() {
+        let img = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(100, 100, Rgb([255, 255, 255])));
+        let faces = vec![
+            Face::new(4_000_000_000, 0, u32::MAX, u32::MAX, 0.9),
+            Face::new(50, 50, u32::MAX, u32::MAX, 0.9),
+        ];
+        let options = DrawOptions {
+            color: Rgb([0, 255, 0]),
+            thickness: crate::types::MAX_DRAW_THICKNESS,
+            show_labels: false,
+            ..DrawOptions::default()
+        };
+        
+        let drawn = draw_bounding_boxes_with(&img, &faces, &options).unwrap().to_rgb8();
+        assert_eq!(*drawn.get_pixel(50, 70), Rgb([0, 255, 0]));
+        assert_eq!(*drawn.get_pixel(99, 70), Rgb([0, 255, 0]));
+        assert_eq!(*drawn.get_pixel(20, 20), Rgb([255, 255, 255]));
+    }
+    
+    #[test]
+    fn test_excessive_thickness_is_rejected() {
+        let img = DynamicImage::new_rgb8(100, 100);
+        let faces = vec![Face::new(10, 10, 50, 50, 0.9)];
+        let options = DrawOptions {
+            thickness: 4_000_000_000,
+            ..DrawOptions::default()
+        };
+        
+        let result = draw_bounding_boxes_with(&img, &faces, &options);
+        assert!(matches!(result, Err(FaceDetectionError::Validation { .. })));
+    }
+
+    #[test]
+    fn test_mock_confidences_filtered_by_threshold() {
+        let detector = FaceDetector::new()
+            .unwrap()
+            .with_mock_confidences(vec![0.4, 0.99])
+            .with_confidence_threshold(0.5);
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let test_path = temp_dir.path().join("test.png");
+        DynamicImage::new_rgb8(500, 500).save(&test_path).unwrap();
+        
+        let detection = detector.detect_faces(&test_path).unwrap();
+        assert_eq!(detection.total_faces, 1);
+        assert_eq!(detection.faces[0].confidence, 0.99);
+    }
+
+    #[test]
+    fn test_sub_threshold_face_reported_as_rejected() {
+        let detector = FaceDetector::new()
+            .unwrap()
+            .with_mock_confidences(vec![0.4, 0.99])
+            .with_confidence_threshold(0.5);
+        let img = DynamicImage::new_rgb8(500, 500);
+        
+        let detection = detector.detect_image_with_rejections(&img).unwrap();
+        assert_eq!(detection.total_faces, 1);
+        let rejected = detection.rejected.unwrap();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].reason, RejectionReason::BelowThreshold);
+        assert_eq!(rejected[0].confidence, 0.4);
+        assert_eq!(rejected[0].bbox, BoundingBox::new(125, 125, 125, 125));
+        
+        assert!(detector.detect_image(&img).unwrap().rejected.is_none());
+    }
+
+    #[test]
+    fn test_skin_ratio_filters_mock_faces_on_gray() {
+        let detector = FaceDetector::new().unwrap().with_min_skin_ratio(0.5);
+        let skin = Rgb([224, 172, 140]);
+        let gray = Rgb([128, 128, 128]);
+        
+        // Left half skin, right half gray: the mock's first face (at a
+        // quarter of the image) lands on skin, the others on gray
+        let img = DynamicImage::ImageRgb8(ImageBuffer::from_fn(800, 800, |x, _| {
+            if x < 400 { skin } else { gray }
+        }));
+        let detection = detector.detect_image(&img).unwrap();
+        assert!(detection.total_faces >= 1);
+        assert!(detection.faces.iter().all(|face| face.bbox.right() <= 400.0));
+        
+        let all_gray = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(800, 800, gray));
+        assert_eq!(detector.detect_image(&all_gray).unwrap().total_faces, 0);
+        assert!(FaceDetector::new().unwrap().detect_image(&all_gray).unwrap().total_faces > 0);
+    }
+
+    #[test]
+    fn test_builder_rejects_skin_ratio_with_grayscale() {
+        assert!(FaceDetector::builder().min_skin_ratio(1.5).build().is_err());
+        assert!(FaceDetector::builder().min_skin_ratio(0.3).grayscale_detection(true).build().is_err());
+        assert!(FaceDetector::builder().min_skin_ratio(0.3).build().is_ok());
+    }
+
+    #[test]
+    fn test_builder_builds_valid_detector() {
+        let detector = FaceDetector::builder()
+            .min_dimension(100)
+            .confidence_threshold(0.9)
+            .face_area_range(0, 100 * 100)
+            .build()
+            .unwrap();
+        
+        assert_eq!(detector.min_dimension(), 100);
+        assert_eq!(detector.confidence_threshold(), 0.9);
+        
+        // The 500x500 image yields faces of 125x125 and 100x100; only the latter fits
+        let temp_dir = tempfile::tempdir().unwrap();
+        let test_path = temp_dir.path().join("test.png");
+        DynamicImage::new_rgb8(500, 500).save(&test_path).unwrap();
+        
+        let detection = detector.detect_faces(&test_path).unwrap();
+        assert_eq!(detection.total_faces, 0, "0.87 confidence is below 0.9");
+        
+        let detector = FaceDetector::builder().face_area_range(0, 100 * 100).build().unwrap();
+        let detection = detector.detect_faces(&test_path).unwrap();
+        assert_eq!(detection.total_faces, 1);
+        assert_eq!(detection.faces[0].bbox.width, 100.0);
+    }
+
+    #[test]
+    fn test_detect_in_memory_matches_path_and_bytes() {
+        let detector = FaceDetector::new().unwrap();
+        let img = DynamicImage::new_rgb8(700, 500);
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let test_path = temp_dir.path().join("test.png");
+        img.save(&test_path).unwrap();
+        
+        let in_memory = detector.detect(&img).unwrap();
+        let from_path = detector.detect_faces(&test_path).unwrap();
+        let from_bytes = detector.detect_bytes(&std::fs::read(&test_path).unwrap()).unwrap();
+        
+        assert_eq!(in_memory.total_faces, 2);
+        for other in [&from_path, &from_bytes] {
+            let boxes = |result: &DetectionResult| -> Vec<_> {
+                result.faces.iter().map(|face| (face.bbox, face.confidence)).collect()
+            };
+            assert_eq!(boxes(other), boxes(&in_memory));
+            assert_eq!((other.image_width, other.image_height), (700, 500));
+        }
+        assert!(detector.detect_bytes(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_downscale_only_above_megapixel_trigger() {
+        let detector = FaceDetector::builder()
+            .max_detection_dimension(500)
+            .downscale_min_megapixels(1.0)
+            .build()
+            .unwrap();
+        
+        // 0.49 MP stays below the trigger: three faces at native resolution
+        let small = detector.detect(&DynamicImage::new_rgb8(700, 700)).unwrap();
+        let native = FaceDetector::new().unwrap().detect(&DynamicImage::new_rgb8(700, 700)).unwrap();
+        assert_eq!(small.total_faces, 3);
+        assert_eq!(
+            small.faces.iter().map(|face| face.bbox).collect::<Vec<_>>(),
+            native.faces.iter().map(|face| face.bbox).collect::<Vec<_>>()
+        );
+        
+        // 4 MP is detected on a 500x500 copy: the mock yields two faces there,
+        // and their boxes are scaled by 4 back onto the original
+        let huge = detector.detect(&DynamicImage::new_rgb8(2000, 2000)).unwrap();
+        assert_eq!((huge.image_width, huge.image_height), (2000, 2000));
+        assert_eq!(huge.total_faces, 2);
+        assert_eq!(huge.faces[0].bbox, BoundingBox::new(500, 500, 500, 500));
+        assert_eq!(huge.faces[1].bbox, BoundingBox::new(1332, 664, 400, 400));
+        
+        // Without the trigger every image above the maximum is downscaled
+        let always = FaceDetector::new().unwrap().with_max_detection_dimension(500);
+        assert_eq!(always.detect(&DynamicImage::new_rgb8(700, 700)).unwrap().total_faces, 2);
+        assert!(FaceDetector::builder().downscale_min_megapixels(-1.0).build().is_err());
+        assert!(FaceDetector::builder().downscale_min_megapixels(f64::NAN).build().is_err());
+    }
+
+    #[test]
+    fn test_detector_config_applies_file_values() {
+        let json = r#"{
+            "min_dimension": 150,
+            "confidence_threshold": 0.7,
+            "min_face_area": 1024,
+            "max_faces": 1,
+            "nms_iou": 0.4
+        }"#;
+        let config: DetectorConfig = serde_json::from_str(json).unwrap();
+        let detector = FaceDetector::builder().with_detector_config(&config).build().unwrap();
+        
+        assert_eq!(detector.min_dimension(), 150);
+        assert_eq!(detector.confidence_threshold(), 0.7);
+        assert_eq!(detector.min_face_area(), 1024);
+        assert_eq!(detector.max_faces(), Some(1));
+        assert_eq!(detector.nms_iou(), Some(0.4));
+        
+        // The 700x700 mock faces would be three; the limit keeps the most confident
+        let detection = detector.detect_image_with_rejections(&DynamicImage::new_rgb8(700, 700)).unwrap();
+        assert_eq!(detection.total_faces, 1);
+        assert_eq!(detection.faces[0].confidence, 0.95);
+        let rejected = detection.rejected.unwrap();
+        assert_eq!(rejected.len(), 2);
+        assert!(rejected.iter().all(|face| face.reason == RejectionReason::MaxFaces));
+        
+        // Unset fields keep the defaults; unknown or invalid ones are rejected
+        let partial: DetectorConfig = serde_json::from_str(r#"{ "max_faces": 2 }"#).unwrap();
+        let detector = FaceDetector::builder().with_detector_config(&partial).build().unwrap();
+        assert_eq!(detector.min_dimension(), 200);
+        assert_eq!(detector.nms_iou(), None);
+        assert!(serde_json::from_str::<DetectorConfig>(r#"{ "max_face": 2 }"#).is_err());
+        let invalid = DetectorConfig { nms_iou: Some(1.5), ..DetectorConfig::default() };
+        assert!(FaceDetector::builder().with_detector_config(&invalid).build().is_err());
+        assert!(FaceDetector::builder().max_faces(0).build().is_err());
+    }
+
+    #[test]
+    fn test_results_rounded_to_configured_precision() {
+        let img = DynamicImage::new_rgb8(500, 500);
+        let detector = FaceDetector::builder()
+            .mock_confidences(vec![0.987_654])
+            .confidence_precision(2)
+            .build()
+            .unwrap();
+        
+        let result = detector.detect_image(&img).unwrap();
+        assert_eq!(result.faces[0].confidence, 0.99);
+        assert_eq!(serde_json::to_value(&result.faces[0]).unwrap()["confidence"], 0.99);
+        assert!(result.faces.iter().all(|face| face.bbox == face.bbox.round()));
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_threshold() {
+        for threshold in [-0.1, 1.5, f32::NAN] {
+            let result = FaceDetector::builder().confidence_threshold(threshold).build();
+            assert!(matches!(result, Err(FaceDetectionError::Configuration { .. })));
+        }
+    }
+
+    #[test]
+    fn test_aspect_ratio_bounds_filter_implausible_boxes() {
+        // A 2000x200 image yields a single 500x50 (10:1) mock face
+        let wide = DynamicImage::new_rgb8(2000, 200);
+        let detector = FaceDetector::new().unwrap().with_min_dimension(100);
+        
+        let detection = detector.detect_image(&wide).unwrap();
+        assert_eq!(detection.total_faces, 1);
+        assert_eq!(detection.faces[0].bbox.aspect_ratio(), 10.0);
+        
+        let bounded = FaceDetector::builder().min_dimension(100).aspect_ratio_range(0.5, 2.0).build().unwrap();
+        let detection = bounded.detect_image(&wide).unwrap();
+        assert_eq!(detection.total_faces, 0);
+        assert!(detection.faces.is_empty());
+        
+        // Square boxes pass the same bounds
+        let detection = bounded.detect_image(&DynamicImage::new_rgb8(500, 500)).unwrap();
+        assert_eq!(detection.total_faces, 2);
+    }
+
+    #[test]
+    fn test_faces_at_border_are_marked_truncated() {
+        let mut faces = vec![
+            Face::new(0, 40, 20, 20, 0.9),
+            Face::new(40, 40, 20, 20, 0.9),
+            Face::new(79, 40, 20, 20, 0.9),
+            Face::new(40, 2, 20, 20, 0.9),
+        ];
+        mark_truncated(&mut faces, 100, 100);
+        
+        let truncated: Vec<_> = faces.iter().map(|face| face.truncated).collect();
+        assert_eq!(truncated, [true, false, true, true]);
+        
+        // The mock's faces sit well inside the image
+        let detection = FaceDetector::new().unwrap().detect_image(&DynamicImage::new_rgb8(500, 500)).unwrap();
+        assert!(detection.faces.iter().all(|face| !face.truncated));
+    }
+
+    #[test]
+    fn test_builder_rejects_inverted_area_bounds() {
+        let result = FaceDetector::builder().face_area_range(10_000, 100).build();
+        assert!(matches!(result, Err(FaceDetectionError::Configuration { .. })));
+        
+        let result = FaceDetector::builder().aspect_ratio_range(2.0, 0.5).build();
+        assert!(matches!(result, Err(FaceDetectionError::Configuration { .. })));
+        
+        let result = FaceDetector::builder().aspect_ratio_range(f32::NAN, 2.0).build();
+        assert!(matches!(result, Err(FaceDetectionError::Configuration { .. })));
+        
+        let result = FaceDetector::builder().min_dimension(0).build();
+        assert!(matches!(result, Err(FaceDetectionError::Configuration { .. })));
+    }
+
+    #[test]
+    fn test_grayscale_detection_matches_color() {
+        let color = FaceDetector::new().unwrap();
+        let gray = FaceDetector::new().unwrap().with_grayscale_detection(true);
+        
+        let img = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(700, 650, Rgb([200, 30, 60])));
+        let boxes = |detector: &FaceDetector| -> Vec<(u32, u32, u32, u32)> {
+            let result = detector.detect_image(&img).unwrap();
+            result.faces.iter().map(|face| face.bbox.into()).collect()
+        };
+        
+        assert_eq!(boxes(&gray), boxes(&color));
+        assert_eq!(boxes(&gray).len(), 3);
+    }
+
+    #[test]
+    fn test_auto_orient_finds_faces_only_visible_rotated() {
+        // Stand-in backend that only sees a face when the marker pixel is top-left
+        let upright_only = |img: &DynamicImage| -> Result<Vec<Face>> {
+            let marked = img.get_pixel(0, 0) == Rgba([255, 255, 255, 255]);
+            Ok(if marked { vec![Face::new(10, 20, 30, 40, 0.9)] } else { Vec::new() })
+        };
+        
+        // Rotating 90 degrees clockwise moves the bottom-left pixel to the top-left
+        let mut scan = image::RgbImage::new(300, 200);
+        scan.put_pixel(0, 199, Rgb([255, 255, 255]));
+        let scan = DynamicImage::ImageRgb8(scan);
+        assert!(upright_only(&scan).unwrap().is_empty());
+        
+        let (degrees, faces) = detect_rotated(&scan, upright_only).unwrap().unwrap();
+        assert_eq!(degrees, 90);
+        assert_eq!(faces.len(), 1);
+        assert_eq!(faces[0].bbox, BoundingBox::new(20, 160, 40, 30));
+        assert_eq!(faces[0].confidence, 0.9);
+        
+        assert!(detect_rotated(&DynamicImage::new_rgb8(300, 200), upright_only).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_auto_orient_is_opt_in() {
+        let img = DynamicImage::new_rgb8(150, 150);
+        
+        let plain = FaceDetector::new().unwrap().detect_image(&img).unwrap();
+        assert_eq!(plain.applied_rotation, None);
+        
+        // The mock backend is rotation-invariant, so no rotation finds faces either
+        let oriented = FaceDetector::builder().auto_orient(true).build().unwrap();
+        let result = oriented.detect_image(&img).unwrap();
+        assert!(!result.has_faces());
+        assert_eq!(result.applied_rotation, None);
+    }
+
+    #[test]
+    fn test_warmup_then_detect() {
+        let detector = FaceDetector::new().unwrap();
+        
+        assert!(detector.warmup().is_ok());
+        
+        let result = detector.detect_image(&DynamicImage::new_rgb8(300, 300)).unwrap();
+        assert_eq!(result.total_faces, 1);
+    }
+
+    #[test]
+    fn test_single_pixel_image_has_no_faces() {
+        let detector = FaceDetector::new().unwrap().with_min_dimension(0);
+        
+        let result = detector.detect(&DynamicImage::new_rgb8(1, 1)).unwrap();
+        assert_eq!(result.total_faces, 0);
+        assert!(result.faces.is_empty());
+    }
+
+    #[test]
+    fn test_empty_image_is_rejected() {
+        let detector = FaceDetector::new().unwrap();
+        
+        for (width, height) in [(0, 0), (1, 0), (0, 1)] {
+            assert!(matches!(
+                detector.detect(&DynamicImage::new_rgb8(width, height)),
+                Err(FaceDetectionError::InvalidImageData)
+            ));
+        }
+    }
+
     #[test]
     fn test_detector_configuration() {
         let detector = FaceDetector::new()
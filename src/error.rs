@@ -65,6 +65,13 @@ pub enum FaceDetectionError {
     #[snafu(display("No file uploaded"))]
     NoFileUploaded,
 
+    /// The upload stalled without sending data for longer than allowed.
+    #[snafu(display("Upload stalled for more than {idle_secs} seconds"))]
+    UploadTimeout {
+        /// Idle period in seconds after which the upload was aborted.
+        idle_secs: u64,
+    },
+
     /// Image processing failed.
     #[snafu(display("Image processing failed"))]
     ImageProcessing {
@@ -76,10 +83,66 @@ pub enum FaceDetectionError {
     #[snafu(display("Face detection failed"))]
     DetectionFailed,
 
+    /// Detection completed but no faces were found when at least one was required.
+    #[snafu(display("No faces detected"))]
+    NoFacesDetected,
+
     /// Internal server error occurred.
     #[snafu(display("Internal server error"))]
     InternalError,
 
+    /// The storage backend failed.
+    #[snafu(display("Storage error: {message}"))]
+    Storage {
+        /// Description of the failure.
+        message: String,
+    },
+
+    /// Fetching a remote image failed.
+    #[snafu(display("Remote fetch failed: {message}"))]
+    RemoteFetch {
+        /// Description of the failure.
+        message: String,
+    },
+
+    /// The detection backend is failing and calls are being rejected.
+    #[snafu(display("Detection backend unavailable; retry after {retry_after_secs}s"))]
+    BackendUnavailable {
+        /// Seconds until the backend will be tried again.
+        retry_after_secs: u64,
+    },
+
+    /// Too many detections are already running.
+    #[snafu(display("Server is at detection capacity; retry after {retry_after_secs}s"))]
+    Overloaded {
+        /// Seconds the client should wait before retrying.
+        retry_after_secs: u64,
+    },
+
+    /// The service is up but not fit to take traffic.
+    #[snafu(display("Service not ready: {reason}"))]
+    NotReady {
+        /// Why the service is not ready.
+        reason: String,
+    },
+
+    /// A requested server-local path lies outside the allowed root.
+    #[snafu(display("Path is not allowed: {path}"))]
+    PathNotAllowed {
+        /// The path as requested.
+        path: String,
+    },
+
+    /// Image data ended early or could not be read, as when an upload is cut
+    /// short by a flaky proxy; unlike a malformed image, resending may work.
+    #[snafu(display("Image data appears truncated; retry after {retry_after_secs}s"))]
+    TransientDecode {
+        /// The underlying decode error.
+        source: image::ImageError,
+        /// Seconds the client should wait before retrying.
+        retry_after_secs: u64,
+    },
+
     /// IO operation failed.
     #[snafu(display("IO error"))]
     Io {
@@ -87,6 +150,15 @@ pub enum FaceDetectionError {
         source: std::io::Error,
     },
 
+    /// The request body has a content type the endpoint does not accept.
+    #[snafu(display("Unsupported content type {content_type}; expected {expected}"))]
+    UnsupportedMediaType {
+        /// The content type sent, or `none` if the header was missing.
+        content_type: String,
+        /// The content type the endpoint accepts.
+        expected: &'static str,
+    },
+
     /// Multipart form parsing failed.
     #[snafu(display("Multipart parsing failed"))]
     MultipartError,
@@ -155,6 +227,13 @@ impl snafu::IntoError<FaceDetectionError> for ImageProcessingSnafu {
     #[snafu(display("No file uploaded"))]
     NoFileUploaded,
 
+    /// The upload stalled without sending data for longer than allowed.
+    #[snafu(display("Upload stalled for more than {idle_secs} seconds"))]
+    UploadTimeout {
+        /// Idle period in seconds after which the upload was aborted.
+        idle_secs: u64,
+    },
+
     /// Image processing failed.
     #[snafu(display("Image processing failed: {source}"))]
     ImageProcessing {
@@ -166,10 +245,66 @@ impl snafu::IntoError<FaceDetectionError> for ImageProcessingSnafu {
     #[snafu(display("Face detection failed"))]
     DetectionFailed,
 
+    /// Detection completed but no faces were found when at least one was required.
+    #[snafu(display("No faces detected"))]
+    NoFacesDetected,
+
     /// Internal server error occurred.
     #[snafu(display("Internal server error"))]
     InternalError,
 
+    /// The storage backend failed.
+    #[snafu(display("Storage error: {message}"))]
+    Storage {
+        /// Description of the failure.
+        message: String,
+    },
+
+    /// Fetching a remote image failed.
+    #[snafu(display("Remote fetch failed: {message}"))]
+    RemoteFetch {
+        /// Description of the failure.
+        message: String,
+    },
+
+    /// The detection backend is failing and calls are being rejected.
+    #[snafu(display("Detection backend unavailable; retry after {retry_after_secs}s"))]
+    BackendUnavailable {
+        /// Seconds until the backend will be tried again.
+        retry_after_secs: u64,
+    },
+
+    /// Too many detections are already running.
+    #[snafu(display("Server is at detection capacity; retry after {retry_after_secs}s"))]
+    Overloaded {
+        /// Seconds the client should wait before retrying.
+        retry_after_secs: u64,
+    },
+
+    /// The service is up but not fit to take traffic.
+    #[snafu(display("Service not ready: {reason}"))]
+    NotReady {
+        /// Why the service is not ready.
+        reason: String,
+    },
+
+    /// A requested server-local path lies outside the allowed root.
+    #[snafu(display("Path is not allowed: {path}"))]
+    PathNotAllowed {
+        /// The path as requested.
+        path: String,
+    },
+
+    /// Image data ended early or could not be read, as when an upload is cut
+    /// short by a flaky proxy; unlike a malformed image, resending may work.
+    #[snafu(display("Image data appears truncated; retry after {retry_after_secs}s"))]
+    TransientDecode {
+        /// The underlying decode error.
+        source: image::ImageError,
+        /// Seconds the client should wait before retrying.
+        retry_after_secs: u64,
+    },
+
     /// IO operation failed.
     #[snafu(display("IO error: {source}"))]
     Io {
@@ -177,6 +312,15 @@ impl snafu::IntoError<FaceDetectionError> for ImageProcessingSnafu {
         source: std::io::Error,
     },
 
+    /// The request body has a content type the endpoint does not accept.
+    #[snafu(display("Unsupported content type {content_type}; expected {expected}"))]
+    UnsupportedMediaType {
+        /// The content type sent, or `none` if the header was missing.
+        content_type: String,
+        /// The content type the endpoint accepts.
+        expected: &'static str,
+    },
+
     /// Multipart form parsing failed.
     #[snafu(display("Multipart parsing failed"))]
     MultipartError,
@@ -216,10 +360,20 @@ impl actix_web::error::ResponseError for FaceDetectionError {
             Self::InvalidFileFormat { .. } => (StatusCode::BAD_REQUEST, "Invalid file format"),
             Self::FileTooLarge { .. } => (StatusCode::PAYLOAD_TOO_LARGE, "File too large"),
             Self::NoFileUploaded => (StatusCode::BAD_REQUEST, "No file uploaded"),
+            Self::UploadTimeout { .. } => (StatusCode::REQUEST_TIMEOUT, "Upload timed out"),
             Self::ImageProcessing { .. } => (StatusCode::BAD_REQUEST, "Invalid image format"),
             Self::DetectionFailed => (StatusCode::INTERNAL_SERVER_ERROR, "Face detection failed"),
+            Self::NoFacesDetected => (StatusCode::UNPROCESSABLE_ENTITY, "No faces detected"),
             Self::InternalError => (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error"),
+            Self::Storage { .. } => (StatusCode::INTERNAL_SERVER_ERROR, "Storage error"),
+            Self::RemoteFetch { .. } => (StatusCode::BAD_GATEWAY, "Failed to fetch remote image"),
+            Self::BackendUnavailable { .. } => (StatusCode::SERVICE_UNAVAILABLE, "Detection backend unavailable"),
+            Self::Overloaded { .. } => (StatusCode::SERVICE_UNAVAILABLE, "Server overloaded"),
+            Self::NotReady { .. } => (StatusCode::SERVICE_UNAVAILABLE, "Service not ready"),
+            Self::PathNotAllowed { .. } => (StatusCode::FORBIDDEN, "Path not allowed"),
+            Self::TransientDecode { .. } => (StatusCode::SERVICE_UNAVAILABLE, "Image upload incomplete"),
             Self::Io { .. } => (StatusCode::INTERNAL_SERVER_ERROR, "File system error"),
+            Self::UnsupportedMediaType { .. } => (StatusCode::UNSUPPORTED_MEDIA_TYPE, "Unsupported media type"),
             Self::MultipartError => (StatusCode::BAD_REQUEST, "Invalid form data"),
             Self::Base64Error => (StatusCode::BAD_REQUEST, "Invalid image encoding"),
             Self::InvalidImageData => (StatusCode::BAD_REQUEST, "Invalid image data"),
@@ -227,11 +381,42 @@ impl actix_web::error::ResponseError for FaceDetectionError {
             Self::Validation { .. } => (StatusCode::BAD_REQUEST, "Validation failed"),
         };
 
-        actix_web::HttpResponse::build(status).json(serde_json::json!({
-            "success": false,
-            "error": message,
-            "details": self.to_string()
-        }))
+        let mut response = actix_web::HttpResponse::build(status);
+        if let Self::BackendUnavailable { retry_after_secs }
+        | Self::Overloaded { retry_after_secs }
+        | Self::TransientDecode { retry_after_secs, .. } = self
+        {
+            response.insert_header((actix_web::http::header::RETRY_AFTER, retry_after_secs.to_string()));
+        }
+        
+        response.json(ErrorBody {
+            success: false,
+            error: message,
+            details: self.to_string(),
+            metadata: crate::types::ResponseMetadata::now(),
+        })
+    }
+}
+
+/// Error response body: the [`crate::types::ApiResponse`] envelope, metadata
+/// included, with the full error message as `details`.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorBody {
+    success: bool,
+    error: &'static str,
+    details: String,
+    metadata: crate::types::ResponseMetadata,
+}
+
+impl FaceDetectionError {
+    /// Whether the error means the detection backend itself is unhealthy,
+    /// as opposed to the request being bad.
+    pub fn is_backend_failure(&self) -> bool {
+        matches!(
+            self,
+            Self::DetectionFailed | Self::InternalError | Self::RemoteFetch { .. } | Self::Io { .. }
+        )
     }
 }
 
@@ -253,6 +438,48 @@ impl<T> IntoFaceDetectionError<T> for std::io::Error {
     }
 }
 
+/// `Retry-After` seconds sent when an image looks truncated in transit.
+pub const DECODE_RETRY_AFTER_SECS: u64 = 1;
+
+/// Helper function to create image decoding errors.
+/// 
+/// Read failures and unexpected ends of data are likely transmission
+/// problems and become a retryable [`FaceDetectionError::TransientDecode`];
+/// unsupported or malformed images stay [`FaceDetectionError::ImageProcessing`].
+pub fn decode_error(source: image::ImageError) -> FaceDetectionError {
+    if is_transient_decode_error(&source) {
+        FaceDetectionError::TransientDecode {
+            source,
+            retry_after_secs: DECODE_RETRY_AFTER_SECS,
+        }
+    } else {
+        FaceDetectionError::ImageProcessing { source }
+    }
+}
+
+/// Whether a decode error looks like truncated or unreadable input rather
+/// than a bad image.
+fn is_transient_decode_error(error: &image::ImageError) -> bool {
+    match error {
+        image::ImageError::IoError(_) => true,
+        image::ImageError::Decoding(decoding) => {
+            // Decoders report running out of data as a wrapped io::Error
+            let mut cause = std::error::Error::source(decoding);
+            while let Some(error) = cause {
+                if error
+                    .downcast_ref::<std::io::Error>()
+                    .is_some_and(|e| e.kind() == std::io::ErrorKind::UnexpectedEof)
+                {
+                    return true;
+                }
+                cause = error.source();
+            }
+            false
+        }
+        _ => false,
+    }
+}
+
 /// Helper function to create validation errors.
 pub fn validation_error(message: impl Into<String>) -> FaceDetectionError {
     FaceDetectionError::Validation {
@@ -265,4 +492,56 @@ pub fn config_error(message: impl Into<String>) -> FaceDetectionError {
     FaceDetectionError::Configuration {
         message: message.into(),
     }
-}
\ No newline at end of file
+}
+
+/// Helper function to create storage errors.
+pub fn storage_error(message: impl Into<String>) -> FaceDetectionError {
+    FaceDetectionError::Storage {
+        message: message.into(),
+    }
+}
+
+/// Helper function to create remote fetch errors.
+pub fn fetch_error(message: impl Into<String>) -> FaceDetectionError {
+    FaceDetectionError::RemoteFetch {
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::ResponseError;
+    use image::error::{ImageFormatHint, UnsupportedError, UnsupportedErrorKind};
+
+    #[test]
+    fn test_truncated_read_is_retryable() {
+        let truncated = image::ImageError::IoError(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "failed to fill whole buffer",
+        ));
+        let error = decode_error(truncated);
+        assert!(matches!(error, FaceDetectionError::TransientDecode { .. }));
+        
+        let response = error.error_response();
+        assert_eq!(response.status(), 503);
+        assert_eq!(
+            response.headers().get("retry-after").unwrap(),
+            DECODE_RETRY_AFTER_SECS.to_string().as_str()
+        );
+    }
+
+    #[test]
+    fn test_unsupported_format_stays_bad_request() {
+        let unsupported = image::ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+            ImageFormatHint::Unknown,
+            UnsupportedErrorKind::Format(ImageFormatHint::Unknown),
+        ));
+        let error = decode_error(unsupported);
+        assert!(matches!(error, FaceDetectionError::ImageProcessing { .. }));
+        
+        let response = error.error_response();
+        assert_eq!(response.status(), 400);
+        assert!(!response.headers().contains_key("retry-after"));
+    }
+}
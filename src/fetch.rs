@@ -0,0 +1,309 @@
+//! Fetching images from remote URLs.
+//!
+//! Image hosts are occasionally flaky, so [`fetch_image_bytes`] retries
+//! transient failures (timeouts, connection errors and 5xx responses) with
+//! exponential backoff. Client errors (4xx) are never retried. The whole
+//! fetch, including retries and backoff, is bounded by the detection timeout,
+//! and bodies are capped at a maximum size like uploads are.
+//!
+//! No endpoint fetches remote images yet; this is the building block for one.
+
+use crate::config::AppConfig;
+use crate::error::{fetch_error, Result};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How a remote fetch is retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; `0` disables retrying.
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled for each subsequent retry.
+    pub initial_backoff: Duration,
+    /// Upper bound on the total time spent fetching, including backoff.
+    pub max_total: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(200),
+            max_total: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Builds the policy from the fetch settings, capped by the detection timeout.
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            max_retries: config.fetch_max_retries,
+            initial_backoff: Duration::from_millis(config.fetch_backoff_ms),
+            max_total: Duration::from_secs(config.detection_timeout_secs),
+        }
+    }
+
+    /// Returns the delay before retry number `retry` (zero-based).
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff.saturating_mul(2u32.saturating_pow(retry))
+    }
+}
+
+/// Outcome of a single fetch attempt.
+enum Attempt {
+    /// The body was received.
+    Done(Vec<u8>),
+    /// A transient failure worth retrying.
+    Transient(String),
+    /// A failure retrying won't fix.
+    Fatal(String),
+}
+
+/// Downloads the image at `url`, retrying transient failures per `policy`.
+///
+/// A declared `Content-Length` above `max_bytes` is rejected before the body
+/// is read, and the body is read in chunks that stop once it grows past
+/// `max_bytes`, so a lying or missing length cannot exhaust memory.
+///
+/// # Errors
+///
+/// Returns a `RemoteFetch` error if the server answers with a 4xx status,
+/// the body is larger than `max_bytes`, or every attempt failed or the time
+/// budget ran out.
+pub async fn fetch_image_bytes(
+    client: &reqwest::Client,
+    url: &str,
+    policy: &RetryPolicy,
+    max_bytes: usize,
+) -> Result<Vec<u8>> {
+    let deadline = Instant::now() + policy.max_total;
+    let mut retry = 0;
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(fetch_error(format!("timed out fetching {}", url)));
+        }
+
+        let message = match attempt(client, url, remaining, max_bytes).await {
+            Attempt::Done(bytes) => return Ok(bytes),
+            Attempt::Fatal(message) => return Err(fetch_error(message)),
+            Attempt::Transient(message) => message,
+        };
+
+        if retry >= policy.max_retries {
+            return Err(fetch_error(format!(
+                "{} (gave up after {} attempts)",
+                message,
+                retry + 1
+            )));
+        }
+
+        let delay = policy.backoff(retry);
+        if Instant::now() + delay >= deadline {
+            return Err(fetch_error(format!("{} (no time left to retry)", message)));
+        }
+
+        retry += 1;
+        warn!("Fetching {} failed: {}; retry {} in {:?}", url, message, retry, delay);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Makes one request for `url`, giving up after `timeout` or once the body
+/// exceeds `max_bytes`.
+async fn attempt(client: &reqwest::Client, url: &str, timeout: Duration, max_bytes: usize) -> Attempt {
+    let mut response = match client.get(url).timeout(timeout).send().await {
+        Ok(response) => response,
+        Err(e) if is_transient(&e) => return Attempt::Transient(e.to_string()),
+        Err(e) => return Attempt::Fatal(e.to_string()),
+    };
+
+    let status = response.status();
+    if status.is_server_error() {
+        return Attempt::Transient(format!("{} returned {}", url, status));
+    }
+    if !status.is_success() {
+        return Attempt::Fatal(format!("{} returned {}", url, status));
+    }
+
+    if let Some(length) = response.content_length().filter(|&length| length > max_bytes as u64) {
+        return Attempt::Fatal(format!("{url} declares {length} bytes, more than the {max_bytes} byte limit"));
+    }
+
+    let mut body = Vec::new();
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                if body.len() + chunk.len() > max_bytes {
+                    return Attempt::Fatal(format!("{url} sent more than the {max_bytes} byte limit"));
+                }
+                body.extend_from_slice(&chunk);
+            }
+            Ok(None) => return Attempt::Done(body),
+            Err(e) if is_transient(&e) => return Attempt::Transient(e.to_string()),
+            Err(e) => return Attempt::Fatal(e.to_string()),
+        }
+    }
+}
+
+/// Whether a transport error is worth retrying.
+///
+/// Only timeouts and failures to connect qualify; other request errors
+/// (bad URLs, redirect loops, broken bodies) would fail the same way again.
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_timeout() || error.is_connect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::FaceDetectionError;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const MAX_BYTES: usize = 1024;
+
+    fn fast_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff: Duration::from_millis(10),
+            max_total: Duration::from_secs(5),
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        let policy = fast_policy(3);
+        assert_eq!(policy.backoff(0), Duration::from_millis(10));
+        assert_eq!(policy.backoff(1), Duration::from_millis(20));
+        assert_eq!(policy.backoff(2), Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_retries_until_success() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/face.jpg"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/face.jpg"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"image".to_vec()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/face.jpg", server.uri());
+        let bytes = fetch_image_bytes(&reqwest::Client::new(), &url, &fast_policy(3), MAX_BYTES)
+            .await
+            .unwrap();
+        assert_eq!(bytes, b"image");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_gives_up_after_max_retries() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/face.jpg", server.uri());
+        let result = fetch_image_bytes(&reqwest::Client::new(), &url, &fast_policy(2), MAX_BYTES).await;
+        assert!(matches!(result, Err(FaceDetectionError::RemoteFetch { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_does_not_retry_client_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/missing.jpg", server.uri());
+        let result = fetch_image_bytes(&reqwest::Client::new(), &url, &fast_policy(3), MAX_BYTES).await;
+        assert!(matches!(result, Err(FaceDetectionError::RemoteFetch { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_does_not_retry_invalid_urls() {
+        let started = Instant::now();
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_secs(1),
+            ..fast_policy(3)
+        };
+        let result = fetch_image_bytes(&reqwest::Client::new(), "http://[::1", &policy, MAX_BYTES).await;
+
+        assert!(matches!(result, Err(FaceDetectionError::RemoteFetch { .. })));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_respects_total_time_budget() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_total: Duration::from_millis(250),
+        };
+        let url = format!("{}/face.jpg", server.uri());
+        let started = Instant::now();
+        let result = fetch_image_bytes(&reqwest::Client::new(), &url, &policy, MAX_BYTES).await;
+
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rejects_declared_oversized_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0; MAX_BYTES + 1]))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/face.jpg", server.uri());
+        let result = fetch_image_bytes(&reqwest::Client::new(), &url, &fast_policy(3), MAX_BYTES).await;
+        assert!(matches!(result, Err(FaceDetectionError::RemoteFetch { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_caps_body_without_content_length() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // A chunked response declares no length, so only the running count can stop it
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = socket.read(&mut request).await;
+            let mut response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+            for _ in 0..4 {
+                response.extend_from_slice(b"200\r\n");
+                response.extend_from_slice(&[0; 0x200]);
+                response.extend_from_slice(b"\r\n");
+            }
+            response.extend_from_slice(b"0\r\n\r\n");
+            let _ = socket.write_all(&response).await;
+        });
+
+        let url = format!("http://{address}/face.jpg");
+        let result = fetch_image_bytes(&reqwest::Client::new(), &url, &fast_policy(0), MAX_BYTES).await;
+        assert!(matches!(result, Err(FaceDetectionError::RemoteFetch { .. })));
+    }
+}
@@ -0,0 +1,50 @@
+//! Most recent annotated image, kept as a front-end debugging aid.
+//!
+//! When `ENABLE_DEBUG_ENDPOINTS` is set, the upload and annotate endpoints
+//! store the image they drew boxes on in a single in-memory slot, served by
+//! `GET /api/debug/last`. Each image replaces the previous one and nothing is
+//! persisted, so the slot is empty after a restart. Shared with handlers as
+//! `web::Data<LastAnnotatedImage>`.
+
+use image::DynamicImage;
+use std::sync::{Arc, Mutex};
+
+/// Slot holding the most recently annotated image.
+#[derive(Debug, Default)]
+pub struct LastAnnotatedImage {
+    image: Mutex<Option<Arc<DynamicImage>>>,
+}
+
+impl LastAnnotatedImage {
+    /// Creates an empty slot.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the stored image with `image`.
+    pub fn store(&self, image: Arc<DynamicImage>) {
+        *self.image.lock().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(image);
+    }
+
+    /// Returns the stored image, if any has been annotated since startup.
+    pub fn get(&self) -> Option<Arc<DynamicImage>> {
+        self.image.lock().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_keeps_only_latest_image() {
+        let slot = LastAnnotatedImage::new();
+        assert!(slot.get().is_none());
+
+        slot.store(Arc::new(DynamicImage::new_rgb8(10, 10)));
+        slot.store(Arc::new(DynamicImage::new_rgb8(20, 5)));
+
+        let latest = slot.get().unwrap();
+        assert_eq!((latest.width(), latest.height()), (20, 5));
+    }
+}
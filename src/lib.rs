@@ -16,10 +16,30 @@
 //! 
 //! The service is organized into several modules:
 //! 
+//! * [`access_log`] - Sampled access logging
 //! * [`api`] - HTTP API endpoints
+//! * [`backend`] - Sync and async detection backend hooks
+//! * [`batch`] - Batch detection with per-item timeouts
+//! * [`breaker`] - Circuit breaker for detection backend calls
+//! * [`cache`] - Shared decoded image cache
+//! * [`config`] - Application configuration
+//! * [`dedup`] - Duplicate upload detection
 //! * [`detection`] - Core face detection functionality
 //! * [`detector`] - Face detection implementation
+//! * [`embedding`] - Face embedding extraction
 //! * [`error`] - Unified error handling
+//! * [`fetch`] - Remote image fetching
+//! * [`last_image`] - Most recent annotated image for debugging
+//! * [`limiter`] - Bound on concurrent detections
+//! * [`matcher`] - Face matching by identity
+//! * [`request_log`] - Per-request summary logging
+//! * [`retention`] - Retention of uploaded originals for review
+//! * [`scan`] - Detection over server-local directories
+//! * [`selftest`] - Startup pipeline self-test
+//! * [`stats`] - Runtime statistics
+//! * [`storage`] - Pluggable blob storage
+//! * [`stream`] - Streaming JSON bodies for large results
+//! * [`tls`] - HTTPS server configuration
 //! * [`types`] - Type definitions and data structures
 //! 
 //! # Example
@@ -41,13 +61,33 @@
 #![warn(clippy::pedantic)]
 #![warn(clippy::nursery)]
 
+pub mod access_log;
 pub mod api;
+pub mod backend;
+pub mod batch;
+pub mod breaker;
+pub mod cache;
+pub mod config;
+pub mod dedup;
 pub mod detection;
 pub mod detector;
+pub mod embedding;
 pub mod error;
+pub mod fetch;
+pub mod last_image;
+pub mod limiter;
+pub mod matcher;
+pub mod request_log;
+pub mod retention;
+pub mod scan;
+pub mod selftest;
+pub mod stats;
+pub mod storage;
+pub mod stream;
+pub mod tls;
 pub mod types;
 
 // Re-export commonly used types
 pub use error::{FaceDetectionError, Result};
-pub use types::{ApiResponse, CropRequest, CropResponse, DetectionResult, Face, HealthResponse};
+pub use types::{ApiResponse, BoundingBox, CropRequest, CropResponse, DetectionResult, Face, HealthResponse};
 pub use detector::FaceDetector;
\ No newline at end of file
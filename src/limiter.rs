@@ -0,0 +1,99 @@
+//! Bound on concurrently running detections.
+//!
+//! Each detection holds a decoded image plus the drawn and encoded copies,
+//! so an unbounded number of them can exhaust memory during a traffic spike.
+//! Handlers take a permit from the shared [`DetectionLimiter`] before
+//! decoding and hold it until the response is built. When no permit frees up
+//! within a short wait, the request is shed with `Overloaded` (503 with
+//! `Retry-After`) instead of queuing indefinitely. Shared with handlers as
+//! `web::Data<DetectionLimiter>`.
+
+use crate::error::{FaceDetectionError, Result};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// `Retry-After` seconds sent with shed requests.
+const RETRY_AFTER_SECS: u64 = 1;
+
+/// Semaphore limiting how many detections run at once.
+#[derive(Debug)]
+pub struct DetectionLimiter {
+    /// Permits for running detections.
+    permits: Arc<Semaphore>,
+    /// How long a request waits for a permit before it is shed.
+    acquire_timeout: Duration,
+}
+
+impl DetectionLimiter {
+    /// Creates a limiter allowing `max_concurrent` detections, with requests
+    /// waiting at most `acquire_timeout` for a free slot. `0` means unlimited.
+    pub fn new(max_concurrent: usize, acquire_timeout: Duration) -> Self {
+        let permits = if max_concurrent == 0 { Semaphore::MAX_PERMITS } else { max_concurrent };
+        Self {
+            permits: Arc::new(Semaphore::new(permits)),
+            acquire_timeout,
+        }
+    }
+
+    /// Waits for a detection slot; the slot is released when the permit drops.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Overloaded` if no slot frees up within the acquire timeout.
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit> {
+        let acquire = Arc::clone(&self.permits).acquire_owned();
+        match tokio::time::timeout(self.acquire_timeout, acquire).await {
+            Ok(Ok(permit)) => Ok(permit),
+            // The semaphore is never closed, but treat that like being full
+            Ok(Err(_)) | Err(_) => {
+                tracing::warn!("All detection slots busy for {:?}; shedding request", self.acquire_timeout);
+                Err(FaceDetectionError::Overloaded {
+                    retry_after_secs: RETRY_AFTER_SECS,
+                })
+            }
+        }
+    }
+
+    /// Returns the number of free detection slots.
+    pub fn available(&self) -> usize {
+        self.permits.available_permits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sheds_when_saturated_and_recovers() {
+        let limiter = DetectionLimiter::new(2, Duration::from_millis(20));
+
+        let first = limiter.acquire().await.unwrap();
+        let _second = limiter.acquire().await.unwrap();
+        assert_eq!(limiter.available(), 0);
+
+        let started = std::time::Instant::now();
+        let shed = limiter.acquire().await.unwrap_err();
+        assert!(matches!(shed, FaceDetectionError::Overloaded { retry_after_secs: 1 }));
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        drop(first);
+        assert!(limiter.acquire().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_waiting_request_gets_released_slot() {
+        let limiter = Arc::new(DetectionLimiter::new(1, Duration::from_secs(5)));
+        let held = limiter.acquire().await.unwrap();
+
+        let waiter = tokio::spawn({
+            let limiter = Arc::clone(&limiter);
+            async move { limiter.acquire().await.map(drop) }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(held);
+
+        assert!(waiter.await.unwrap().is_ok());
+    }
+}
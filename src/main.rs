@@ -8,84 +8,54 @@
 #![warn(clippy::pedantic)]
 #![warn(clippy::nursery)]
 
-use actix_cors::Cors;
 use actix_files;
-use actix_web::{middleware, web, App, HttpServer};
-use std::env;
-use tracing::{error, info, Level};
+use actix_web::dev::Service;
+use actix_web::{web, App, HttpServer};
+use std::time::Duration;
+use tracing::{error, info, warn};
 use tracing_subscriber::{fmt, EnvFilter};
 
+mod access_log;
 mod api;
+mod backend;
+mod batch;
+mod breaker;
+mod cache;
+mod config;
+mod dedup;
 mod detection;
+mod embedding;
 mod error;
+mod last_image;
+mod limiter;
+mod matcher;
+mod request_log;
+mod retention;
+mod scan;
+mod selftest;
+mod stats;
+mod storage;
+mod stream;
+mod tls;
 mod types;
 
-use crate::api::{crop_faces, health_check, upload_image};
+use crate::api::{
+    annotate_image, configure_debug_routes, configure_scan_routes, cors_middleware, crop_faces, detect_and_crop, detect_batch, embed_faces, group_faces, has_face, json_config,
+    health_check, not_found, primary_face, readiness, overlay_png, smart_crop, supported_formats, upload_image, validate_image,
+};
+use crate::backend::{AsyncDetector, BenchDetector, BlockingDetector, BreakerDetector};
+use crate::cache::DecodeCache;
+use crate::config::AppConfig;
+use crate::dedup::RecentUploads;
 use crate::detector::FaceDetector;
-
-/// Application configuration.
-#[derive(Debug, Clone)]
-pub struct AppConfig {
-    /// Server port.
-    pub port: u16,
-    /// Log level.
-    pub log_level: Level,
-    /// Maximum file upload size in bytes.
-    pub max_file_size: usize,
-    /// Upload directory path.
-    pub upload_dir: String,
-}
-
-impl Default for AppConfig {
-    fn default() -> Self {
-        Self {
-            port: 8080,
-            log_level: Level::INFO,
-            max_file_size: 10 * 1024 * 1024, // 10MB
-            upload_dir: "uploads".to_string(),
-        }
-    }
-}
-
-impl AppConfig {
-    /// Loads configuration from environment variables.
-    pub fn from_env() -> Self {
-        let mut config = Self::default();
-        
-        // Parse port
-        if let Ok(port_str) = env::var("PORT") {
-            if let Ok(port) = port_str.parse::<u16>() {
-                config.port = port;
-            }
-        }
-        
-        // Parse log level
-        if let Ok(log_level_str) = env::var("RUST_LOG") {
-            match log_level_str.to_lowercase().as_str() {
-                "error" => config.log_level = Level::ERROR,
-                "warn" => config.log_level = Level::WARN,
-                "info" => config.log_level = Level::INFO,
-                "debug" => config.log_level = Level::DEBUG,
-                "trace" => config.log_level = Level::TRACE,
-                _ => {}
-            }
-        }
-        
-        // Parse max file size
-        if let Ok(size_str) = env::var("MAX_FILE_SIZE") {
-            if let Ok(size) = size_str.parse::<usize>() {
-                config.max_file_size = size;
-            }
-        }
-        
-        // Parse upload directory
-        if let Ok(upload_dir) = env::var("UPLOAD_DIR") {
-            config.upload_dir = upload_dir;
-        }
-        
-        config
-    }
-}
+use crate::embedding::{ColorHistogramEmbedder, Embedder};
+use crate::last_image::LastAnnotatedImage;
+use crate::limiter::DetectionLimiter;
+use crate::matcher::{CosineMatcher, FaceMatcher};
+use crate::selftest::SelfTestReport;
+use crate::stats::RuntimeStats;
+use crate::storage::Storage;
+use std::sync::Arc;
 
 /// Initializes the tracing/logging system.
 fn init_tracing(config: &AppConfig) {
@@ -104,33 +74,119 @@ fn init_tracing(config: &AppConfig) {
         .expect("setting default subscriber failed");
 }
 
+/// Returns the backend handlers detect with: the canned [`BenchDetector`]
+/// with `BENCH_MODE`, otherwise `detector` on the blocking pool, guarded by
+/// the circuit breaker when one is configured.
+fn build_backend(detector: &FaceDetector, config: &AppConfig) -> Arc<dyn AsyncDetector> {
+    let backend: Arc<dyn AsyncDetector> = if config.bench_mode {
+        Arc::new(BenchDetector::default())
+    } else {
+        Arc::new(BlockingDetector::new(detector.clone()))
+    };
+    match config.build_breaker() {
+        Some(breaker) => Arc::new(BreakerDetector::new(backend, breaker)),
+        None => backend,
+    }
+}
+
+/// Shared state created once at startup and cloned into every worker's app.
+#[derive(Clone)]
+struct AppState {
+    detector: web::Data<FaceDetector>,
+    backend: web::Data<dyn AsyncDetector>,
+    stats: web::Data<RuntimeStats>,
+    storage: web::Data<dyn Storage>,
+    decode_cache: web::Data<DecodeCache>,
+    recent_uploads: web::Data<RecentUploads>,
+    limiter: web::Data<DetectionLimiter>,
+    self_test: web::Data<SelfTestReport>,
+    last_image: web::Data<LastAnnotatedImage>,
+}
+
 /// Creates and configures the Actix-web application.
-fn create_app(detector: web::Data<FaceDetector>, config: &AppConfig) -> App<FaceDetector> {
+fn create_app(state: AppState, config: &AppConfig) -> App<FaceDetector> {
+    let request_stats = state.stats.clone();
+    let log_requests = config.log_requests;
+    let access_log = Arc::new(crate::access_log::AccessLog::new(config.log_sample_rate));
+    
     App::new()
         // Add shared state
-        .app_data(detector)
+        .app_data(state.detector)
+        .app_data(state.backend)
+        .app_data(state.stats)
+        .app_data(state.storage)
+        .app_data(state.decode_cache)
+        .app_data(state.recent_uploads)
+        .app_data(state.limiter)
+        .app_data(state.self_test)
         .app_data(web::Data::new(config.clone()))
+        .app_data(web::Data::from(
+            Arc::new(ColorHistogramEmbedder::default()) as Arc<dyn Embedder>
+        ))
+        .app_data(web::Data::from(Arc::new(CosineMatcher) as Arc<dyn FaceMatcher>))
+        .configure(|cfg| {
+            if config.enable_debug_endpoints {
+                cfg.app_data(state.last_image);
+            }
+        })
         
         // Configure JSON payload limits
-        .app_data(web::JsonConfig::default().limit(config.max_file_size))
+        .app_data(json_config(config.max_file_size))
         .app_data(web::FormConfig::default().limit(config.max_file_size))
+        .app_data(web::PayloadConfig::new(config.max_file_size))
         
         // Enable CORS
-        .wrap(
-            Cors::default()
-                .allow_any_origin()
-                .allow_any_method()
-                .allow_any_header()
-                .max_age(3600)
-        )
+        .wrap(cors_middleware(config))
+        
+        // Enable sampled access logging
+        .wrap_fn(move |req, srv| {
+            let start = std::time::Instant::now();
+            let access_log = access_log.clone();
+            let response = srv.call(req);
+            async move {
+                let response = response.await?;
+                access_log.log_response(&response, start.elapsed());
+                Ok(response)
+            }
+        })
         
-        // Enable request logging
-        .wrap(middleware::Logger::default())
+        // Count served requests
+        .wrap_fn(move |req, srv| {
+            request_stats.record_request();
+            srv.call(req)
+        })
+        
+        // Log a summary of each request when enabled
+        .wrap_fn(move |req, srv| {
+            let start = std::time::Instant::now();
+            let response = srv.call(req);
+            async move {
+                let response = response.await?;
+                if log_requests {
+                    crate::request_log::log_response(&response, start.elapsed());
+                }
+                Ok(response)
+            }
+        })
         
         // API routes
         .service(health_check)
+        .service(readiness)
+        .service(supported_formats)
         .service(upload_image)
+        .service(detect_batch)
         .service(crop_faces)
+        .service(annotate_image)
+        .service(overlay_png)
+        .service(has_face)
+        .service(primary_face)
+        .service(detect_and_crop)
+        .service(smart_crop)
+        .service(validate_image)
+        .service(embed_faces)
+        .service(group_faces)
+        .configure(|cfg| configure_debug_routes(cfg, config))
+        .configure(|cfg| configure_scan_routes(cfg, config))
         
         // Static file serving
         .service(
@@ -140,6 +196,9 @@ fn create_app(detector: web::Data<FaceDetector>, config: &AppConfig) -> App<Face
         
         // Serve index.html for root
         .route("/", web::get().to(serve_index))
+        
+        // JSON 404/405 for unknown routes and methods
+        .default_service(web::to(not_found))
 }
 
 /// Serves the main HTML page.
@@ -162,62 +221,117 @@ async fn main() -> std::io::Result<()> {
     info!("Starting Face Detection Rust Server v{}", env!("CARGO_PKG_VERSION"));
     info!("Configuration: {:?}", config);
     
-    // Create uploads directory
-    std::fs::create_dir_all(&config.upload_dir)
-        .unwrap_or_else(|e| {
-            error!("Failed to create uploads directory: {}", e);
-            std::process::exit(1);
-        });
+    // Validate configuration
+    if let Err(e) = config.validate() {
+        error!("Invalid configuration: {}", e);
+        std::process::exit(1);
+    }
+    
+    if config.bench_mode {
+        warn!("==========================================================");
+        warn!("BENCH_MODE is enabled: detection is DISABLED and every");
+        warn!("image gets the same canned faces. Never use in production.");
+        warn!("==========================================================");
+    }
+    
+    // Load the TLS certificate up front so a bad path fails at startup
+    let tls_config = match (&config.tls_cert_path, &config.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => match crate::tls::load_server_config(cert_path, key_path) {
+            Ok(tls_config) => Some(tls_config),
+            Err(e) => {
+                error!("Failed to load TLS certificate: {}", e);
+                std::process::exit(1);
+            }
+        },
+        _ => None,
+    };
     
     // Initialize face detector
-    let detector = match FaceDetector::new() {
+    let detector = match config.build_detector() {
         Ok(detector) => {
             info!("Face detector initialized successfully");
             web::Data::new(detector)
         }
         Err(e) => {
             error!("Failed to initialize face detector: {}", e);
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to initialize face detector"
-            ));
+            return Err(std::io::Error::other("Failed to initialize face detector"));
         }
     };
     
+    // Warm up the detection backend so the first request is not slowed down
+    let warmup_start = std::time::Instant::now();
+    if let Err(e) = detector.warmup() {
+        error!("Face detector warmup failed: {}", e);
+        return Err(std::io::Error::other("Face detector warmup failed"));
+    }
+    info!("Face detector warmed up in {:?}", warmup_start.elapsed());
+    
+    // Check the full pipeline; a failure only keeps readiness failing
+    let self_test = web::Data::new(crate::selftest::run_self_test(&detector));
+    
+    let stats = web::Data::new(RuntimeStats::new());
+    let decode_cache = web::Data::new(DecodeCache::new(
+        config.decode_cache_max_entries,
+        config.decode_cache_max_bytes,
+    ));
+    let recent_uploads = web::Data::new(RecentUploads::new(
+        config.duplicate_history_size,
+        config.duplicate_hash_threshold,
+    ));
+    let limiter = web::Data::new(DetectionLimiter::new(
+        config.max_concurrent_detections,
+        Duration::from_millis(config.detection_queue_timeout_ms),
+    ));
+    let last_image = web::Data::new(LastAnnotatedImage::new());
+    let backend = web::Data::from(build_backend(&detector, &config));
+    
+    // Initialize storage backend
+    let storage = match crate::storage::from_config(&config).await {
+        Ok(storage) => web::Data::from(storage),
+        Err(e) => {
+            error!("Failed to initialize storage backend: {}", e);
+            std::process::exit(1);
+        }
+    };
+    
+    // Expire retained uploads in the background
+    if config.retain_uploads && config.retained_upload_ttl_secs > 0 {
+        crate::retention::spawn_sweeper(
+            storage.clone().into_inner(),
+            Duration::from_secs(config.retained_upload_ttl_secs),
+        );
+    }
+    
     info!("Server will run on port {}", config.port);
     
     // Start HTTP server
-    let server = HttpServer::new(move || create_app(detector.clone(), &config))
-        .bind(("0.0.0.0", config.port))?
-        .run();
+    let port = config.port;
+    let workers = config.workers;
+    let keep_alive = Duration::from_secs(config.keep_alive_secs);
+    let state = AppState {
+        detector,
+        backend,
+        stats,
+        storage,
+        decode_cache,
+        recent_uploads,
+        limiter,
+        self_test,
+        last_image,
+    };
+    let server = HttpServer::new(move || create_app(state.clone(), &config))
+        .workers(workers)
+        .keep_alive(keep_alive);
+    
+    // Serve HTTPS, with HTTP/2 negotiated over ALPN, when a certificate is configured
+    let scheme = if tls_config.is_some() { "https" } else { "http" };
+    let server = match tls_config {
+        Some(tls_config) => server.bind_rustls_0_23(("0.0.0.0", port), tls_config)?,
+        None => server.bind(("0.0.0.0", port))?,
+    }
+    .run();
     
-    info!("Server started successfully at http://0.0.0.0:{}", config.port);
+    info!("Server started successfully at {}://0.0.0.0:{}", scheme, port);
     
     server.await
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_app_config_default() {
-        let config = AppConfig::default();
-        assert_eq!(config.port, 8080);
-        assert_eq!(config.max_file_size, 10 * 1024 * 1024);
-        assert_eq!(config.upload_dir, "uploads");
-    }
-
-    #[test]
-    fn test_app_config_from_env() {
-        std::env::set_var("PORT", "3000");
-        std::env::set_var("RUST_LOG", "debug");
-        
-        let config = AppConfig::from_env();
-        assert_eq!(config.port, 3000);
-        assert_eq!(config.log_level, Level::DEBUG);
-        
-        std::env::remove_var("PORT");
-        std::env::remove_var("RUST_LOG");
-    }
-}
\ No newline at end of file
@@ -0,0 +1,80 @@
+//! Face matching by identity.
+//! 
+//! This module defines the [`FaceMatcher`] hook scoring how alike two face
+//! embeddings are, a cosine-similarity placeholder implementation until a
+//! real recognition model is integrated, and [`group_faces`] for clustering
+//! faces of the same person.
+
+/// Scores how likely two face embeddings are to belong to the same person.
+pub trait FaceMatcher: Send + Sync {
+    /// Returns the similarity of two embeddings, higher meaning more alike.
+    fn similarity(&self, a: &[f32], b: &[f32]) -> f32;
+}
+
+/// Placeholder matcher scoring embeddings by cosine similarity.
+/// 
+/// Identical embeddings score 1; embeddings of differing length or zero norm
+/// score 0.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CosineMatcher;
+
+impl FaceMatcher for CosineMatcher {
+    fn similarity(&self, a: &[f32], b: &[f32]) -> f32 {
+        if a.len() != b.len() {
+            return 0.0;
+        }
+        
+        let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+        let norms = norm(a) * norm(b);
+        if norms > 0.0 {
+            a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>() / norms
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Groups embedding indices that `matcher` scores at least `threshold` alike.
+/// 
+/// Groups are the connected components of that relation (single linkage),
+/// so every face lands in exactly one group, alone if it matches no other.
+/// Groups are ordered by their lowest index and list indices in ascending
+/// order.
+pub fn group_faces(embeddings: &[Vec<f32>], matcher: &dyn FaceMatcher, threshold: f32) -> Vec<Vec<usize>> {
+    crate::types::connected_components(embeddings.len(), |i, j| {
+        matcher.similarity(&embeddings[i], &embeddings[j]) >= threshold
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity_bounds() {
+        let matcher = CosineMatcher;
+        
+        assert!((matcher.similarity(&[1.0, 2.0], &[2.0, 4.0]) - 1.0).abs() < 1e-6);
+        assert!(matcher.similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(matcher.similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(matcher.similarity(&[1.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_group_faces_links_transitively() {
+        let embeddings = vec![
+            vec![1.0, 0.0, 0.0],
+            vec![0.0, 0.0, 1.0],
+            vec![0.9, 0.3, 0.0],
+            vec![0.8, 0.6, 0.0],
+        ];
+        
+        // 0 and 3 are only linked through 2
+        let groups = group_faces(&embeddings, &CosineMatcher, 0.9);
+        assert_eq!(groups, vec![vec![0, 2, 3], vec![1]]);
+        
+        let groups = group_faces(&embeddings, &CosineMatcher, 1.0);
+        assert_eq!(groups, vec![vec![0], vec![1], vec![2], vec![3]]);
+        assert!(group_faces(&[], &CosineMatcher, 0.5).is_empty());
+    }
+}
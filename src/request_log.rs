@@ -0,0 +1,89 @@
+//! Per-request summary logging for debugging client integrations.
+//!
+//! Enabled with `LOG_REQUESTS`, [`log_response`] writes one debug-level line
+//! per request with its method, path, declared length, status, face count
+//! and duration. Request and response bodies are never read, so image
+//! payloads, base64 or otherwise, cannot end up in the log; the query string
+//! is left out as well.
+
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::CONTENT_LENGTH;
+use std::time::Duration;
+
+/// Response header carrying the number of detected faces.
+const FACE_COUNT_HEADER: &str = "x-face-count";
+
+/// Logs a summary of a handled request at debug level.
+///
+/// The face count comes from the `X-Face-Count` response header and is
+/// logged as `-` for responses without one.
+pub fn log_response<B>(res: &ServiceResponse<B>, elapsed: Duration) {
+    let req = res.request();
+    let header = |headers: &actix_web::http::header::HeaderMap, name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("-")
+            .to_string()
+    };
+
+    tracing::debug!(
+        method = %req.method(),
+        path = %req.path(),
+        content_length = %header(req.headers(), CONTENT_LENGTH.as_str()),
+        status = res.status().as_u16(),
+        faces = %header(res.headers(), FACE_COUNT_HEADER),
+        elapsed_ms = elapsed.as_secs_f64() * 1000.0,
+        "Request handled"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, HttpResponse};
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    /// Collects formatted tracing output.
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_log_line_has_face_count_but_no_payload() {
+        let payload = format!(r#"{{"imageData":"data:image/jpeg;base64,{}"}}"#, "QUJD".repeat(64));
+        let req = test::TestRequest::post()
+            .uri("/api/upload?imageData=QUJDQUJD")
+            .insert_header((CONTENT_LENGTH, payload.len().to_string()))
+            .set_payload(payload.clone())
+            .to_srv_request();
+        let res = req.into_response(HttpResponse::Ok().insert_header((FACE_COUNT_HEADER, "3")).finish());
+
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, || log_response(&res, Duration::from_millis(12)));
+
+        let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("faces=3"), "{}", output);
+        assert!(output.contains("method=POST"));
+        assert!(output.contains("path=/api/upload"));
+        assert!(output.contains(&format!("content_length={}", payload.len())));
+        assert!(!output.contains("QUJD"), "payload leaked into log: {}", output);
+        assert!(!output.contains("base64"));
+    }
+}
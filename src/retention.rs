@@ -0,0 +1,110 @@
+//! Retention of uploaded originals for moderation review.
+//!
+//! With `RETAIN_UPLOADS` set, processed uploads are stored through the
+//! configured [`Storage`] under `retained/{date}/{upload_id}.{ext}`, so every
+//! instance sharing the backend sees them. A background sweeper deletes
+//! retained uploads once they are older than `RETAINED_UPLOAD_TTL_SECS`.
+
+use crate::error::Result;
+use crate::storage::Storage;
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Storage key prefix of retained uploads.
+pub const RETAINED_PREFIX: &str = "retained";
+
+/// How often the sweeper looks for expired retained uploads.
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Format of the per-day key segment.
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Stores the uploaded `bytes` under today's retention prefix.
+///
+/// The blob is named after `upload_id`, with the extension of the image
+/// format found in its contents. Returns its storage key.
+///
+/// # Errors
+///
+/// Returns the storage backend's error if the blob cannot be written.
+pub async fn retain_upload(storage: &dyn Storage, bytes: &[u8], upload_id: &str) -> Result<String> {
+    let extension = image::guess_format(bytes)
+        .ok()
+        .and_then(|format| format.extensions_str().first().copied())
+        .unwrap_or("bin");
+
+    let key = format!(
+        "{}/{}/{}.{}",
+        RETAINED_PREFIX,
+        Utc::now().format(DATE_FORMAT),
+        upload_id,
+        extension
+    );
+    storage.put(&key, bytes).await?;
+    Ok(key)
+}
+
+/// Deletes retained uploads last written more than `ttl` before `now`.
+///
+/// Returns the number of uploads deleted.
+///
+/// # Errors
+///
+/// Returns the storage backend's error if retained uploads cannot be listed.
+/// Failures to delete single uploads are logged and skipped.
+pub async fn sweep_retained(storage: &dyn Storage, ttl: Duration, now: SystemTime) -> Result<usize> {
+    let mut removed = 0;
+    for blob in storage.list(&format!("{}/", RETAINED_PREFIX)).await? {
+        let expired = now.duration_since(blob.modified).is_ok_and(|age| age > ttl);
+        if !expired {
+            continue;
+        }
+        match storage.delete(&blob.key).await {
+            Ok(()) => removed += 1,
+            Err(e) => tracing::warn!("Failed to remove retained upload {}: {}", blob.key, e),
+        }
+    }
+    Ok(removed)
+}
+
+/// Starts a background task running [`sweep_retained`] every [`SWEEP_INTERVAL`].
+pub fn spawn_sweeper(storage: Arc<dyn Storage>, ttl: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            match sweep_retained(storage.as_ref(), ttl, SystemTime::now()).await {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!("Removed {} expired retained uploads", removed),
+                Err(e) => tracing::warn!("Retained upload sweep failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::LocalStorage;
+
+    #[tokio::test]
+    async fn test_sweep_removes_only_expired_uploads() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(dir.path());
+        let key = retain_upload(&storage, b"a", "a").await.unwrap();
+        storage.put("crops/b.jpg", b"b").await.unwrap();
+
+        let ttl = Duration::from_secs(3600);
+        assert_eq!(sweep_retained(&storage, ttl, SystemTime::now()).await.unwrap(), 0);
+        assert_eq!(storage.get(&key).await.unwrap(), b"a");
+
+        let later = SystemTime::now() + ttl + Duration::from_secs(60);
+        assert_eq!(sweep_retained(&storage, ttl, later).await.unwrap(), 1);
+        assert!(storage.get(&key).await.is_err());
+        assert!(!dir.path().join(RETAINED_PREFIX).exists());
+
+        // Blobs outside the retention prefix are never swept
+        assert_eq!(storage.get("crops/b.jpg").await.unwrap(), b"b");
+    }
+}
@@ -0,0 +1,168 @@
+//! Detection over a directory of server-local images.
+//!
+//! Meant for one-off batch jobs run by an operator: the client names a
+//! directory below the configured scan root and gets one JSON line per image
+//! back as each detection finishes. Requested paths are canonicalized before
+//! they are checked against the root, so `..` components and symlinks cannot
+//! lead outside it.
+
+use crate::detection::SUPPORTED_INPUT_FORMATS;
+use crate::error::{validation_error, FaceDetectionError, IoSnafu, Result};
+use crate::types::{DetectionResult, ScanLine};
+use actix_web::web::Bytes;
+use futures_util::stream::{self, Stream, StreamExt};
+use image::ImageFormat;
+use snafu::ResultExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Resolves `requested` against `root` and checks it stays inside it.
+///
+/// Relative paths are taken relative to `root`.
+///
+/// # Errors
+///
+/// Returns `PathNotAllowed` if the path does not exist or resolves outside
+/// `root`, without telling the two apart, and `Validation` if it is not a
+/// directory.
+pub fn resolve_scan_dir(root: &Path, requested: &str) -> Result<PathBuf> {
+    let not_allowed = || FaceDetectionError::PathNotAllowed {
+        path: requested.to_string(),
+    };
+
+    let root = root.canonicalize().map_err(|e| {
+        tracing::error!("Scan root {:?} is unavailable: {}", root, e);
+        not_allowed()
+    })?;
+    let dir = root.join(requested).canonicalize().map_err(|_| not_allowed())?;
+
+    if !dir.starts_with(&root) {
+        tracing::warn!("Rejected scan of {:?} outside scan root {:?}", dir, root);
+        return Err(not_allowed());
+    }
+
+    if !dir.is_dir() {
+        return Err(validation_error(format!("{} is not a directory", requested)));
+    }
+
+    Ok(dir)
+}
+
+/// Lists the image files directly inside `dir`, sorted by name.
+///
+/// Files are picked by extension; subdirectories are not descended into.
+///
+/// # Errors
+///
+/// Returns `Io` if the directory cannot be read.
+pub fn list_image_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).context(IoSnafu)? {
+        let path = entry.context(IoSnafu)?.path();
+        let is_image = ImageFormat::from_path(&path)
+            .is_ok_and(|format| SUPPORTED_INPUT_FORMATS.contains(&format));
+        if is_image && path.is_file() {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Runs `detect` on each file in turn and streams one JSON line per file.
+///
+/// Each detection runs on the blocking pool bounded by `timeout`. A file that
+/// fails or times out gets a line with `error` set instead of ending the
+/// stream.
+pub fn scan_lines<F>(files: Vec<PathBuf>, timeout: Duration, detect: F) -> impl Stream<Item = Result<Bytes>>
+where
+    F: Fn(&Path) -> Result<DetectionResult> + Send + Sync + 'static,
+{
+    let detect = Arc::new(detect);
+
+    stream::iter(files).then(move |path| {
+        let detect = Arc::clone(&detect);
+        async move {
+            let file = path
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+
+            let work = tokio::task::spawn_blocking(move || detect(&path));
+            let (result, error) = match tokio::time::timeout(timeout, work).await {
+                Ok(Ok(Ok(result))) => (Some(result), None),
+                Ok(Ok(Err(e))) => (None, Some(e.to_string())),
+                Ok(Err(join_error)) => {
+                    tracing::error!("Scan of {} panicked: {}", file, join_error);
+                    (None, Some("Internal server error".to_string()))
+                }
+                Err(_elapsed) => (None, Some(format!("Detection timed out after {:?}", timeout))),
+            };
+
+            let mut line = serde_json::to_vec(&ScanLine { file, result, error }).map_err(|e| {
+                tracing::error!("Failed to serialize scan line: {}", e);
+                FaceDetectionError::InternalError
+            })?;
+            line.push(b'\n');
+            Ok(Bytes::from(line))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolves_directories_inside_root() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("a/b")).unwrap();
+
+        let dir = resolve_scan_dir(root.path(), "a/b").unwrap();
+        assert_eq!(dir, root.path().canonicalize().unwrap().join("a/b"));
+
+        // `..` is fine as long as it stays inside the root
+        assert!(resolve_scan_dir(root.path(), "a/b/..").is_ok());
+    }
+
+    #[test]
+    fn test_rejects_paths_leaving_root() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::create_dir(root.path().join("a")).unwrap();
+
+        for requested in ["..", "a/../..", outside.path().to_str().unwrap(), "missing"] {
+            let result = resolve_scan_dir(root.path(), requested);
+            assert!(
+                matches!(result, Err(FaceDetectionError::PathNotAllowed { .. })),
+                "{} should be rejected",
+                requested
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rejects_symlink_leaving_root() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), root.path().join("link")).unwrap();
+
+        let result = resolve_scan_dir(root.path(), "link");
+        assert!(matches!(result, Err(FaceDetectionError::PathNotAllowed { .. })));
+    }
+
+    #[test]
+    fn test_lists_only_image_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.png", "a.JPG", "notes.txt"] {
+            std::fs::write(dir.path().join(name), b"").unwrap();
+        }
+        std::fs::create_dir(dir.path().join("nested.png")).unwrap();
+
+        let files = list_image_files(dir.path()).unwrap();
+        let names: Vec<_> = files.iter().map(|p| p.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, ["a.JPG", "b.png"]);
+    }
+}
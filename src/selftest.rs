@@ -0,0 +1,103 @@
+//! Startup self-test of the detection pipeline.
+//!
+//! [`run_self_test`] pushes a synthetic image through the same steps an
+//! upload takes (detect, draw, encode, decode the encoded output again) and
+//! reports whether they all worked. It runs once after the detector is
+//! initialized; a failure does not stop the server but keeps
+//! `GET /api/ready` failing, so the instance receives no traffic. The report
+//! is shared with handlers as `web::Data<SelfTestReport>`.
+
+use crate::detector::FaceDetector;
+use crate::error::{FaceDetectionError, Result};
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Smallest side length of the synthetic self-test image.
+const SELF_TEST_IMAGE_SIZE: u32 = 256;
+
+/// Outcome of the startup self-test.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestReport {
+    /// Whether every pipeline step succeeded.
+    pub passed: bool,
+    /// Faces found in the synthetic image.
+    pub faces: usize,
+    /// Time the whole self-test took, in milliseconds.
+    pub elapsed_ms: f64,
+    /// Why the self-test failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Runs the synthetic image through detect, draw, encode and decode.
+///
+/// Failures are reported in the returned [`SelfTestReport`] rather than as
+/// an error, and logged either way.
+pub fn run_self_test(detector: &FaceDetector) -> SelfTestReport {
+    let start = Instant::now();
+    let outcome = run_pipeline(detector);
+    let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    match outcome {
+        Ok(faces) => {
+            tracing::info!("Pipeline self-test passed: {} faces in {:.1}ms", faces, elapsed_ms);
+            SelfTestReport { passed: true, faces, elapsed_ms, error: None }
+        }
+        Err(e) => {
+            tracing::error!("Pipeline self-test failed after {:.1}ms: {}", elapsed_ms, e);
+            SelfTestReport { passed: false, faces: 0, elapsed_ms, error: Some(e.to_string()) }
+        }
+    }
+}
+
+/// Runs each pipeline step and returns the number of faces found.
+fn run_pipeline(detector: &FaceDetector) -> Result<usize> {
+    // Large enough for the detector to consider, with some structure to find
+    let side = SELF_TEST_IMAGE_SIZE.max(detector.min_dimension().saturating_add(1));
+    let synthetic = DynamicImage::ImageRgb8(RgbImage::from_fn(side, side, |x, y| {
+        Rgb([(x % 256) as u8, (y % 256) as u8, 128])
+    }));
+
+    let detection = detector.detect_image(&synthetic)?;
+    let drawn = detector.draw_bounding_boxes(&synthetic, &detection.faces)?;
+    let encoded = crate::detection::image_to_base64(&drawn)?;
+    let bytes = crate::detection::decode_base64_image(&encoded)?;
+    let decoded = crate::detection::decode_image(&bytes)?;
+
+    if bytes.is_empty() || decoded.dimensions() != synthetic.dimensions() {
+        tracing::error!(
+            "Self-test output is {:?} ({} bytes), expected {:?}",
+            decoded.dimensions(),
+            bytes.len(),
+            synthetic.dimensions()
+        );
+        return Err(FaceDetectionError::InvalidImageData);
+    }
+
+    Ok(detection.total_faces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_passes_with_mock_detector() {
+        let report = run_self_test(&FaceDetector::new().unwrap());
+
+        assert!(report.passed, "self-test failed: {:?}", report.error);
+        assert!(report.error.is_none());
+        assert_eq!(report.faces, 1);
+    }
+
+    #[test]
+    fn test_self_test_image_fits_detector_minimum() {
+        let detector = FaceDetector::new().unwrap().with_min_dimension(400);
+        let report = run_self_test(&detector);
+
+        assert!(report.passed);
+        assert_eq!(report.faces, 2);
+    }
+}
@@ -0,0 +1,91 @@
+//! Runtime statistics for the face detection service.
+//!
+//! Counters are updated lock-free from request handlers and shared as
+//! `web::Data<RuntimeStats>`.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Process-wide request and detection counters.
+#[derive(Debug, Default)]
+pub struct RuntimeStats {
+    /// Total requests served.
+    requests_served: AtomicU64,
+    /// Total faces detected across all requests.
+    faces_detected: AtomicU64,
+    /// Cache lookups that found an entry.
+    cache_hits: AtomicU64,
+    /// Cache lookups that missed.
+    cache_misses: AtomicU64,
+}
+
+/// Point-in-time copy of [`RuntimeStats`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsSnapshot {
+    /// Total requests served.
+    pub requests_served: u64,
+    /// Total faces detected across all requests.
+    pub faces_detected: u64,
+    /// Fraction of cache lookups that hit, or `None` before any lookup.
+    pub cache_hit_rate: Option<f64>,
+}
+
+impl RuntimeStats {
+    /// Creates a new set of zeroed counters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a served request.
+    pub fn record_request(&self) {
+        self.requests_served.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records faces found by a detection.
+    pub fn record_faces(&self, count: usize) {
+        self.faces_detected.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Records the outcome of a cache lookup.
+    pub fn record_cache_lookup(&self, hit: bool) {
+        let counter = if hit { &self.cache_hits } else { &self.cache_misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the current counter values.
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let hits = self.cache_hits.load(Ordering::Relaxed);
+        let lookups = hits + self.cache_misses.load(Ordering::Relaxed);
+
+        StatsSnapshot {
+            requests_served: self.requests_served.load(Ordering::Relaxed),
+            faces_detected: self.faces_detected.load(Ordering::Relaxed),
+            cache_hit_rate: (lookups > 0).then(|| hits as f64 / lookups as f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_reflects_counters() {
+        let stats = RuntimeStats::new();
+        assert_eq!(stats.snapshot().cache_hit_rate, None);
+
+        stats.record_request();
+        stats.record_request();
+        stats.record_faces(3);
+        stats.record_cache_lookup(true);
+        stats.record_cache_lookup(false);
+        stats.record_cache_lookup(true);
+        stats.record_cache_lookup(true);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.requests_served, 2);
+        assert_eq!(snapshot.faces_detected, 3);
+        assert_eq!(snapshot.cache_hit_rate, Some(0.75));
+    }
+}
@@ -0,0 +1,336 @@
+//! Pluggable blob storage for persisted files.
+//!
+//! [`LocalStorage`] keeps blobs on the local filesystem. With the `s3` feature,
+//! [`S3Storage`] stores them in an S3 bucket so several instances behind a
+//! load balancer share the same data. The backend is chosen by
+//! `STORAGE_BACKEND` and shared with handlers as `web::Data<dyn Storage>`.
+
+use crate::config::{AppConfig, StorageBackend};
+use crate::error::{validation_error, FaceDetectionError, Result};
+use async_trait::async_trait;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// A key/value store for binary blobs.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Stores `bytes` under `key`, replacing any existing blob.
+    async fn put(&self, key: &str, bytes: &[u8]) -> Result<()>;
+
+    /// Returns the blob stored under `key`.
+    async fn get(&self, key: &str) -> Result<Vec<u8>>;
+
+    /// Removes the blob stored under `key`.
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// Lists the blobs whose keys start with `prefix`.
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredBlob>>;
+}
+
+/// A blob returned by [`Storage::list`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredBlob {
+    /// Key the blob is stored under.
+    pub key: String,
+    /// When the blob was last written.
+    pub modified: SystemTime,
+}
+
+/// Builds the storage backend selected by the configuration.
+///
+/// # Errors
+///
+/// Returns a `Configuration` error if the selected backend is unavailable or
+/// incompletely configured.
+pub async fn from_config(config: &AppConfig) -> Result<Arc<dyn Storage>> {
+    match config.storage_backend {
+        StorageBackend::Local => Ok(Arc::new(LocalStorage::new(&config.storage_dir))),
+        #[cfg(feature = "s3")]
+        StorageBackend::S3 => {
+            let bucket = config.s3_bucket.clone().ok_or_else(|| {
+                crate::error::config_error("S3_BUCKET is required when STORAGE_BACKEND=s3")
+            })?;
+            Ok(Arc::new(S3Storage::from_env(bucket).await))
+        }
+        #[cfg(not(feature = "s3"))]
+        StorageBackend::S3 => Err(crate::error::config_error(
+            "STORAGE_BACKEND=s3 requires building with the `s3` feature",
+        )),
+    }
+}
+
+/// Storage backed by a directory on the local filesystem.
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    /// Directory blobs are stored under.
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    /// Creates a store rooted at `root`. The directory is created on first write.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Resolves `key` to a path under the root, rejecting keys that would escape it.
+    fn path_for(&self, key: &str) -> Result<PathBuf> {
+        let relative = Path::new(key);
+        let is_safe = !key.is_empty()
+            && relative.components().all(|c| matches!(c, Component::Normal(_)));
+
+        if !is_safe {
+            return Err(validation_error(format!("Invalid storage key: {:?}", key)));
+        }
+
+        Ok(self.root.join(relative))
+    }
+}
+
+/// Lists the files under `root` whose keys, their `/`-separated paths
+/// relative to `root`, start with `prefix`.
+fn list_files(root: &Path, prefix: &str) -> Result<Vec<StoredBlob>> {
+    let io_error = |e| FaceDetectionError::Io { source: e };
+    let start = prefix.rfind('/').map_or("", |end| &prefix[..end]);
+
+    let mut pending = vec![root.join(start)];
+    let mut blobs = Vec::new();
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(io_error(e)),
+        };
+        for entry in entries {
+            let entry = entry.map_err(io_error)?;
+            let metadata = entry.metadata().map_err(io_error)?;
+            if metadata.is_dir() {
+                pending.push(entry.path());
+                continue;
+            }
+
+            let path = entry.path();
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let key = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if key.starts_with(prefix) {
+                blobs.push(StoredBlob { key, modified: metadata.modified().map_err(io_error)? });
+            }
+        }
+    }
+
+    blobs.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(blobs)
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, bytes: &[u8]) -> Result<()> {
+        let path = self.path_for(key)?;
+        let create_parent = || async {
+            match path.parent() {
+                Some(parent) => tokio::fs::create_dir_all(parent).await,
+                None => Ok(()),
+            }
+        };
+
+        create_parent().await.map_err(|e| FaceDetectionError::Io { source: e })?;
+        let written = match tokio::fs::write(&path, bytes).await {
+            // A concurrent delete may have pruned the directory just created
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                create_parent().await.map_err(|e| FaceDetectionError::Io { source: e })?;
+                tokio::fs::write(&path, bytes).await
+            }
+            result => result,
+        };
+        written.map_err(|e| FaceDetectionError::Io { source: e })
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let path = self.path_for(key)?;
+
+        tokio::fs::read(&path)
+            .await
+            .map_err(|e| FaceDetectionError::Io { source: e })
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let path = self.path_for(key)?;
+
+        tokio::fs::remove_file(&path)
+            .await
+            .map_err(|e| FaceDetectionError::Io { source: e })?;
+
+        // Prune directories left empty, which fails harmlessly on the first non-empty one
+        for dir in path.ancestors().skip(1).take_while(|dir| *dir != self.root) {
+            if tokio::fs::remove_dir(dir).await.is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredBlob>> {
+        let root = self.root.clone();
+        let owned_prefix = prefix.to_string();
+        tokio::task::spawn_blocking(move || list_files(&root, &owned_prefix))
+            .await
+            .map_err(|e| crate::error::storage_error(format!("Listing {} failed: {}", prefix, e)))?
+    }
+}
+
+/// Storage backed by an S3 bucket.
+#[cfg(feature = "s3")]
+#[derive(Debug, Clone)]
+pub struct S3Storage {
+    /// S3 client.
+    client: aws_sdk_s3::Client,
+    /// Bucket blobs are stored in.
+    bucket: String,
+}
+
+#[cfg(feature = "s3")]
+impl S3Storage {
+    /// Creates a store for `bucket` using an existing client.
+    pub fn new(client: aws_sdk_s3::Client, bucket: impl Into<String>) -> Self {
+        Self {
+            client,
+            bucket: bucket.into(),
+        }
+    }
+
+    /// Creates a store for `bucket` with credentials and region from the environment.
+    pub async fn from_env(bucket: impl Into<String>) -> Self {
+        let sdk_config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        Self::new(aws_sdk_s3::Client::new(&sdk_config), bucket)
+    }
+}
+
+#[cfg(feature = "s3")]
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, key: &str, bytes: &[u8]) -> Result<()> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(bytes.to_vec().into())
+            .send()
+            .await
+            .map_err(|e| crate::error::storage_error(format!("S3 put {} failed: {}", key, e)))?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let object = self.client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| crate::error::storage_error(format!("S3 get {} failed: {}", key, e)))?;
+
+        let body = object.body
+            .collect()
+            .await
+            .map_err(|e| crate::error::storage_error(format!("S3 read {} failed: {}", key, e)))?;
+        Ok(body.into_bytes().to_vec())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| crate::error::storage_error(format!("S3 delete {} failed: {}", key, e)))?;
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredBlob>> {
+        let mut pages = self.client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(prefix)
+            .into_paginator()
+            .send();
+
+        let mut blobs = Vec::new();
+        while let Some(page) = pages.next().await {
+            let page = page
+                .map_err(|e| crate::error::storage_error(format!("S3 list {} failed: {}", prefix, e)))?;
+            for object in page.contents() {
+                let (Some(key), Some(modified)) = (object.key(), object.last_modified()) else {
+                    continue;
+                };
+                let modified = SystemTime::try_from(*modified).map_err(|e| {
+                    crate::error::storage_error(format!("S3 list {} returned a bad timestamp: {}", prefix, e))
+                })?;
+                blobs.push(StoredBlob { key: key.to_string(), modified });
+            }
+        }
+        Ok(blobs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_storage_roundtrip_and_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(dir.path());
+
+        storage.put("crops/face-1.jpg", b"blob").await.unwrap();
+        assert_eq!(storage.get("crops/face-1.jpg").await.unwrap(), b"blob");
+
+        storage.put("crops/face-1.jpg", b"replaced").await.unwrap();
+        assert_eq!(storage.get("crops/face-1.jpg").await.unwrap(), b"replaced");
+
+        storage.delete("crops/face-1.jpg").await.unwrap();
+        assert!(matches!(
+            storage.get("crops/face-1.jpg").await,
+            Err(FaceDetectionError::Io { .. })
+        ));
+        assert!(storage.delete("crops/face-1.jpg").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_local_storage_lists_by_prefix_and_prunes_empty_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(dir.path());
+
+        storage.put("retained/2024-01-01/a.jpg", b"a").await.unwrap();
+        storage.put("retained/2024-01-02/b.jpg", b"b").await.unwrap();
+        storage.put("crops/c.jpg", b"c").await.unwrap();
+
+        let keys: Vec<_> = storage.list("retained/").await.unwrap().into_iter().map(|blob| blob.key).collect();
+        assert_eq!(keys, ["retained/2024-01-01/a.jpg", "retained/2024-01-02/b.jpg"]);
+        assert!(storage.list("missing/").await.unwrap().is_empty());
+
+        storage.delete("retained/2024-01-01/a.jpg").await.unwrap();
+        assert!(!dir.path().join("retained/2024-01-01").exists());
+        assert!(dir.path().join("retained/2024-01-02").is_dir());
+    }
+
+    #[tokio::test]
+    async fn test_local_storage_rejects_escaping_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(dir.path().join("root"));
+
+        for key in ["", "../outside", "/etc/passwd", "a/../../b"] {
+            assert!(
+                matches!(storage.put(key, b"x").await, Err(FaceDetectionError::Validation { .. })),
+                "key {:?} should be rejected",
+                key
+            );
+        }
+    }
+}
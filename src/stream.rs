@@ -0,0 +1,145 @@
+//! Streaming JSON bodies for very large results.
+//!
+//! A crowd photo run through a real detector can yield thousands of faces.
+//! Instead of rendering the whole response into one string, [`stream_array_at`]
+//! renders everything around one array up front and serializes the array's
+//! items a chunk at a time as the body is read, so the serialized size held
+//! in memory stays bounded by the chunk size.
+
+use crate::error::{FaceDetectionError, Result};
+use actix_web::web::Bytes;
+use futures_util::stream::{self, Stream};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Number of array items serialized into each body chunk.
+pub const STREAM_CHUNK_ITEMS: usize = 256;
+
+/// Face count above which detection responses are streamed.
+pub const STREAM_FACE_THRESHOLD: usize = 1000;
+
+/// Streams `envelope` as JSON with `items` as the array at `path`.
+///
+/// Whatever `envelope` holds at `path` is replaced. Every item is passed
+/// through `map_item` after serialization, e.g. to rewrite its keys.
+///
+/// # Errors
+///
+/// Returns `InternalError` if `path` does not lead through JSON objects.
+/// Items that fail to serialize end the stream with an error.
+pub fn stream_array_at<T, F>(
+    envelope: Value,
+    path: &[&str],
+    items: Vec<T>,
+    map_item: F,
+) -> Result<impl Stream<Item = Result<Bytes>>>
+where
+    T: Serialize + 'static,
+    F: Fn(Value) -> Value + 'static,
+{
+    let (head, tail) = split_at_path(envelope, path).ok_or_else(|| {
+        tracing::error!("Cannot stream array at {:?}: not an object path", path);
+        FaceDetectionError::InternalError
+    })?;
+
+    let mut items = items.into_iter();
+    let mut first = true;
+    let body = std::iter::from_fn(move || {
+        let mut chunk = Vec::new();
+        for item in items.by_ref().take(STREAM_CHUNK_ITEMS) {
+            if !std::mem::take(&mut first) {
+                chunk.push(b',');
+            }
+            let written = serde_json::to_value(&item)
+                .and_then(|value| serde_json::to_writer(&mut chunk, &map_item(value)));
+            if let Err(e) = written {
+                tracing::error!("Failed to serialize streamed item: {}", e);
+                return Some(Err(FaceDetectionError::InternalError));
+            }
+        }
+        (!chunk.is_empty()).then(|| Ok(Bytes::from(chunk)))
+    });
+
+    let chunks = std::iter::once(Ok(Bytes::from(head)))
+        .chain(body)
+        .chain(std::iter::once(Ok(Bytes::from(tail))));
+    Ok(stream::iter(chunks))
+}
+
+/// Renders `value` as the JSON text before and after an array at `path`.
+///
+/// The array's key is moved to the end of its object so the text before it
+/// can be emitted without knowing the array's contents.
+fn split_at_path(mut value: Value, path: &[&str]) -> Option<(String, String)> {
+    let (key, rest) = path.split_first()?;
+    let object = value.as_object_mut()?;
+    let child = object.remove(*key)?;
+
+    // Render the object without the key, then reopen it to append the key last
+    let mut head = serde_json::to_string(object).ok()?;
+    head.pop();
+    if !object.is_empty() {
+        head.push(',');
+    }
+    head.push_str(&serde_json::to_string(key).ok()?);
+    head.push(':');
+
+    let tail = if rest.is_empty() {
+        head.push('[');
+        String::from("]}")
+    } else {
+        let (inner_head, inner_tail) = split_at_path(child, rest)?;
+        head.push_str(&inner_head);
+        inner_tail + "}"
+    };
+
+    Some((head, tail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use serde_json::json;
+
+    async fn collect(stream: impl Stream<Item = Result<Bytes>>) -> (usize, Value) {
+        let chunks: Vec<_> = stream.collect().await;
+        let body: Vec<u8> = chunks
+            .iter()
+            .flat_map(|chunk| chunk.as_ref().unwrap().to_vec())
+            .collect();
+        (chunks.len(), serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_streams_items_in_chunks_inside_envelope() {
+        let envelope = json!({ "success": true, "data": { "result": { "total": 600, "items": [] }, "id": "x" } });
+        let items: Vec<u32> = (0..600).collect();
+
+        let stream = stream_array_at(envelope, &["data", "result", "items"], items, |v| v).unwrap();
+        let (chunk_count, body) = collect(stream).await;
+
+        // Head, three chunks of at most 256 items, tail
+        assert_eq!(chunk_count, 5);
+        assert_eq!(body["success"], true);
+        assert_eq!(body["data"]["id"], "x");
+        assert_eq!(body["data"]["result"]["total"], 600);
+        let streamed = body["data"]["result"]["items"].as_array().unwrap();
+        assert_eq!(streamed.len(), 600);
+        assert_eq!(streamed[599], 599);
+    }
+
+    #[tokio::test]
+    async fn test_handles_empty_objects_and_arrays() {
+        let stream = stream_array_at(json!({ "items": null }), &["items"], Vec::<u32>::new(), |v| v).unwrap();
+        let (_, body) = collect(stream).await;
+
+        assert_eq!(body, json!({ "items": [] }));
+    }
+
+    #[test]
+    fn test_rejects_path_outside_objects() {
+        assert!(stream_array_at(json!({ "data": [] }), &["data", "items"], vec![1], |v| v).is_err());
+        assert!(stream_array_at(json!({ "data": {} }), &["missing"], vec![1], |v| v).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+//! TLS setup for serving HTTPS without a reverse proxy.
+//!
+//! When `TLS_CERT_PATH` and `TLS_KEY_PATH` are set, the server binds with
+//! rustls instead of plain HTTP; clients that offer it via ALPN get HTTP/2.
+//! Certificates are loaded once at startup so a bad path or key fails fast.
+
+use crate::error::{config_error, Result};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::ServerConfig;
+use std::sync::Arc;
+
+/// Loads a PEM certificate chain and private key into a rustls server config.
+///
+/// The ring crypto provider is used explicitly, so the result does not
+/// depend on which providers other dependencies enable.
+///
+/// # Errors
+///
+/// Returns a `Configuration` error if either file cannot be read or holds no
+/// usable certificate or key, or if the key does not fit the certificate.
+pub fn load_server_config(cert_path: &str, key_path: &str) -> Result<ServerConfig> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(Iterator::collect::<std::result::Result<Vec<_>, _>>)
+        .map_err(|e| config_error(format!("Failed to read TLS certificate {}: {}", cert_path, e)))?;
+    if certs.is_empty() {
+        return Err(config_error(format!("TLS certificate file {} contains no certificates", cert_path)));
+    }
+    
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| config_error(format!("Failed to read TLS private key {}: {}", key_path, e)))?;
+    
+    ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| config_error(format!("Unsupported TLS protocol versions: {}", e)))?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| config_error(format!("Invalid TLS certificate or private key: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::FaceDetectionError;
+
+    /// Self-signed `localhost` certificate and its key, valid until 2125.
+    const CERT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls/cert.pem");
+    const KEY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls/key.pem");
+
+    #[test]
+    fn test_loads_self_signed_certificate() {
+        let config = load_server_config(CERT, KEY).unwrap();
+        assert!(config.alpn_protocols.is_empty());
+    }
+
+    #[test]
+    fn test_bad_cert_or_key_is_configuration_error() {
+        let missing = load_server_config("/nonexistent/cert.pem", KEY).unwrap_err();
+        assert!(matches!(missing, FaceDetectionError::Configuration { .. }));
+        assert!(missing.to_string().contains("/nonexistent/cert.pem"));
+        
+        // The key file has no certificate blocks, the certificate no key
+        let swapped = load_server_config(KEY, CERT).unwrap_err();
+        assert!(matches!(swapped, FaceDetectionError::Configuration { .. }));
+        assert!(swapped.to_string().contains("no certificates"));
+        assert!(load_server_config(CERT, CERT).is_err());
+    }
+}
@@ -3,26 +3,172 @@
 //! This module contains all the data structures used throughout the application,
 //! organized by domain and purpose.
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use chrono::{DateTime, Utc};
+use image::imageops::FilterType;
+use image::Rgb;
+
+/// Default number of decimal places confidences are rounded to.
+pub const DEFAULT_CONFIDENCE_PRECISION: u32 = 3;
+
+/// Rounds `value` to `decimals` decimal places.
+pub fn round_to_decimals(value: f32, decimals: u32) -> f32 {
+    let factor = 10f64.powi(decimals.min(15) as i32);
+    ((f64::from(value) * factor).round() / factor) as f32
+}
+
+/// Serializes an `f32` as the shortest decimal reading back as the same
+/// value, so `0.95` is not widened to `0.949999988079071`.
+fn serialize_short_f32<S: Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(value.to_string().parse().unwrap_or_else(|_| f64::from(*value)))
+}
+
+/// How bounding box coordinates are written to JSON.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoordinateFormat {
+    /// Whole pixels, rounded to the nearest integer (`12`).
+    #[default]
+    Integer,
+    /// Floats keeping sub-pixel precision (`12.5`).
+    Float,
+}
+
+/// Serializes a coordinate: whole values as integers, keeping the sign of
+/// boxes reaching past the top or left image border, others as floats.
+fn serialize_coordinate<S: Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+    if value.fract() == 0.0 && value.abs() < i32::MAX as f32 {
+        serializer.serialize_i64(*value as i64)
+    } else {
+        serialize_short_f32(value, serializer)
+    }
+}
+
+/// Largest coordinate magnitude a [`BoundingBox`] accepts, 2^24: every whole
+/// pixel up to it is exactly representable as an `f32`.
+pub const MAX_COORDINATE: f32 = 16_777_216.0;
+
+/// Deserializes a coordinate, rejecting values that are not finite or whose
+/// magnitude exceeds [`MAX_COORDINATE`].
+fn deserialize_coordinate<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let value = f64::deserialize(deserializer)?;
+    if !value.is_finite() {
+        return Err(serde::de::Error::custom(format!("coordinate {value} is not a finite number")));
+    }
+    if value.abs() > f64::from(MAX_COORDINATE) {
+        return Err(serde::de::Error::custom(format!("coordinate {value} exceeds {MAX_COORDINATE}")));
+    }
+    Ok(value as f32)
+}
+
+/// An axis-aligned rectangle in image pixel coordinates.
+/// 
+/// Coordinates may be fractional; whole values are serialized as integers,
+/// and coordinates are rounded to whole pixels by
+/// [`BoundingBox::pixel_bounds`] when cropping or drawing. NaN and infinite
+/// coordinates, and coordinates beyond [`MAX_COORDINATE`] where an `f32`
+/// can no longer hold every whole pixel, are rejected when deserializing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoundingBox {
+    /// X coordinate of the top-left corner.
+    #[serde(serialize_with = "serialize_coordinate", deserialize_with = "deserialize_coordinate")]
+    pub x: f32,
+    /// Y coordinate of the top-left corner.
+    #[serde(serialize_with = "serialize_coordinate", deserialize_with = "deserialize_coordinate")]
+    pub y: f32,
+    /// Width of the box.
+    #[serde(serialize_with = "serialize_coordinate", deserialize_with = "deserialize_coordinate")]
+    pub width: f32,
+    /// Height of the box.
+    #[serde(serialize_with = "serialize_coordinate", deserialize_with = "deserialize_coordinate")]
+    pub height: f32,
+}
 
 /// Represents a detected face with its bounding box and confidence score.
+/// 
+/// The box is flattened on the wire, so a face serializes as
+/// `{x, y, width, height, confidence}`, plus `truncated: true` for faces
+/// cut off by the image border and `landmarks` for faces that have them.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Face {
-    /// X coordinate of the top-left corner of the bounding box.
-    pub x: u32,
-    /// Y coordinate of the top-left corner of the bounding box.
-    pub y: u32,
-    /// Width of the bounding box.
-    pub width: u32,
-    /// Height of the bounding box.
-    pub height: u32,
+    /// Bounding box of the face.
+    #[serde(flatten)]
+    pub bbox: BoundingBox,
     /// Confidence score of the detection (0.0 to 1.0).
+    /// 
+    /// Detectors round it to their configured precision when finishing a
+    /// result (see [`DetectionResult::round_for_output`]).
+    #[serde(serialize_with = "serialize_short_f32")]
     pub confidence: f32,
+    /// Whether the box touches or is clipped by the image border, so the
+    /// face is likely cut off.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Facial landmark points (eyes, nose, mouth corners, ...) in image
+    /// coordinates, when the backend or client provides them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub landmarks: Vec<Landmark>,
+}
+
+/// A facial landmark point in image coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Landmark {
+    /// X coordinate in pixels.
+    pub x: f32,
+    /// Y coordinate in pixels.
+    pub y: f32,
+}
+
+/// Why a candidate face was dropped by the detector's filters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectionReason {
+    /// Confidence below the detector's threshold.
+    BelowThreshold,
+    /// Box area below the minimum face area.
+    TooSmall,
+    /// Box area above the maximum face area.
+    TooLarge,
+    /// Width/height ratio outside the accepted range.
+    AspectRatio,
+    /// Collapsed into a more confident face with the same or an
+    /// overlapping box.
+    NmsSuppressed,
+    /// Dropped because more confident faces already filled the face limit.
+    MaxFaces,
+}
+
+/// A candidate face that did not make it into the detection result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedFace {
+    /// Bounding box of the candidate, flattened like [`Face`]'s.
+    #[serde(flatten)]
+    pub bbox: BoundingBox,
+    /// Confidence reported by the backend, unrounded.
+    pub confidence: f32,
+    /// Filter that dropped the candidate.
+    pub reason: RejectionReason,
+}
+
+impl RejectedFace {
+    /// Records `face` as rejected for `reason`.
+    pub fn new(face: &Face, reason: RejectionReason) -> Self {
+        Self {
+            bbox: face.bbox,
+            confidence: face.confidence,
+            reason,
+        }
+    }
 }
 
 /// Result of face detection operation.
+/// 
+/// Optional fields, here and on [`Face`], are left out of the JSON when
+/// unset so results stay compact; new optional fields should follow suit
+/// with `skip_serializing_if`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DetectionResult {
@@ -32,6 +178,57 @@ pub struct DetectionResult {
     pub total_faces: usize,
     /// Processing time in milliseconds.
     pub processing_time_ms: u64,
+    /// Width of the source image in pixels.
+    pub image_width: u32,
+    /// Height of the source image in pixels.
+    pub image_height: u32,
+    /// Per-phase timings, populated when `?timings=true` is requested.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub timings: Option<PhaseTimings>,
+    /// Clockwise rotation in degrees under which the faces were found, when
+    /// auto-orientation had to rotate the image.
+    /// 
+    /// Face coordinates are always relative to the unrotated image.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub applied_rotation: Option<u16>,
+    /// Groups of face indices lying close together, populated when
+    /// `?cluster=true` is requested.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub clusters: Option<Vec<Vec<usize>>>,
+    /// Candidates dropped by the detector's filters, populated when
+    /// `?debug=true` is requested.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub rejected: Option<Vec<RejectedFace>>,
+    /// Version of the detector model that produced the faces, e.g.
+    /// `mock-0.1.0` for the built-in mock detection.
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    pub model_version: String,
+    /// Index of the first returned face, populated when the faces were
+    /// paginated with `?offset=` or `?limit=`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub offset: Option<usize>,
+    /// Requested page size, when the faces were paginated with `?limit=`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub limit: Option<usize>,
+    /// Number of faces in this page, populated when the faces were paginated.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub returned: Option<usize>,
+}
+
+/// Time spent in each phase of the upload pipeline, in milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhaseTimings {
+    /// Decoding the uploaded image.
+    pub decode_ms: f64,
+    /// Running face detection.
+    pub detect_ms: f64,
+    /// Drawing boxes and padding the processed image.
+    pub draw_ms: f64,
+    /// Encoding the returned images.
+    pub encode_ms: f64,
+    /// Wall time from the start of decoding to the end of encoding.
+    pub total_ms: f64,
 }
 
 /// API response wrapper for consistent response format.
@@ -73,6 +270,41 @@ pub struct HealthResponse {
     pub version: String,
 }
 
+/// Readiness check response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessResponse {
+    /// Whether the service is ready to take traffic.
+    pub ready: bool,
+    /// Outcome of the startup pipeline self-test, if one ran.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub self_test: Option<crate::selftest::SelfTestReport>,
+}
+
+/// Live detector parameters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetectorParams {
+    /// Minimum image dimension considered for detection.
+    pub min_dimension: u32,
+    /// Confidence threshold for detections.
+    pub confidence_threshold: f32,
+    /// Version of the model behind the active detection backend.
+    pub model_version: String,
+}
+
+/// Debug snapshot of the running service.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugConfigResponse {
+    /// Application configuration with secrets redacted.
+    pub config: serde_json::Value,
+    /// Live detector parameters.
+    pub detector: DetectorParams,
+    /// Runtime counters.
+    pub stats: crate::stats::StatsSnapshot,
+}
+
 /// Request for face cropping operation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -81,14 +313,816 @@ pub struct CropRequest {
     pub image_data: String,
     /// List of faces to crop.
     pub faces: Vec<Face>,
+    /// Skip faces that overlap an earlier, already cropped face so near
+    /// identical crops are not returned twice.
+    #[serde(default)]
+    pub dedupe_overlapping: bool,
+    /// Resize each crop to an `output_size`x`output_size` square,
+    /// letterboxed; same as `?size=`, which it takes precedence over.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_size: Option<u32>,
+}
+
+/// Options controlling how face boxes are drawn on an image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DrawOptions {
+    /// Box and label color, serialized as `#rrggbb`.
+    #[serde(with = "rgb_hex")]
+    pub color: Rgb<u8>,
+    /// Box outline thickness in pixels, drawn inward from the face edge.
+    pub thickness: u32,
+    /// Whether to draw the `Face N: xx.x%` label above each box.
+    pub show_labels: bool,
+    /// Translucent fill drawn inside each box as a color and an alpha in
+    /// `0.0..=1.0`, serialized as `{"color": "#rrggbb", "alpha": 0.5}`.
+    #[serde(with = "fill_serde", skip_serializing_if = "Option::is_none")]
+    pub fill: Option<(Rgb<u8>, f32)>,
+    /// Color of a filled rectangle drawn behind each label so it stays
+    /// readable on any image, serialized as `#rrggbb`.
+    #[serde(with = "opt_rgb_hex", skip_serializing_if = "Option::is_none")]
+    pub label_background: Option<Rgb<u8>>,
+    /// How the confidence is written in labels.
+    pub confidence_format: ConfidenceFormat,
+    /// Attribution text stamped in a corner after the boxes are drawn,
+    /// sized relative to the image.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watermark: Option<String>,
+    /// Corner the watermark is stamped in.
+    pub watermark_position: WatermarkPosition,
+    /// Opacity of the watermark in `0.0..=1.0`.
+    pub watermark_opacity: f32,
+    /// Whether to draw a dot at each landmark of faces that have them.
+    pub show_landmarks: bool,
+}
+
+/// How a confidence is written in face labels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfidenceFormat {
+    /// Percentage with one decimal, `95.0%` (default).
+    #[default]
+    Percent,
+    /// Raw ratio with two decimals, `0.95`.
+    Ratio,
+    /// Percentage with one decimal and no sign, `95.0`.
+    PercentNoSign,
+}
+
+impl ConfidenceFormat {
+    /// Formats a confidence in `0.0..=1.0` for display.
+    pub fn format(self, confidence: f32) -> String {
+        match self {
+            Self::Percent => format!("{:.1}%", confidence * 100.0),
+            Self::Ratio => format!("{:.2}", confidence),
+            Self::PercentNoSign => format!("{:.1}", confidence * 100.0),
+        }
+    }
+}
+
+/// Image corner a watermark is stamped in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WatermarkPosition {
+    /// Top-left corner.
+    TopLeft,
+    /// Top-right corner.
+    TopRight,
+    /// Bottom-left corner.
+    BottomLeft,
+    /// Bottom-right corner (default).
+    #[default]
+    BottomRight,
+}
+
+impl Default for DrawOptions {
+    fn default() -> Self {
+        Self {
+            color: Rgb([0, 255, 0]),
+            thickness: 1,
+            show_labels: true,
+            fill: None,
+            label_background: None,
+            confidence_format: ConfidenceFormat::default(),
+            watermark: None,
+            watermark_position: WatermarkPosition::default(),
+            watermark_opacity: 0.5,
+            show_landmarks: false,
+        }
+    }
+}
+
+/// Thickest box outline [`DrawOptions`] accept, in pixels.
+pub const MAX_DRAW_THICKNESS: u32 = 64;
+
+/// Longest watermark [`DrawOptions`] accept, in characters.
+pub const MAX_WATERMARK_LEN: usize = 200;
+
+impl DrawOptions {
+    /// Checks caller-supplied options before anything is drawn with them.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns a validation error if `thickness` exceeds [`MAX_DRAW_THICKNESS`]
+    /// or the watermark is longer than [`MAX_WATERMARK_LEN`] characters.
+    pub fn validate(&self) -> crate::error::Result<()> {
+        if self.thickness > MAX_DRAW_THICKNESS {
+            return Err(crate::error::validation_error(format!(
+                "Thickness {} must be at most {MAX_DRAW_THICKNESS}",
+                self.thickness
+            )));
+        }
+        if let Some(length) = self.watermark.as_ref().map(|text| text.chars().count()) {
+            if length > MAX_WATERMARK_LEN {
+                return Err(crate::error::validation_error(format!(
+                    "Watermark has {length} characters; at most {MAX_WATERMARK_LEN} are allowed"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Serde adapter storing an `Rgb<u8>` as a `#rrggbb` hex string.
+mod rgb_hex {
+    use image::Rgb;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Rgb<u8>, serializer: S) -> Result<S::Ok, S::Error> {
+        let Rgb([r, g, b]) = *color;
+        serializer.serialize_str(&format!("#{:02x}{:02x}{:02x}", r, g, b))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rgb<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        crate::detection::parse_hex_color(&hex).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Serde adapter storing an optional `Rgb<u8>` as a `#rrggbb` hex string.
+mod opt_rgb_hex {
+    use image::Rgb;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Option<Rgb<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match color {
+            Some(color) => super::rgb_hex::serialize(color, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Rgb<u8>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|hex| crate::detection::parse_hex_color(&hex).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+/// Serde adapter storing a box fill as `{"color": "#rrggbb", "alpha": 0.5}`.
+mod fill_serde {
+    use image::Rgb;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Fill {
+        #[serde(with = "super::rgb_hex")]
+        color: Rgb<u8>,
+        alpha: f32,
+    }
+
+    pub fn serialize<S: Serializer>(fill: &Option<(Rgb<u8>, f32)>, serializer: S) -> Result<S::Ok, S::Error> {
+        fill.map(|(color, alpha)| Fill { color, alpha }).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<(Rgb<u8>, f32)>, D::Error> {
+        match Option::<Fill>::deserialize(deserializer)? {
+            Some(Fill { alpha, .. }) if !(0.0..=1.0).contains(&alpha) => Err(serde::de::Error::custom(
+                format!("fill alpha {} must be between 0 and 1", alpha),
+            )),
+            fill => Ok(fill.map(|Fill { color, alpha }| (color, alpha))),
+        }
+    }
+}
+
+/// Request to draw caller-supplied faces on a caller-supplied image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotateRequest {
+    /// Base64 encoded image data.
+    pub image_data: String,
+    /// Faces to draw.
+    pub faces: Vec<Face>,
+    /// Drawing options; defaults are used when omitted.
+    #[serde(default)]
+    pub draw_options: DrawOptions,
+}
+
+/// Request to draw faces on a transparent overlay.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlayRequest {
+    /// Overlay width in pixels, normally the original image width.
+    pub width: u32,
+    /// Overlay height in pixels, normally the original image height.
+    pub height: u32,
+    /// Faces to draw.
+    pub faces: Vec<Face>,
+    /// Drawing options; defaults are used when omitted.
+    #[serde(default)]
+    pub draw_options: DrawOptions,
+}
+
+/// Response carrying an annotated image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotateResponse {
+    /// Base64 encoded image with the faces drawn.
+    pub annotated_image: String,
+}
+
+/// Key naming convention used when serializing JSON responses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyCase {
+    /// `camelCase` keys, matching the struct definitions (default).
+    #[default]
+    Camel,
+    /// `snake_case` keys for clients that have not migrated to camelCase.
+    Snake,
+}
+
+/// Media type a client sends in `Accept` to opt into [`EnvelopeVersion::V2`].
+pub const ENVELOPE_V2_MEDIA_TYPE: &str = "application/vnd.facedetect.v2+json";
+
+/// Shape of the envelope around successful JSON responses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EnvelopeVersion {
+    /// `{ "success", "data", "metadata" }`, the shape served to every
+    /// client that does not ask for another (default).
+    #[default]
+    V1,
+    /// The fields of `data` next to `success` and `metadata`, without the
+    /// extra `data` level.
+    V2,
+}
+
+impl EnvelopeVersion {
+    /// Picks the version requested by an `Accept` header value.
+    /// 
+    /// Any media range equal to [`ENVELOPE_V2_MEDIA_TYPE`], ignoring case
+    /// and parameters, selects v2; everything else gets v1.
+    pub fn from_accept(accept: &str) -> Self {
+        let wants_v2 = accept.split(',').any(|range| {
+            let media_type = range.split(';').next().unwrap_or_default().trim();
+            media_type.eq_ignore_ascii_case(ENVELOPE_V2_MEDIA_TYPE)
+        });
+        if wants_v2 {
+            Self::V2
+        } else {
+            Self::V1
+        }
+    }
+    
+    /// Content type of responses using this envelope.
+    pub fn media_type(self) -> &'static str {
+        match self {
+            Self::V1 => "application/json",
+            Self::V2 => ENVELOPE_V2_MEDIA_TYPE,
+        }
+    }
+    
+    /// Reshapes a serialized v1 [`ApiResponse`] into this version.
+    /// 
+    /// A `data` value that is not an object stays under `data` in v2.
+    pub fn reshape(self, envelope: serde_json::Value) -> serde_json::Value {
+        use serde_json::Value;
+        
+        let Value::Object(mut envelope) = envelope else {
+            return envelope;
+        };
+        if self == Self::V1 {
+            return Value::Object(envelope);
+        }
+        match envelope.remove("data") {
+            Some(Value::Object(data)) => envelope.extend(data),
+            Some(data) => {
+                envelope.insert("data".to_string(), data);
+            }
+            None => {}
+        }
+        Value::Object(envelope)
+    }
+}
+
+/// Order in which detected faces are returned.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FaceOrder {
+    /// The order the detector produced them in (default).
+    #[default]
+    Detection,
+    /// Top-to-bottom rows, left-to-right within a row.
+    Reading,
+}
+
+/// Resampling filter selectable with `?filter=`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeFilter {
+    /// Nearest neighbor; fastest, blocky when upscaling.
+    Nearest,
+    /// Linear (triangle) filter.
+    Triangle,
+    /// Catmull-Rom cubic filter.
+    Catmull,
+    /// Lanczos with window 3; sharpest (default).
+    #[default]
+    Lanczos,
+}
+
+impl From<ResizeFilter> for FilterType {
+    fn from(filter: ResizeFilter) -> Self {
+        match filter {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::Catmull => FilterType::CatmullRom,
+            ResizeFilter::Lanczos => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// How images are resized and letterboxed, shared by every operation that
+/// scales or pads an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResizeOptions {
+    /// Resampling filter.
+    pub filter: FilterType,
+    /// Background color of letterbox and square padding.
+    pub pad_color: Rgb<u8>,
+    /// Keep the aspect ratio and letterbox, instead of stretching to fit.
+    pub preserve_aspect: bool,
+}
+
+impl Default for ResizeOptions {
+    fn default() -> Self {
+        Self {
+            filter: FilterType::Lanczos3,
+            pad_color: Rgb([0, 0, 0]),
+            preserve_aspect: true,
+        }
+    }
+}
+
+/// Query parameters controlling how a JSON response is serialized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResponseFormatQuery {
+    /// Key naming convention, selected with `?case=snake`.
+    pub case: KeyCase,
+    /// Whether to include the `metadata` block; disable with `?meta=false`.
+    pub meta: bool,
+}
+
+impl Default for ResponseFormatQuery {
+    fn default() -> Self {
+        Self {
+            case: KeyCase::default(),
+            meta: true,
+        }
+    }
+}
+
+/// Query parameters for the upload and detection endpoint.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UploadQuery {
+    /// Respond with 422 instead of an empty result when no faces are found.
+    pub require_faces: bool,
+    /// Pad the annotated image to a square, keeping its aspect ratio.
+    pub square: bool,
+    /// Background color for square padding as `rrggbb` hex; black by default.
+    pub pad_color: Option<String>,
+    /// Order of the returned faces.
+    pub order: FaceOrder,
+    /// Embed the detection result as JSON inside the processed image.
+    pub embed_metadata: bool,
+    /// Page or frame of a multi-frame image to run detection on.
+    pub frame: usize,
+    /// JPEG quality (1 to 100) of the returned images; 85 when omitted.
+    pub quality: Option<u32>,
+    /// Encode the returned images as progressive JPEGs.
+    pub progressive: bool,
+    /// Chroma subsampling of the returned images.
+    pub subsampling: ChromaSubsampling,
+    /// Report per-phase timings in the detection result.
+    pub timings: bool,
+    /// Report groups of faces lying close together in the detection result.
+    pub cluster: bool,
+    /// Report candidates dropped by the detector's filters; requires
+    /// `ENABLE_DEBUG_ENDPOINTS`.
+    pub debug: bool,
+    /// Response body format.
+    pub format: DetectionFormat,
+    /// Return the uploaded bytes as `original_image` instead of re-encoding
+    /// them as JPEG; multi-frame uploads are returned whole.
+    pub preserve_original: bool,
+    /// Index of the first face to return, counted in descending confidence.
+    pub offset: usize,
+    /// Largest number of faces to return.
+    pub limit: Option<usize>,
+}
+
+/// Detection response format selectable with `?format=`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DetectionFormat {
+    /// The detection response in the JSON envelope (default).
+    #[default]
+    Json,
+    /// A Pascal VOC XML annotation of the faces, for annotation tooling.
+    Voc,
+}
+
+/// JPEG chroma subsampling, selectable with `?subsampling=444|420`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChromaSubsampling {
+    /// Full-resolution chroma; keeps sharp color edges.
+    #[serde(rename = "444", alias = "4:4:4")]
+    Yuv444,
+    /// Chroma at half resolution in both directions; smaller files (default).
+    #[default]
+    #[serde(rename = "420", alias = "4:2:0")]
+    Yuv420,
+}
+
+/// Encoding used for images returned by the service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Baseline or progressive JPEG.
+    Jpeg {
+        /// Quality from 1 to 100.
+        quality: u8,
+        /// Whether the image is encoded in several progressively refined scans.
+        progressive: bool,
+        /// Chroma subsampling.
+        subsampling: ChromaSubsampling,
+    },
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        Self::Jpeg {
+            quality: crate::detection::DEFAULT_JPEG_QUALITY,
+            progressive: false,
+            subsampling: ChromaSubsampling::default(),
+        }
+    }
+}
+
+/// Padding applied to the top and left of an image, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PadOffset {
+    /// Rows added above the image.
+    pub top: u32,
+    /// Columns added left of the image.
+    pub left: u32,
+}
+
+/// Query parameters for the face cropping endpoint.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CropQuery {
+    /// Convert images with an embedded ICC profile to sRGB before cropping.
+    pub srgb: bool,
+    /// Resize each crop to fit a `size`x`size` square, letterboxed.
+    pub size: Option<u32>,
+    /// Resampling filter used when resizing crops.
+    pub filter: ResizeFilter,
+    /// Letterbox color for resized crops as `rrggbb` hex; black by default.
+    pub pad_color: Option<String>,
+    /// Color transparent pixels are composited over as `rrggbb` hex; white
+    /// by default.
+    pub background: Option<String>,
+    /// Whether crops come back as base64 JSON or as a zip archive.
+    pub format: CropFormat,
+    /// Also store each crop as a JPEG in the configured storage backend.
+    pub persist: bool,
+}
+
+/// Crop response format selectable with `?format=`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CropFormat {
+    /// Base64 encoded crops in the JSON envelope (default).
+    #[default]
+    Json,
+    /// A zip archive of JPEG crops plus a `manifest.json`.
+    Zip,
+}
+
+/// Entry of the `manifest.json` in a zipped crop response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CropManifestEntry {
+    /// Index of the face in the crop request.
+    pub index: usize,
+    /// The requested face box this crop was taken from.
+    pub face: Face,
+    /// Name of the crop's archive entry, if cropping succeeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Reason the face could not be cropped, if cropping failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Index of the overlapping face cropped in its place, if this face was
+    /// skipped as a duplicate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<usize>,
+}
+
+/// Outcome of cropping a single requested face.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CroppedFace {
+    /// Index of the face in the crop request.
+    pub index: usize,
+    /// The requested face box this crop was taken from.
+    pub face: Face,
+    /// Base64 encoded cropped image, if cropping succeeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Reason the face could not be cropped, if cropping failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Index of the overlapping face cropped in its place, if this face was
+    /// skipped as a duplicate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<usize>,
+    /// Storage key the crop was persisted under, with `?persist=true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_key: Option<String>,
+}
+
+/// Request to detect faces in several images at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchRequest {
+    /// Base64 encoded images.
+    pub images: Vec<String>,
+}
+
+/// How a single batch item ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BatchItemStatus {
+    /// Detection completed.
+    Ok,
+    /// The image could not be decoded or detection failed.
+    Failed,
+    /// Detection did not finish within the detection timeout.
+    TimedOut,
+}
+
+/// Outcome of detecting faces in a single batch image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchItem {
+    /// Index of the image in the batch request.
+    pub index: usize,
+    /// How detection for this image ended.
+    pub status: BatchItemStatus,
+    /// Detection result, if detection completed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<DetectionResult>,
+    /// Reason detection did not complete.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Response for batch detection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchResponse {
+    /// One entry per requested image, in request order.
+    pub items: Vec<BatchItem>,
+    /// Whether any item failed or timed out.
+    pub partial: bool,
+}
+
+/// Request to detect faces in every image of a server-local directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanRequest {
+    /// Directory to scan, relative to the configured scan root.
+    pub dir: String,
+}
+
+/// One line of a directory scan's JSON Lines output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanLine {
+    /// File name of the image within the scanned directory.
+    pub file: String,
+    /// Detection result, if detection completed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<DetectionResult>,
+    /// Reason detection did not complete.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 /// Response for face cropping operation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CropResponse {
-    /// List of base64 encoded cropped face images.
-    pub cropped_faces: Vec<String>,
+    /// One entry per requested face, in request order.
+    pub cropped_faces: Vec<CroppedFace>,
+    /// Indices of faces skipped as duplicates of an overlapping face.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<usize>,
+}
+
+/// Request to detect faces and crop a selection of them in one call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetectAndCropRequest {
+    /// Base64 encoded image data.
+    pub image_data: String,
+    /// Indices into the detected faces to crop; all faces when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indices: Option<Vec<usize>>,
+    /// Space added around each face on every side, as a fraction of its
+    /// longer side; the endpoint's default is used when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub margin: Option<f32>,
+}
+
+/// Response carrying a detection result and crops of the selected faces.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetectAndCropResponse {
+    /// All faces detected in the image.
+    pub detection_result: DetectionResult,
+    /// One entry per selected face, in request order; `index` refers to
+    /// the face in `detection_result`.
+    pub crops: Vec<CroppedFace>,
+}
+
+/// Request to crop the dominant face out of an image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrimaryFaceRequest {
+    /// Base64 encoded image data.
+    pub image_data: String,
+    /// Space added around the face on every side, as a fraction of its
+    /// longer side; the endpoint's default is used when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub margin: Option<f32>,
+    /// Whether the crop is made square around the face.
+    #[serde(default)]
+    pub square: bool,
+}
+
+/// Response carrying the crop of the dominant face.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrimaryFaceResponse {
+    /// The detected face the crop was taken from.
+    pub face: Face,
+    /// Region of the image that was cropped, including the margin.
+    pub crop: BoundingBox,
+    /// Base64 encoded cropped image.
+    pub image: String,
+}
+
+/// Request to crop an image to the region containing all its faces.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartCropRequest {
+    /// Base64 encoded image data.
+    pub image_data: String,
+    /// Pixels added around the faces on every side.
+    #[serde(default)]
+    pub padding: u32,
+}
+
+/// Response carrying the image cropped to its faces.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartCropResponse {
+    /// The detected faces, relative to the uncropped image.
+    pub faces: Vec<Face>,
+    /// Region of the image that was cropped, including the padding.
+    pub crop: BoundingBox,
+    /// Base64 encoded cropped image.
+    pub image: String,
+}
+
+/// Request for face embedding extraction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbedRequest {
+    /// Base64 encoded image data.
+    pub image_data: String,
+    /// Faces to compute embeddings for.
+    pub faces: Vec<Face>,
+}
+
+/// Response for the face presence check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HasFaceResponse {
+    /// Whether at least one face passed the confidence threshold.
+    pub has_face: bool,
+    /// Confidence of the best face found, or 0 when there is none.
+    pub max_confidence: f32,
+}
+
+/// Request to validate a base64 encoded image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateRequest {
+    /// Base64 encoded image data.
+    pub image_data: String,
+}
+
+/// Result of validating an image without running detection.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateResponse {
+    /// Whether the image would be accepted for detection.
+    pub valid: bool,
+    /// Detected container format, if recognized and supported.
+    pub format: Option<String>,
+    /// Image width in pixels, if the header could be read.
+    pub width: Option<u32>,
+    /// Image height in pixels, if the header could be read.
+    pub height: Option<u32>,
+    /// Size of the encoded image in bytes.
+    pub size_bytes: usize,
+    /// Why the image would be rejected; empty when valid.
+    pub reasons: Vec<String>,
+}
+
+/// Response for face embedding extraction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbedResponse {
+    /// One feature vector per requested face, in request order.
+    pub embeddings: Vec<Vec<f32>>,
+}
+
+/// Request to group faces of the same person.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupRequest {
+    /// Images whose faces are grouped; a single image with several faces works too.
+    pub images: Vec<GroupImage>,
+    /// Similarity in `0.0..=1.0` two faces need to share a group.
+    #[serde(default)]
+    pub threshold: Option<f32>,
+}
+
+/// An image in a grouping request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupImage {
+    /// Base64 encoded image data.
+    pub image_data: String,
+    /// Faces to group; detected when omitted.
+    #[serde(default)]
+    pub faces: Option<Vec<Face>>,
+}
+
+/// Reference to a face in a grouping request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceRef {
+    /// Index of the image in the request.
+    pub image: usize,
+    /// Index of the face among that image's faces.
+    pub face: usize,
+}
+
+/// Response for face grouping.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupResponse {
+    /// Faces of each image, in request order; detected ones for images sent without faces.
+    pub faces: Vec<Vec<Face>>,
+    /// Faces judged to be the same person; every face is in exactly one group.
+    pub groups: Vec<Vec<FaceRef>>,
+}
+
+/// Image formats supported by the service.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatsResponse {
+    /// Container formats accepted for upload, e.g. `"jpeg"`.
+    pub input_formats: Vec<String>,
+    /// Formats returned images can be encoded in.
+    pub output_formats: Vec<String>,
 }
 
 /// Complete detection response including images.
@@ -100,7 +1134,24 @@ pub struct DetectionResponse {
     /// Base64 encoded processed image with bounding boxes.
     pub processed_image: String,
     /// Detection results.
+    /// 
+    /// Face coordinates are always relative to the unpadded image.
     pub detection_result: DetectionResult,
+    /// Padding applied to the processed image when `?square=true` was requested.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub pad_offset: Option<PadOffset>,
+    /// Identifier assigned to this upload.
+    pub upload_id: String,
+    /// SHA-256 of the uploaded bytes as lowercase hex, for client-side caching.
+    pub image_hash: String,
+    /// Perceptual hash of the uploaded image as 16 hex digits.
+    pub phash: String,
+    /// Upload id of a recent upload this image is a near duplicate of.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub duplicate_of: Option<String>,
+    /// Storage key of the kept original, when `RETAIN_UPLOADS` is set.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub retained_path: Option<String>,
 }
 
 // Implementations
@@ -111,27 +1162,85 @@ impl<T> ApiResponse<T> {
             success: true,
             data: Some(data),
             error: None,
-            metadata: Some(ResponseMetadata {
-                timestamp: Utc::now(),
-                version: env!("CARGO_PKG_VERSION").to_string(),
-            }),
+            metadata: Some(ResponseMetadata::now()),
         }
     }
 
+    /// Drops the response metadata, omitting it from the serialized body.
+    pub fn without_metadata(mut self) -> Self {
+        self.metadata = None;
+        self
+    }
+
     /// Create an error API response.
     pub fn error(error_message: impl Into<String>) -> ApiResponse<()> {
         Self {
             success: false,
             data: None,
             error: Some(error_message.into()),
-            metadata: Some(ResponseMetadata {
-                timestamp: Utc::now(),
-                version: env!("CARGO_PKG_VERSION").to_string(),
-            }),
+            metadata: Some(ResponseMetadata::now()),
         }
     }
 }
 
+impl ResponseMetadata {
+    /// Metadata for a response generated now by this service version.
+    #[must_use]
+    pub fn now() -> Self {
+        Self {
+            timestamp: Utc::now(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+/// Escapes the characters XML reserves in text and attribute values.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Recursively rewrites all object keys of a JSON value from camelCase to snake_case.
+pub fn to_snake_case_keys(value: serde_json::Value) -> serde_json::Value {
+    use serde_json::Value;
+    
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (camel_to_snake(&key), to_snake_case_keys(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(to_snake_case_keys).collect()),
+        other => other,
+    }
+}
+
+/// Converts a single camelCase identifier to snake_case.
+fn camel_to_snake(key: &str) -> String {
+    let mut snake = String::with_capacity(key.len() + 4);
+    for ch in key.chars() {
+        if ch.is_ascii_uppercase() {
+            if !snake.is_empty() {
+                snake.push('_');
+            }
+            snake.push(ch.to_ascii_lowercase());
+        } else {
+            snake.push(ch);
+        }
+    }
+    snake
+}
+
 impl Default for HealthResponse {
     fn default() -> Self {
         Self {
@@ -142,29 +1251,278 @@ impl Default for HealthResponse {
     }
 }
 
+impl BoundingBox {
+    /// Creates a pixel-aligned box from its top-left corner and size.
+    /// 
+    /// Fractional boxes are built with [`BoundingBox::try_new`].
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x: x as f32,
+            y: y as f32,
+            width: width as f32,
+            height: height as f32,
+        }
+    }
+
+    /// Creates a box with fractional coordinates, e.g. from a detector.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns a validation error if any value is NaN or infinite.
+    pub fn try_new(x: f32, y: f32, width: f32, height: f32) -> crate::error::Result<Self> {
+        let bbox = Self { x, y, width, height };
+        if !bbox.is_finite() {
+            return Err(crate::error::validation_error(format!(
+                "Bounding box {:?} has a coordinate that is not a finite number",
+                bbox
+            )));
+        }
+        Ok(bbox)
+    }
+
+    /// Returns the box with every coordinate rounded to a whole pixel.
+    pub fn round(&self) -> Self {
+        Self {
+            x: self.x.round(),
+            y: self.y.round(),
+            width: self.width.round(),
+            height: self.height.round(),
+        }
+    }
+
+    /// Whether every coordinate is a finite number.
+    pub fn is_finite(&self) -> bool {
+        [self.x, self.y, self.width, self.height].iter().all(|value| value.is_finite())
+    }
+
+    /// X coordinate of the right edge.
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    /// Y coordinate of the bottom edge.
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    /// Whether any edge of the box lies within `margin` pixels of the border
+    /// of a `width`x`height` image, or beyond it.
+    pub fn is_near_border(&self, width: u32, height: u32, margin: u32) -> bool {
+        let margin = margin as f32;
+        self.x <= margin
+            || self.y <= margin
+            || self.right() + margin >= width as f32
+            || self.bottom() + margin >= height as f32
+    }
+
+    /// Area of the box in whole pixels, rounded to the nearest pixel.
+    pub fn area(&self) -> u64 {
+        (f64::from(self.width.max(0.0)) * f64::from(self.height.max(0.0))).round() as u64
+    }
+
+    /// Width divided by height; infinite for a zero-height box.
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width / self.height
+    }
+
+    /// Center point of the box.
+    pub fn center(&self) -> (f32, f32) {
+        (self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+
+    /// Calculate the intersection-over-union with another box.
+    pub fn iou(&self, other: &BoundingBox) -> f32 {
+        let left = self.x.max(other.x);
+        let top = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        
+        if right <= left || bottom <= top {
+            return 0.0;
+        }
+        
+        let area = |bbox: &BoundingBox| f64::from(bbox.width) * f64::from(bbox.height);
+        let intersection = f64::from(right - left) * f64::from(bottom - top);
+        let union = area(self) + area(other) - intersection;
+        if union <= 0.0 {
+            return 0.0;
+        }
+        
+        (intersection / union) as f32
+    }
+
+    /// Whether the pixel at (`x`, `y`) lies inside the box.
+    pub fn contains_point(&self, x: u32, y: u32) -> bool {
+        let (x, y) = (x as f32, y as f32);
+        (self.x..self.right()).contains(&x) && (self.y..self.bottom()).contains(&y)
+    }
+
+    /// Returns the part of the box inside a `width` x `height` image.
+    /// 
+    /// A box entirely outside the image becomes empty at the nearest edge.
+    pub fn clamp_to(&self, width: u32, height: u32) -> Self {
+        let (width, height) = (width as f32, height as f32);
+        let x = self.x.clamp(0.0, width);
+        let y = self.y.clamp(0.0, height);
+        
+        Self {
+            x,
+            y,
+            width: (self.right().min(width) - x).max(0.0),
+            height: (self.bottom().min(height) - y).max(0.0),
+        }
+    }
+
+    /// Returns the whole-pixel `(left, top, right, bottom)` bounds of the box
+    /// inside a `width` x `height` image, with `right` and `bottom` exclusive.
+    /// 
+    /// Edges are rounded to the nearest pixel; this is where fractional
+    /// coordinates become integers for cropping and drawing.
+    pub fn pixel_bounds(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let bounds = self.clamp_to(width, height);
+        (
+            bounds.x.round() as u32,
+            bounds.y.round() as u32,
+            bounds.right().round() as u32,
+            bounds.bottom().round() as u32,
+        )
+    }
+
+    /// Returns the box scaled by `factor`, as when mapping it onto a resized image.
+    pub fn scale(&self, factor: f32) -> Self {
+        let factor = factor.max(0.0);
+        
+        Self {
+            x: self.x * factor,
+            y: self.y * factor,
+            width: self.width * factor,
+            height: self.height * factor,
+        }
+    }
+
+    /// Returns the box moved by the given offset, clamped at zero.
+    pub fn offset(&self, dx: i32, dy: i32) -> Self {
+        Self {
+            x: (self.x + dx as f32).max(0.0),
+            y: (self.y + dy as f32).max(0.0),
+            ..*self
+        }
+    }
+
+    /// Returns the box grown by `pixels` on every side, or shrunk for a
+    /// negative value, clamped to a `width` x `height` image.
+    /// 
+    /// A box shrunk past zero collapses to a 1x1 box at its center.
+    pub fn expand(&self, pixels: i32, width: u32, height: u32) -> Self {
+        let delta = pixels as f32;
+        let side = |start: f32, len: f32, limit: u32| -> (f32, f32) {
+            let limit = limit as f32;
+            let low = (start - delta).clamp(0.0, limit);
+            let high = (start + len + delta).clamp(0.0, limit);
+            if high > low {
+                return (low, high - low);
+            }
+            let center = (start + len / 2.0).clamp(0.0, (limit - 1.0).max(0.0));
+            (center, 1.0)
+        };
+        
+        let (x, box_width) = side(self.x, self.width, width);
+        let (y, box_height) = side(self.y, self.height, height);
+        Self { x, y, width: box_width, height: box_height }
+    }
+
+    /// Returns the smallest square centered on the box that contains it,
+    /// shifted to stay inside a `width` x `height` image.
+    /// 
+    /// The side never exceeds the shorter side of the image, so a box wider
+    /// than a narrow image stays as close to square as fits.
+    pub fn squared(&self, width: u32, height: u32) -> Self {
+        let side = self.width.max(self.height).min(width.min(height) as f32);
+        let (center_x, center_y) = self.center();
+        let place = |center: f32, limit: u32| (center - side / 2.0).clamp(0.0, (limit as f32 - side).max(0.0));
+        
+        Self {
+            x: place(center_x, width),
+            y: place(center_y, height),
+            width: side,
+            height: side,
+        }
+    }
+}
+
+impl From<(u32, u32, u32, u32)> for BoundingBox {
+    /// Converts an `(x, y, width, height)` tuple.
+    fn from((x, y, width, height): (u32, u32, u32, u32)) -> Self {
+        Self::new(x, y, width, height)
+    }
+}
+
+impl From<BoundingBox> for (u32, u32, u32, u32) {
+    /// Converts to an `(x, y, width, height)` tuple, rounded to whole pixels.
+    fn from(bbox: BoundingBox) -> Self {
+        let round = |value: f32| value.max(0.0).round() as u32;
+        (round(bbox.x), round(bbox.y), round(bbox.width), round(bbox.height))
+    }
+}
+
 impl Face {
     /// Create a new face detection result.
     pub fn new(x: u32, y: u32, width: u32, height: u32, confidence: f32) -> Self {
         Self {
-            x,
-            y,
-            width,
-            height,
+            bbox: BoundingBox::new(x, y, width, height),
             confidence,
+            truncated: false,
+            landmarks: Vec::new(),
         }
     }
 
     /// Calculate the area of the face bounding box.
-    pub fn area(&self) -> u32 {
-        self.width * self.height
+    pub fn area(&self) -> u64 {
+        self.bbox.area()
     }
 
     /// Check if the face detection has high confidence.
     pub fn is_high_confidence(&self) -> bool {
         self.confidence > 0.8
     }
+
+    /// Calculate the intersection-over-union of two face bounding boxes.
+    pub fn iou(&self, other: &Face) -> f32 {
+        self.bbox.iou(&other.bbox)
+    }
+
+    /// Returns a copy of the face moved by the given offset, clamped at zero.
+    pub fn offset(&self, dx: i32, dy: i32) -> Self {
+        Self {
+            bbox: self.bbox.offset(dx, dy),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of the face with its box grown by `pixels` on every
+    /// side, or shrunk for a negative value, clamped to the image.
+    /// 
+    /// See [`BoundingBox::expand`].
+    pub fn expand(&self, pixels: i32, img_width: u32, img_height: u32) -> Self {
+        Self {
+            bbox: self.bbox.expand(pixels, img_width, img_height),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of the face with its box shrunk by `pixels` on every
+    /// side; the same as [`Face::expand`] with `-pixels`.
+    pub fn shrink(&self, pixels: i32, img_width: u32, img_height: u32) -> Self {
+        self.expand(pixels.saturating_neg(), img_width, img_height)
+    }
 }
 
+/// Cluster distance used by `?cluster=true`, in average face sizes.
+pub const DEFAULT_CLUSTER_DISTANCE: f32 = 1.5;
+
+/// IoU above which faces from neighbouring tiles are treated as the same face.
+pub const TILE_MERGE_IOU_THRESHOLD: f32 = 0.5;
+
 impl DetectionResult {
     /// Create a new detection result.
     pub fn new(faces: Vec<Face>, processing_time_ms: u64) -> Self {
@@ -172,16 +1530,742 @@ impl DetectionResult {
             total_faces: faces.len(),
             faces,
             processing_time_ms,
+            image_width: 0,
+            image_height: 0,
+            timings: None,
+            applied_rotation: None,
+            clusters: None,
+            rejected: None,
+            model_version: String::new(),
+            offset: None,
+            limit: None,
+            returned: None,
         }
     }
 
+    /// Sets the dimensions of the source image the faces were detected in.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `width` - Source image width in pixels
+    /// * `height` - Source image height in pixels
+    pub fn with_image_dimensions(mut self, width: u32, height: u32) -> Self {
+        self.image_width = width;
+        self.image_height = height;
+        self
+    }
+
+    /// Rounds face confidences to `confidence_decimals` places and, with
+    /// [`CoordinateFormat::Integer`], box coordinates to whole pixels.
+    /// 
+    /// Rejected candidates keep their unrounded confidence.
+    pub fn round_for_output(&mut self, confidence_decimals: u32, coordinates: CoordinateFormat) {
+        for face in &mut self.faces {
+            face.confidence = round_to_decimals(face.confidence, confidence_decimals);
+        }
+        if coordinates == CoordinateFormat::Integer {
+            for face in &mut self.faces {
+                face.bbox = face.bbox.round();
+            }
+            for face in self.rejected.iter_mut().flatten() {
+                face.bbox = face.bbox.round();
+            }
+        }
+    }
+
+    /// Sets the version of the model that produced the faces.
+    pub fn with_model_version(mut self, model_version: impl Into<String>) -> Self {
+        self.model_version = model_version.into();
+        self
+    }
+
     /// Check if any faces were detected.
     pub fn has_faces(&self) -> bool {
         !self.faces.is_empty()
     }
 
+    /// Keeps one page of the faces, most confident first.
+    /// 
+    /// Sorts the faces by descending confidence and keeps at most `limit`
+    /// of them starting at `offset`; without a limit every face from
+    /// `offset` on is kept. `total_faces` keeps counting all faces, and
+    /// `offset`, `limit` and `returned` describe the page.
+    pub fn paginate(&mut self, offset: usize, limit: Option<usize>) {
+        self.faces.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        let start = offset.min(self.faces.len());
+        let end = limit.map_or(self.faces.len(), |limit| start.saturating_add(limit).min(self.faces.len()));
+        self.faces.truncate(end);
+        self.faces.drain(..start);
+        
+        self.offset = Some(offset);
+        self.limit = limit;
+        self.returned = Some(self.faces.len());
+    }
+
+    /// Returns the face with the largest box, preferring the more confident
+    /// face when two are the same size.
+    pub fn largest_face(&self) -> Option<&Face> {
+        self.faces.iter().max_by(|a, b| {
+            a.area()
+                .cmp(&b.area())
+                .then(a.confidence.total_cmp(&b.confidence))
+        })
+    }
+
+    /// Renders the faces as a Pascal VOC `<annotation>` document.
+    /// 
+    /// Every face becomes an `<object>` named `face` whose `<bndbox>` holds
+    /// pixel corners, with `xmax = x + width` and `ymax = y + height`,
+    /// clamped to the image. Faces cut off by the border are marked
+    /// `<truncated>1</truncated>`.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `filename` - Image file name recorded in `<filename>`
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    pub fn to_voc_xml(&self, filename: &str, width: u32, height: u32) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<annotation>\n");
+        xml.push_str(&format!("  <filename>{}</filename>\n", escape_xml(filename)));
+        xml.push_str(&format!(
+            "  <size>\n    <width>{}</width>\n    <height>{}</height>\n    <depth>3</depth>\n  </size>\n",
+            width, height
+        ));
+        xml.push_str("  <segmented>0</segmented>\n");
+        
+        for face in &self.faces {
+            let (xmin, ymin, xmax, ymax) = face.bbox.pixel_bounds(width, height);
+            xml.push_str(&format!(
+                concat!(
+                    "  <object>\n",
+                    "    <name>face</name>\n",
+                    "    <pose>Unspecified</pose>\n",
+                    "    <truncated>{}</truncated>\n",
+                    "    <difficult>0</difficult>\n",
+                    "    <bndbox>\n",
+                    "      <xmin>{}</xmin>\n",
+                    "      <ymin>{}</ymin>\n",
+                    "      <xmax>{}</xmax>\n",
+                    "      <ymax>{}</ymax>\n",
+                    "    </bndbox>\n",
+                    "  </object>\n",
+                ),
+                u8::from(face.truncated),
+                xmin,
+                ymin,
+                xmax,
+                ymax
+            ));
+        }
+        
+        xml.push_str("</annotation>\n");
+        xml
+    }
+
     /// Get faces with high confidence only.
     pub fn high_confidence_faces(&self) -> Vec<&Face> {
         self.faces.iter().filter(|f| f.is_high_confidence()).collect()
     }
+
+    /// Merges per-tile detection results into a single result.
+    /// 
+    /// Each tile's faces are shifted by the tile's `(x, y)` origin, concatenated,
+    /// and then non-maximum suppression removes duplicates of faces that were
+    /// seen by more than one overlapping tile. Processing time is the sum of
+    /// the tiles' times, and the image dimensions cover the union of all tiles.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `results` - Tile results paired with the tile's origin in the full image
+    pub fn merge(results: &[(DetectionResult, i32, i32)]) -> DetectionResult {
+        let mut faces = Vec::new();
+        let mut processing_time_ms = 0;
+        let mut image_width = 0;
+        let mut image_height = 0;
+        
+        for (result, dx, dy) in results {
+            faces.extend(result.faces.iter().map(|face| face.offset(*dx, *dy)));
+            processing_time_ms += result.processing_time_ms;
+            image_width = image_width.max(result.image_width.saturating_add_signed(*dx));
+            image_height = image_height.max(result.image_height.saturating_add_signed(*dy));
+        }
+        
+        // Tiles come from the same detector, so any tile's version will do
+        let model_version = results.first().map(|(result, _, _)| result.model_version.clone()).unwrap_or_default();
+        let mut merged = DetectionResult::new(faces, processing_time_ms)
+            .with_image_dimensions(image_width, image_height)
+            .with_model_version(model_version);
+        merged.apply_nms(TILE_MERGE_IOU_THRESHOLD);
+        merged
+    }
+
+    /// Applies non-maximum suppression, keeping the most confident face of
+    /// every group whose IoU exceeds `iou_threshold`.
+    /// 
+    /// Returns the faces that were removed.
+    pub fn apply_nms(&mut self, iou_threshold: f32) -> Vec<Face> {
+        let mut candidates = std::mem::take(&mut self.faces);
+        candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        
+        let mut removed = Vec::new();
+        for face in candidates {
+            if self.faces.iter().all(|kept| kept.iou(&face) <= iou_threshold) {
+                self.faces.push(face);
+            } else {
+                removed.push(face);
+            }
+        }
+        
+        self.total_faces = self.faces.len();
+        removed
+    }
+
+    /// Keeps only the `max_faces` most confident faces, in their current order.
+    /// 
+    /// Returns the faces that were removed.
+    pub fn keep_most_confident(&mut self, max_faces: usize) -> Vec<Face> {
+        if self.faces.len() <= max_faces {
+            return Vec::new();
+        }
+        
+        let mut by_confidence: Vec<usize> = (0..self.faces.len()).collect();
+        by_confidence.sort_by(|&a, &b| self.faces[b].confidence.total_cmp(&self.faces[a].confidence));
+        let mut keep = vec![false; self.faces.len()];
+        for &index in &by_confidence[..max_faces] {
+            keep[index] = true;
+        }
+        
+        let (kept, removed): (Vec<_>, Vec<_>) = std::mem::take(&mut self.faces)
+            .into_iter()
+            .zip(keep)
+            .partition(|(_, keep)| *keep);
+        self.faces = kept.into_iter().map(|(face, _)| face).collect();
+        self.total_faces = self.faces.len();
+        removed.into_iter().map(|(face, _)| face).collect()
+    }
+
+    /// Removes faces with identical boxes, keeping the most confident of each.
+    /// 
+    /// Much cheaper than [`DetectionResult::apply_nms`] when only exact
+    /// duplicates need collapsing. The order of the remaining faces is kept.
+    /// 
+    /// Returns the faces that were removed.
+    pub fn dedupe_exact(&mut self) -> Vec<Face> {
+        let mut kept: Vec<Face> = Vec::with_capacity(self.faces.len());
+        let mut removed = Vec::new();
+        
+        for face in std::mem::take(&mut self.faces) {
+            let duplicate = kept.iter_mut().find(|k| k.bbox == face.bbox);
+            match duplicate {
+                Some(existing) if face.confidence > existing.confidence => {
+                    removed.push(std::mem::replace(existing, face));
+                }
+                Some(_) => removed.push(face),
+                None => kept.push(face),
+            }
+        }
+        
+        self.faces = kept;
+        self.total_faces = self.faces.len();
+        removed
+    }
+
+    /// Returns the faces in reading order: top-to-bottom, then left-to-right.
+    /// 
+    /// Faces whose top edge lies within `row_tolerance` pixels of the first
+    /// face of a row are treated as being on that row.
+    pub fn in_reading_order(&self, row_tolerance: u32) -> Vec<&Face> {
+        let mut by_y: Vec<&Face> = self.faces.iter().collect();
+        by_y.sort_by(|a, b| a.bbox.y.total_cmp(&b.bbox.y).then(a.bbox.x.total_cmp(&b.bbox.x)));
+        
+        let mut rows: Vec<Vec<&Face>> = Vec::new();
+        for face in by_y {
+            match rows.last_mut() {
+                Some(row) if face.bbox.y <= row[0].bbox.y + row_tolerance as f32 => row.push(face),
+                _ => rows.push(vec![face]),
+            }
+        }
+        
+        rows.into_iter()
+            .flat_map(|mut row| {
+                row.sort_by(|a, b| a.bbox.x.total_cmp(&b.bbox.x));
+                row
+            })
+            .collect()
+    }
+
+    /// Groups face indices whose centers lie close together.
+    /// 
+    /// Two faces are linked when their centers are at most
+    /// `distance_threshold` times the average face size apart, the size of a
+    /// face being the mean of its width and height; groups are the connected
+    /// components of that relation (single linkage). Groups are ordered by
+    /// their lowest index and list indices in ascending order.
+    pub fn cluster(&self, distance_threshold: f32) -> Vec<Vec<usize>> {
+        let count = self.faces.len();
+        if count == 0 {
+            return Vec::new();
+        }
+        
+        let average_size = self.faces.iter()
+            .map(|face| (face.bbox.width + face.bbox.height) / 2.0)
+            .sum::<f32>() / count as f32;
+        let max_distance = distance_threshold * average_size;
+        let centers: Vec<(f32, f32)> = self.faces.iter().map(|face| face.bbox.center()).collect();
+        
+        connected_components(count, |i, j| {
+            let (dx, dy) = (centers[i].0 - centers[j].0, centers[i].1 - centers[j].1);
+            dx.hypot(dy) <= max_distance
+        })
+    }
+}
+
+/// Groups the indices `0..count` into the connected components of `linked`,
+/// which is called once for every pair `i < j`.
+/// 
+/// Groups are ordered by their lowest index and list indices in ascending
+/// order.
+pub fn connected_components(count: usize, mut linked: impl FnMut(usize, usize) -> bool) -> Vec<Vec<usize>> {
+    // Union-find over the indices, with the lowest index as root
+    let mut parent: Vec<usize> = (0..count).collect();
+    for i in 0..count {
+        for j in (i + 1)..count {
+            if linked(i, j) {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+    }
+    
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of_root = vec![usize::MAX; count];
+    for i in 0..count {
+        let r = root(&mut parent, i);
+        if group_of_root[r] == usize::MAX {
+            group_of_root[r] = groups.len();
+            groups.push(Vec::new());
+        }
+        groups[group_of_root[r]].push(i);
+    }
+    groups
+}
+
+/// Finds the root of `i` in a union-find forest, halving paths on the way.
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paginate_returns_requested_slice() {
+        let faces: Vec<Face> = (0..2500u32).map(|i| Face::new(i, 0, 8, 8, i as f32 / 2500.0)).collect();
+        let mut result = DetectionResult::new(faces, 0);
+        
+        result.paginate(100, Some(100));
+        
+        assert_eq!(result.total_faces, 2500);
+        assert_eq!((result.offset, result.limit, result.returned), (Some(100), Some(100), Some(100)));
+        assert_eq!(result.faces.len(), 100);
+        assert_eq!(result.faces[0].confidence, 2399.0 / 2500.0);
+        assert_eq!(result.faces[99].confidence, 2300.0 / 2500.0);
+        assert!(result.faces.windows(2).all(|pair| pair[0].confidence > pair[1].confidence));
+        
+        let mut result = DetectionResult::new(vec![Face::new(0, 0, 8, 8, 0.9)], 0);
+        result.paginate(5, None);
+        assert_eq!((result.total_faces, result.returned), (1, Some(0)));
+    }
+
+    #[test]
+    fn test_envelope_version_from_accept() {
+        assert_eq!(EnvelopeVersion::from_accept("application/json"), EnvelopeVersion::V1);
+        assert_eq!(EnvelopeVersion::from_accept("*/*"), EnvelopeVersion::V1);
+        assert_eq!(
+            EnvelopeVersion::from_accept("application/json;q=0.5, Application/VND.facedetect.v2+json; q=1"),
+            EnvelopeVersion::V2
+        );
+        
+        let envelope = serde_json::json!({ "success": true, "data": { "totalFaces": 2 }, "metadata": {} });
+        assert_eq!(EnvelopeVersion::V1.reshape(envelope.clone()), envelope);
+        assert_eq!(
+            EnvelopeVersion::V2.reshape(envelope),
+            serde_json::json!({ "success": true, "totalFaces": 2, "metadata": {} })
+        );
+    }
+
+    #[test]
+    fn test_voc_xml_has_object_per_face() {
+        let faces = vec![
+            Face::new(10, 20, 30, 40, 0.9),
+            Face::new(100, 50, 60, 70, 0.8),
+        ];
+        let result = DetectionResult::new(faces, 5);
+        let xml = result.to_voc_xml("a&b.jpg", 200, 150);
+        
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains("<annotation>") && xml.trim_end().ends_with("</annotation>"));
+        assert!(xml.contains("<filename>a&amp;b.jpg</filename>"));
+        assert!(xml.contains("<width>200</width>") && xml.contains("<height>150</height>"));
+        assert_eq!(xml.matches("<object>").count(), 2);
+        assert_eq!(xml.matches("<name>face</name>").count(), 2);
+        
+        // xmax = x + width, ymax = y + height
+        assert!(xml.contains("<xmin>10</xmin>") && xml.contains("<xmax>40</xmax>"));
+        assert!(xml.contains("<ymin>20</ymin>") && xml.contains("<ymax>60</ymax>"));
+        assert!(xml.contains("<xmin>100</xmin>") && xml.contains("<xmax>160</xmax>"));
+        assert!(xml.contains("<ymax>120</ymax>"));
+        
+        assert_eq!(DetectionResult::new(Vec::new(), 0).to_voc_xml("x.jpg", 1, 1).matches("<object>").count(), 0);
+    }
+
+    #[test]
+    fn test_dedupe_exact_keeps_highest_confidence() {
+        let mut result = DetectionResult::new(
+            vec![
+                Face::new(10, 10, 50, 50, 0.6),
+                Face::new(100, 10, 50, 50, 0.7),
+                Face::new(10, 10, 50, 50, 0.9),
+                Face::new(10, 10, 50, 51, 0.5),
+            ],
+            0,
+        );
+        result.dedupe_exact();
+        
+        assert_eq!(result.total_faces, 3);
+        assert_eq!((result.faces[0].bbox.x, result.faces[0].confidence), (10.0, 0.9));
+        assert_eq!(result.faces[1].bbox.x, 100.0);
+        assert_eq!(result.faces[2].bbox.height, 51.0);
+    }
+
+    #[test]
+    fn test_serialization_omits_unset_optional_fields() {
+        let empty = serde_json::to_string(&DetectionResult::new(Vec::new(), 0)).unwrap();
+        assert_eq!(
+            empty,
+            r#"{"faces":[],"totalFaces":0,"processingTimeMs":0,"imageWidth":0,"imageHeight":0}"#
+        );
+        
+        let plain = DetectionResult::new(vec![Face::new(10, 10, 50, 50, 0.9)], 5).with_image_dimensions(100, 100);
+        let mut full = plain.clone();
+        full.faces[0].truncated = true;
+        full.timings = Some(PhaseTimings::default());
+        full.applied_rotation = Some(90);
+        full.clusters = Some(vec![vec![0]]);
+        
+        let plain_json = serde_json::to_string(&plain).unwrap();
+        let full_json = serde_json::to_string(&full).unwrap();
+        for key in ["truncated", "timings", "appliedRotation", "clusters"] {
+            assert!(!plain_json.contains(key), "{} serialized while unset", key);
+            assert!(full_json.contains(key), "{} missing while set", key);
+        }
+        assert!(plain_json.len() < full_json.len());
+    }
+
+    #[test]
+    fn test_expand_clamps_at_top_left_corner() {
+        let face = Face::new(5, 5, 20, 20, 0.9);
+        
+        let grown = face.expand(10, 100, 100);
+        assert_eq!(grown.bbox, BoundingBox::new(0, 0, 35, 35));
+        assert_eq!(grown.confidence, 0.9);
+        
+        let edge = Face::new(90, 80, 10, 10, 0.9).expand(10, 100, 90);
+        assert_eq!(edge.bbox, BoundingBox::new(80, 70, 20, 20));
+    }
+
+    #[test]
+    fn test_shrink_past_zero_gives_1x1_box() {
+        let face = Face::new(10, 10, 20, 20, 0.9);
+        
+        assert_eq!(face.shrink(5, 100, 100).bbox, BoundingBox::new(15, 15, 10, 10));
+        assert_eq!(face.expand(-15, 100, 100).bbox, BoundingBox::new(20, 20, 1, 1));
+        assert_eq!(face.shrink(i32::MAX, 100, 100).bbox, BoundingBox::new(20, 20, 1, 1));
+    }
+
+    #[test]
+    fn test_largest_face_breaks_ties_by_confidence() {
+        let result = DetectionResult::new(
+            vec![
+                Face::new(0, 0, 40, 40, 0.9),
+                Face::new(100, 0, 50, 50, 0.6),
+                Face::new(200, 0, 50, 50, 0.8),
+                Face::new(300, 0, 20, 100, 0.99),
+            ],
+            0,
+        );
+        
+        let largest = result.largest_face().unwrap();
+        assert_eq!((largest.bbox.x, largest.confidence), (200.0, 0.8));
+        assert!(DetectionResult::new(Vec::new(), 0).largest_face().is_none());
+    }
+
+    #[test]
+    fn test_squared_stays_centered_and_inside_image() {
+        let bbox = BoundingBox::new(40, 20, 20, 40);
+        assert_eq!(bbox.squared(100, 100), BoundingBox::new(30, 20, 40, 40));
+        
+        // Shifted back inside at the border, and capped by the image height
+        assert_eq!(BoundingBox::new(0, 10, 10, 30).squared(100, 100), BoundingBox::new(0, 10, 30, 30));
+        assert_eq!(BoundingBox::new(10, 0, 80, 20).squared(100, 50), BoundingBox::new(25, 0, 50, 50));
+    }
+
+    #[test]
+    fn test_cluster_separates_distant_pairs() {
+        let result = DetectionResult::new(
+            vec![
+                Face::new(0, 0, 50, 50, 0.9),
+                Face::new(1000, 1000, 50, 50, 0.9),
+                Face::new(60, 0, 50, 50, 0.9),
+                Face::new(1060, 1010, 50, 50, 0.9),
+            ],
+            0,
+        );
+        
+        assert_eq!(result.cluster(DEFAULT_CLUSTER_DISTANCE), vec![vec![0, 2], vec![1, 3]]);
+        assert_eq!(result.cluster(0.5).len(), 4);
+        assert!(DetectionResult::new(Vec::new(), 0).cluster(1.0).is_empty());
+    }
+
+    #[test]
+    fn test_in_reading_order_2x2_grid() {
+        let top_left = Face::new(10, 12, 50, 50, 0.9);
+        let top_right = Face::new(200, 8, 50, 50, 0.9);
+        let bottom_left = Face::new(12, 150, 50, 50, 0.9);
+        let bottom_right = Face::new(205, 145, 50, 50, 0.9);
+        let expected: Vec<(f32, f32)> = [&top_left, &top_right, &bottom_left, &bottom_right]
+            .iter()
+            .map(|f| (f.bbox.x, f.bbox.y))
+            .collect();
+        
+        let orderings = [
+            vec![bottom_right.clone(), top_left.clone(), bottom_left.clone(), top_right.clone()],
+            vec![top_right.clone(), bottom_left.clone(), top_left.clone(), bottom_right.clone()],
+            vec![bottom_left.clone(), bottom_right.clone(), top_right.clone(), top_left.clone()],
+        ];
+        for faces in orderings {
+            let result = DetectionResult::new(faces, 0);
+            let order: Vec<(f32, f32)> = result.in_reading_order(10).iter().map(|f| (f.bbox.x, f.bbox.y)).collect();
+            assert_eq!(order, expected);
+        }
+    }
+
+    #[test]
+    fn test_confidence_serialized_rounded() {
+        let faces = vec![Face::new(1, 2, 3, 4, 0.950_000_1), Face::new(1, 2, 3, 4, 0.123_456_7)];
+        let mut result = DetectionResult::new(faces, 0);
+        assert!(serde_json::to_string(&result.faces[0]).unwrap().contains("\"confidence\":0.9500001}"));
+        
+        result.round_for_output(DEFAULT_CONFIDENCE_PRECISION, CoordinateFormat::Float);
+        let json = serde_json::to_string(&result.faces[0]).unwrap();
+        assert!(json.contains("\"confidence\":0.95}"), "{}", json);
+        
+        // Not widened to 0.12300000339746475 on the way through a `Value`
+        assert_eq!(serde_json::to_value(&result.faces[1]).unwrap()["confidence"], 0.123);
+        
+        let parsed: Face = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.confidence, 0.95);
+        assert_eq!(parsed.bbox.width, 3.0);
+    }
+
+    #[test]
+    fn test_draw_options_hex_color() {
+        let options: DrawOptions = serde_json::from_str(r##"{"color": "#ff0000"}"##).unwrap();
+        assert_eq!(options.color, Rgb([255, 0, 0]));
+        assert_eq!(options.thickness, 1);
+        assert!(options.show_labels);
+        
+        assert_eq!(serde_json::to_value(&options).unwrap()["color"], "#ff0000");
+        assert!(serde_json::from_str::<DrawOptions>(r#"{"color": "red"}"#).is_err());
+        assert!(serde_json::to_value(&options).unwrap().get("fill").is_none());
+        
+        let options: DrawOptions = serde_json::from_str(
+            r##"{"fill": {"color": "#0000ff", "alpha": 0.25}}"##
+        ).unwrap();
+        assert_eq!(options.fill, Some((Rgb([0, 0, 255]), 0.25)));
+        assert_eq!(
+            serde_json::to_value(&options).unwrap()["fill"],
+            serde_json::json!({ "color": "#0000ff", "alpha": 0.25 })
+        );
+        assert!(serde_json::from_str::<DrawOptions>(
+            r##"{"fill": {"color": "#0000ff", "alpha": 1.5}}"##
+        ).is_err());
+    }
+
+    #[test]
+    fn test_to_snake_case_keys() {
+        let result = DetectionResult::new(vec![Face::new(1, 2, 3, 4, 0.5)], 7);
+        let value = to_snake_case_keys(serde_json::to_value(ApiResponse::success(result)).unwrap());
+        
+        assert_eq!(value["data"]["total_faces"], 1);
+        assert_eq!(value["data"]["processing_time_ms"], 7);
+        assert_eq!(value["data"]["faces"][0]["width"], 3);
+        assert!(value["data"].get("totalFaces").is_none());
+        assert!(value["metadata"]["timestamp"].is_string());
+    }
+
+    #[test]
+    fn test_face_wire_format_stays_flat() {
+        let face: Face = serde_json::from_str(
+            r#"{"x": 10, "y": 20, "width": 30, "height": 40, "confidence": 0.5}"#
+        ).unwrap();
+        assert_eq!(face.bbox, BoundingBox::new(10, 20, 30, 40));
+        assert_eq!(face.confidence, 0.5);
+        
+        let value = serde_json::to_value(&face).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({ "x": 10, "y": 20, "width": 30, "height": 40, "confidence": 0.5 })
+        );
+        
+        let missing_height = r#"{"x": 10, "y": 20, "width": 30, "confidence": 0.5}"#;
+        assert!(serde_json::from_str::<Face>(missing_height).is_err());
+    }
+
+    #[test]
+    fn test_coordinates_round_trip_in_both_formats() {
+        let face = Face {
+            bbox: BoundingBox { x: 10.25, y: 20.0, width: 30.5, height: 40.0 },
+            ..Face::new(0, 0, 0, 0, 0.5)
+        };
+        
+        let mut float = DetectionResult::new(vec![face.clone()], 0);
+        float.round_for_output(DEFAULT_CONFIDENCE_PRECISION, CoordinateFormat::Float);
+        let json = serde_json::to_string(&float.faces[0]).unwrap();
+        assert!(json.contains(r#""x":10.25,"y":20,"width":30.5"#), "{}", json);
+        let parsed: Face = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.bbox, face.bbox);
+        
+        let mut integer = DetectionResult::new(vec![face], 0);
+        integer.round_for_output(DEFAULT_CONFIDENCE_PRECISION, CoordinateFormat::Integer);
+        let json = serde_json::to_string(&integer.faces[0]).unwrap();
+        assert!(json.contains(r#""x":10,"y":20,"width":31,"height":40"#), "{}", json);
+        let parsed: Face = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.bbox, BoundingBox::new(10, 20, 31, 40));
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+    }
+
+    #[test]
+    fn test_non_finite_coordinates_are_rejected() {
+        assert!(BoundingBox::try_new(f32::NAN, 0.0, 10.0, 10.0).is_err());
+        assert!(BoundingBox::try_new(0.0, 0.0, f32::INFINITY, 10.0).is_err());
+        assert_eq!(BoundingBox::try_new(1.5, 2.0, 3.0, 4.0).unwrap(), BoundingBox { x: 1.5, y: 2.0, width: 3.0, height: 4.0 });
+        
+        // 1e300 is a valid JSON number but overflows to infinity as an f32
+        let overflowing = r#"{"x": 1e300, "y": 0, "width": 10, "height": 10, "confidence": 0.5}"#;
+        assert!(serde_json::from_str::<Face>(overflowing).is_err());
+    }
+
+    #[test]
+    fn test_coordinates_beyond_f32_precision_are_rejected() {
+        let face = |x: i64| serde_json::json!({ "x": x, "y": 0, "width": 10, "height": 10, "confidence": 0.5 });
+        
+        // 2^24 + 1 would silently round to 2^24 as an f32
+        assert!(serde_json::from_value::<Face>(face((1 << 24) + 1)).is_err());
+        assert!(serde_json::from_value::<Face>(face(-(1 << 24) - 1)).is_err());
+        assert!(serde_json::from_value::<Face>(face(i64::from(u32::MAX))).is_err());
+        
+        let parsed: Face = serde_json::from_value(face(1 << 24)).unwrap();
+        assert_eq!(parsed.bbox, BoundingBox::new(1 << 24, 0, 10, 10));
+        assert_eq!(serde_json::to_value(&parsed).unwrap()["x"], 1 << 24);
+    }
+
+    #[test]
+    fn test_integer_coordinates_round_negative_values() {
+        let face = Face {
+            bbox: BoundingBox { x: -2.6, y: -0.4, width: 30.5, height: 40.0 },
+            ..Face::new(0, 0, 0, 0, 0.5)
+        };
+        
+        let mut result = DetectionResult::new(vec![face], 0);
+        result.round_for_output(DEFAULT_CONFIDENCE_PRECISION, CoordinateFormat::Integer);
+        let json = serde_json::to_string(&result.faces[0]).unwrap();
+        assert!(json.contains(r#""x":-3,"y":0,"width":31,"height":40"#), "{}", json);
+    }
+
+    #[test]
+    fn test_truncated_serialized_only_when_set() {
+        let mut face = Face::new(0, 20, 30, 40, 0.5);
+        assert!(serde_json::to_value(&face).unwrap().get("truncated").is_none());
+        
+        face.truncated = true;
+        let value = serde_json::to_value(&face).unwrap();
+        assert_eq!(value["truncated"], true);
+        assert!(serde_json::from_value::<Face>(value).unwrap().truncated);
+    }
+
+    #[test]
+    fn test_bounding_box_geometry() {
+        let bbox = BoundingBox::new(10, 20, 30, 40);
+        
+        assert_eq!(bbox.area(), 1200);
+        assert_eq!(bbox.center(), (25.0, 40.0));
+        assert_eq!((bbox.right(), bbox.bottom()), (40.0, 60.0));
+        assert_eq!(BoundingBox::new(0, 0, 1 << 24, 1 << 24).area(), 1 << 48);
+        
+        assert!(bbox.contains_point(10, 20));
+        assert!(bbox.contains_point(39, 59));
+        assert!(!bbox.contains_point(40, 30));
+        assert!(!bbox.contains_point(20, 60));
+        assert!(!bbox.contains_point(9, 30));
+        
+        assert_eq!(bbox.clamp_to(100, 100), bbox);
+        assert_eq!(bbox.clamp_to(25, 50), BoundingBox::new(10, 20, 15, 30));
+        assert_eq!(bbox.clamp_to(5, 5), BoundingBox::new(5, 5, 0, 0));
+        
+        assert_eq!(bbox.scale(0.5), BoundingBox::new(5, 10, 15, 20));
+        assert_eq!(bbox.scale(2.0), BoundingBox::new(20, 40, 60, 80));
+        assert_eq!(bbox.offset(-15, 5), BoundingBox::new(0, 25, 30, 40));
+        
+        let other = BoundingBox::new(25, 20, 30, 40);
+        assert!((bbox.iou(&other) - 600.0 / 1800.0).abs() < 1e-6);
+        assert_eq!(bbox.iou(&BoundingBox::new(100, 100, 5, 5)), 0.0);
+        
+        let tuple: (u32, u32, u32, u32) = bbox.into();
+        assert_eq!(BoundingBox::from(tuple), bbox);
+    }
+
+    #[test]
+    fn test_face_iou() {
+        let a = Face::new(0, 0, 10, 10, 0.9);
+        let b = Face::new(5, 0, 10, 10, 0.9);
+        let c = Face::new(20, 20, 10, 10, 0.9);
+        
+        assert!((a.iou(&a) - 1.0).abs() < f32::EPSILON);
+        assert!((a.iou(&b) - 50.0 / 150.0).abs() < 1e-6);
+        assert_eq!(a.iou(&c), 0.0);
+    }
+
+    #[test]
+    fn test_merge_collapses_faces_on_tile_boundary() {
+        // Two 300x200 tiles overlapping by 100px horizontally; the face at
+        // x=220..280 of the full image lies inside the overlap.
+        let left = DetectionResult::new(
+            vec![Face::new(20, 40, 50, 50, 0.8), Face::new(220, 40, 60, 60, 0.9)],
+            12,
+        )
+        .with_image_dimensions(300, 200);
+        let right = DetectionResult::new(
+            vec![Face::new(20, 41, 60, 60, 0.85), Face::new(150, 100, 40, 40, 0.7)],
+            8,
+        )
+        .with_image_dimensions(300, 200);
+        
+        let merged = DetectionResult::merge(&[(left, 0, 0), (right, 200, 0)]);
+        
+        assert_eq!(merged.total_faces, 3);
+        assert_eq!(merged.faces.len(), 3);
+        assert_eq!(merged.processing_time_ms, 20);
+        assert_eq!((merged.image_width, merged.image_height), (500, 200));
+        
+        let boundary: Vec<&Face> = merged.faces.iter().filter(|f| (200.0..250.0).contains(&f.bbox.x)).collect();
+        assert_eq!(boundary.len(), 1);
+        assert_eq!(boundary[0].confidence, 0.9);
+        assert!(merged.faces.iter().any(|f| (f.bbox.x, f.bbox.y) == (350.0, 100.0)));
+    }
 }
\ No newline at end of file
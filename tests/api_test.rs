@@ -22,76 +22,1972 @@ async fn test_health_endpoint() {
     assert_eq!(body["data"]["status"], "healthy");
 }
 
+#[actix_web::test]
+async fn test_v2_accept_header_flattens_envelope() {
+    let app = test::init_service(
+        App::new().service(face_detect_rust::api::health_check)
+    ).await;
+    
+    let req = test::TestRequest::get().uri("/api/health").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("content-type").unwrap(), "application/json");
+    let v1: serde_json::Value = test::read_body_json(resp).await;
+    let mut keys: Vec<_> = v1.as_object().unwrap().keys().cloned().collect();
+    keys.sort();
+    assert_eq!(keys, ["data", "metadata", "success"]);
+    assert_eq!(v1["data"]["status"], "healthy");
+    
+    let req = test::TestRequest::get()
+        .uri("/api/health")
+        .insert_header(("Accept", "application/vnd.facedetect.v2+json"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("content-type").unwrap(), "application/vnd.facedetect.v2+json");
+    let v2: serde_json::Value = test::read_body_json(resp).await;
+    assert!(v2.get("data").is_none());
+    assert_eq!(v2["success"], true);
+    assert_eq!(v2["status"], "healthy");
+    assert_eq!(v2["version"], v1["data"]["version"]);
+    assert!(v2["metadata"]["timestamp"].is_string());
+}
+
 #[actix_web::test]
 async fn test_upload_endpoint_invalid_file() {
-    // This test would require more complex setup with multipart forms
-    // For now, we'll test the basic structure
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+
+    // Test with empty multipart form
+    let boundary = "----WebKitFormBoundary7MA4YWxkTrZu0gW";
+    let body = format!(
+        "------WebKitFormBoundary7MA4YWxkTrZu0gW\r\n\
+         Content-Disposition: form-data; name=\"image\"; filename=\"\"\r\n\
+         Content-Type: image/jpeg\r\n\r\n\
+         \r\n\
+         ------WebKitFormBoundary7MA4YWxkTrZu0gW--\r\n"
+    );
+
+    let req = test::TestRequest::post()
+        .uri("/api/upload")
+        .insert_header(("Content-Type", format!("multipart/form-data; boundary={}", boundary)))
+        .set_payload(body.into_bytes())
+        .to_request();
+    
+    let resp = test::call_service(&app, req).await;
+    // Should return error for empty file
+    assert_eq!(resp.status(), 400);
+    
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "Validation failed");
+    assert_eq!(body["details"], "Request validation failed: Uploaded image file is empty");
+}
+
+/// Encodes a blank JPEG of the given size.
+fn jpeg_bytes(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    image::DynamicImage::new_rgb8(width, height)
+        .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Jpeg(85))
+        .unwrap();
+    bytes
+}
+
+/// Builds a multipart body with a single `image` field.
+fn multipart_image(bytes: &[u8]) -> (String, Vec<u8>) {
+    let boundary = "----FaceDetectTestBoundary";
+    let mut body = format!(
+        "--{boundary}\r\n\
+         Content-Disposition: form-data; name=\"image\"; filename=\"test.jpg\"\r\n\
+         Content-Type: image/jpeg\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(bytes);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+    
+    (format!("multipart/form-data; boundary={boundary}"), body)
+}
+
+/// Registers `detector` together with the blocking backend running it, as
+/// the server does.
+fn register_detector(
+    detector: face_detect_rust::detection::FaceDetector,
+) -> impl FnOnce(&mut web::ServiceConfig) {
+    use face_detect_rust::backend::{AsyncDetector, BlockingDetector};
+    
+    move |cfg| {
+        let backend: std::sync::Arc<dyn AsyncDetector> = std::sync::Arc::new(BlockingDetector::new(detector.clone()));
+        cfg.app_data(web::Data::new(detector)).app_data(web::Data::from(backend));
+    }
+}
+
+#[actix_web::test]
+async fn test_upload_require_faces() {
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+
+    let (content_type, body) = multipart_image(&jpeg_bytes(50, 50));
+    let req = test::TestRequest::post()
+        .uri("/api/upload?require_faces=true")
+        .insert_header(("Content-Type", content_type.clone()))
+        .set_payload(body.clone())
+        .to_request();
+    
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 422);
+    
+    let body_json: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body_json["success"], false);
+    assert_eq!(body_json["error"], "No faces detected");
+
+    let req = test::TestRequest::post()
+        .uri("/api/upload")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    
+    let body_json: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body_json["data"]["detectionResult"]["totalFaces"], 0);
+}
+
+#[actix_web::test]
+async fn test_upload_stores_nothing_without_retention() {
+    use face_detect_rust::storage::{LocalStorage, Storage};
+    use std::sync::Arc;
+    
+    let root = tempfile::tempdir().unwrap();
+    let storage: Arc<dyn Storage> = Arc::new(LocalStorage::new(root.path()));
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .app_data(web::Data::from(storage.clone()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+    
+    let (content_type, body) = multipart_image(&jpeg_bytes(200, 200));
+    let req = test::TestRequest::post()
+        .uri("/api/upload")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    
+    // Uploads are buffered in memory; nothing is written anywhere
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(body["data"].get("retainedPath").is_none());
+    assert!(storage.list("").await.unwrap().is_empty());
+    assert_eq!(std::fs::read_dir(root.path()).unwrap().count(), 0);
+}
+
+/// Async backend that always reports the same faces, one of them below the
+/// default confidence threshold.
+struct FixedFaceDetector;
+
+#[async_trait::async_trait]
+impl face_detect_rust::backend::AsyncDetector for FixedFaceDetector {
+    async fn detect(
+        &self,
+        _image: std::sync::Arc<image::DynamicImage>,
+    ) -> face_detect_rust::error::Result<Vec<face_detect_rust::types::Face>> {
+        tokio::task::yield_now().await;
+        Ok(vec![
+            face_detect_rust::types::Face::new(5, 6, 20, 20, 0.77),
+            face_detect_rust::types::Face::new(25, 25, 10, 10, 0.1),
+        ])
+    }
+
+    fn model_version(&self) -> &str {
+        "fixed-1"
+    }
+}
+
+#[actix_web::test]
+async fn test_upload_uses_registered_async_detector() {
+    use face_detect_rust::backend::AsyncDetector;
+    
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let backend: std::sync::Arc<dyn AsyncDetector> = std::sync::Arc::new(FixedFaceDetector);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .app_data(web::Data::from(backend))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+
+    // Too small for the mock detector, so any face comes from the async backend
+    let (content_type, body) = multipart_image(&jpeg_bytes(50, 50));
+    let req = test::TestRequest::post()
+        .uri("/api/upload")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    
+    // The detector's threshold still applies to the backend's faces
+    let result = &body["data"]["detectionResult"];
+    assert_eq!(result["totalFaces"], 1);
+    assert_eq!(result["faces"][0]["x"], 5);
+    assert_eq!(result["faces"][0]["confidence"], 0.77);
+    assert_eq!(result["imageWidth"], 50);
+    assert_eq!(result["modelVersion"], "fixed-1");
+}
+
+#[actix_web::test]
+async fn test_upload_summary_headers_match_body() {
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+    
+    let (content_type, body) = multipart_image(&jpeg_bytes(500, 400));
+    let req = test::TestRequest::post()
+        .uri("/api/upload")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    
+    let header = |name: &str| -> u64 {
+        resp.headers().get(name).unwrap_or_else(|| panic!("{} missing", name))
+            .to_str().unwrap()
+            .parse().unwrap()
+    };
+    let (faces, width, height, time) = (
+        header("x-face-count"),
+        header("x-image-width"),
+        header("x-image-height"),
+        header("x-processing-time-ms"),
+    );
+    
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    let result = &body["data"]["detectionResult"];
+    assert_eq!(faces, result["totalFaces"].as_u64().unwrap());
+    assert!(faces > 0);
+    assert_eq!((width, height), (500, 400));
+    assert_eq!(width, result["imageWidth"].as_u64().unwrap());
+    assert_eq!(height, result["imageHeight"].as_u64().unwrap());
+    assert_eq!(time, result["processingTimeMs"].as_u64().unwrap());
+}
+
+#[actix_web::test]
+async fn test_upload_preserve_original_returns_uploaded_bytes() {
+    use base64::Engine;
+    
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+    
+    let mut png = Vec::new();
+    image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(500, 500, |x, y| image::Rgb([x as u8, y as u8, 128])))
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+        .unwrap();
+    
+    let (content_type, body) = multipart_image(&png);
+    let req = test::TestRequest::post()
+        .uri("/api/upload?preserve_original=true")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    
+    let data = &body["data"];
+    let original = data["originalImage"].as_str().unwrap();
+    let encoded = original.strip_prefix("data:image/png;base64,").expect("PNG data URI");
+    assert_eq!(base64::engine::general_purpose::STANDARD.decode(encoded).unwrap(), png);
+    
+    // Faces were found, so the processed image is still drawn and re-encoded
+    assert!(data["detectionResult"]["totalFaces"].as_u64().unwrap() > 0);
+    assert!(data["processedImage"].as_str().unwrap().starts_with("data:image/jpeg;base64,"));
+}
+
+#[actix_web::test]
+async fn test_upload_voc_format_returns_xml() {
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+    
+    // The mock finds two faces in a 500x500 image
+    let (content_type, body) = multipart_image(&jpeg_bytes(500, 500));
+    let req = test::TestRequest::post()
+        .uri("/api/upload?format=voc")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert_eq!(resp.headers().get("content-type").unwrap(), "application/xml");
+    
+    let xml = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(xml.contains("<filename>test.jpg</filename>"), "{}", xml);
+    assert!(xml.contains("<width>500</width>"));
+    assert_eq!(xml.matches("<object>").count(), 2);
+}
+
+#[actix_web::test]
+async fn test_upload_debug_lists_rejected_faces() {
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let detector = face_detect_rust::detection::FaceDetector::new()
+        .unwrap()
+        .with_mock_confidences(vec![0.4, 0.99])
+        .with_confidence_threshold(0.5);
+    for enabled in [true, false] {
+        let config = face_detect_rust::config::AppConfig {
+            enable_debug_endpoints: enabled,
+            ..face_detect_rust::config::AppConfig::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .configure(register_detector(detector.clone()))
+                .app_data(web::Data::new(config))
+                .service(face_detect_rust::api::upload_image)
+        ).await;
+        
+        let (content_type, body) = multipart_image(&jpeg_bytes(500, 500));
+        let req = test::TestRequest::post()
+            .uri("/api/upload?debug=true")
+            .insert_header(("Content-Type", content_type))
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        
+        if !enabled {
+            assert_eq!(resp.status(), 400);
+            continue;
+        }
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        let result = &body["data"]["detectionResult"];
+        assert_eq!(result["totalFaces"], 1);
+        assert_eq!(result["rejected"][0]["reason"], "below_threshold");
+        assert_eq!(result["rejected"][0]["x"], 125);
+    }
+}
+
+#[actix_web::test]
+async fn test_upload_paginates_faces_by_confidence() {
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let detector = face_detect_rust::detection::FaceDetector::new()
+        .unwrap()
+        .with_mock_confidences(vec![0.7, 0.95, 0.8]);
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(detector))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+    
+    let (content_type, body) = multipart_image(&jpeg_bytes(700, 700));
+    let req = test::TestRequest::post()
+        .uri("/api/upload?offset=1&limit=1")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    
+    let result = &body["data"]["detectionResult"];
+    assert_eq!(result["totalFaces"], 3);
+    assert_eq!(result["offset"], 1);
+    assert_eq!(result["limit"], 1);
+    assert_eq!(result["returned"], 1);
+    assert_eq!(result["faces"].as_array().unwrap().len(), 1);
+    assert_eq!(result["faces"][0]["confidence"], 0.8);
+}
+
+#[actix_web::test]
+async fn test_retained_upload_persists_after_request() {
+    use face_detect_rust::storage::{LocalStorage, Storage};
+    use std::sync::Arc;
+    
+    let root = tempfile::tempdir().unwrap();
+    let storage: Arc<dyn Storage> = Arc::new(LocalStorage::new(root.path()));
+    let config = face_detect_rust::config::AppConfig {
+        retain_uploads: true,
+        ..face_detect_rust::config::AppConfig::default()
+    };
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(face_detect_rust::detection::FaceDetector::new().unwrap()))
+            .app_data(web::Data::new(config))
+            .app_data(web::Data::from(storage.clone()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+    
+    let upload = jpeg_bytes(320, 240);
+    let (content_type, body) = multipart_image(&upload);
+    let req = test::TestRequest::post()
+        .uri("/api/upload")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    
+    let upload_id = body["data"]["uploadId"].as_str().unwrap();
+    assert!(!upload_id.is_empty());
+    let retained_key = body["data"]["retainedPath"].as_str().unwrap();
+    let date = chrono::Utc::now().format("%Y-%m-%d");
+    assert_eq!(retained_key, format!("retained/{}/{}.jpg", date, upload_id));
+    assert_eq!(storage.get(retained_key).await.unwrap(), upload);
+}
+
+#[actix_web::test]
+async fn test_debug_last_returns_latest_annotated_png() {
+    use face_detect_rust::last_image::LastAnnotatedImage;
+    
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let detector = face_detect_rust::detection::FaceDetector::new().unwrap();
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(detector))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .app_data(web::Data::new(LastAnnotatedImage::new()))
+            .service(face_detect_rust::api::upload_image)
+            .service(face_detect_rust::api::debug_last_image)
+    ).await;
+    
+    let req = test::TestRequest::get().uri("/api/debug/last").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 404);
+    
+    let (content_type, body) = multipart_image(&jpeg_bytes(640, 480));
+    let req = test::TestRequest::post()
+        .uri("/api/upload")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    
+    let req = test::TestRequest::get().uri("/api/debug/last").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "image/png");
+    
+    let body = test::read_body(resp).await;
+    let png = image::load_from_memory_with_format(&body, image::ImageFormat::Png).unwrap();
+    assert_eq!((png.width(), png.height()), (640, 480));
+}
+
+#[actix_web::test]
+async fn test_saturated_detection_slots_shed_with_503() {
+    use face_detect_rust::limiter::DetectionLimiter;
+    
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let limiter = web::Data::new(DetectionLimiter::new(1, std::time::Duration::from_millis(50)));
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .app_data(limiter.clone())
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+
+    let (content_type, body) = multipart_image(&jpeg_bytes(50, 50));
+    let upload = || test::TestRequest::post()
+        .uri("/api/upload")
+        .insert_header(("Content-Type", content_type.clone()))
+        .set_payload(body.clone())
+        .to_request();
+
+    // Occupy the only slot, as a long-running detection would
+    let busy = limiter.acquire().await.unwrap();
+    let started = std::time::Instant::now();
+    let resp = test::call_service(&app, upload()).await;
+    assert_eq!(resp.status(), 503);
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    assert_eq!(resp.headers().get("retry-after").unwrap(), "1");
+    
+    let body_json: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body_json["error"], "Server overloaded");
+
+    drop(busy);
+    let resp = test::call_service(&app, upload()).await;
+    assert!(resp.status().is_success());
+    assert_eq!(limiter.available(), 1);
+}
+
+#[actix_web::test]
+async fn test_image_work_endpoints_share_detection_slots() {
+    use face_detect_rust::embedding::{ColorHistogramEmbedder, Embedder};
+    use face_detect_rust::limiter::DetectionLimiter;
+    use std::sync::Arc;
+    
+    let limiter = web::Data::new(DetectionLimiter::new(1, std::time::Duration::from_millis(50)));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(face_detect_rust::detection::FaceDetector::new().unwrap()))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .app_data(web::Data::from(Arc::new(ColorHistogramEmbedder::default()) as Arc<dyn Embedder>))
+            .app_data(limiter.clone())
+            .service(face_detect_rust::api::annotate_image)
+            .service(face_detect_rust::api::overlay_png)
+            .service(face_detect_rust::api::crop_faces)
+            .service(face_detect_rust::api::validate_image)
+            .service(face_detect_rust::api::embed_faces)
+    ).await;
+
+    let image_data = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, jpeg_bytes(100, 100));
+    let faces = serde_json::json!([{ "x": 10, "y": 10, "width": 40, "height": 40, "confidence": 0.9 }]);
+    let requests = [
+        ("/api/annotate", serde_json::json!({ "imageData": image_data, "faces": faces })),
+        ("/api/overlay.png", serde_json::json!({ "width": 100, "height": 100, "faces": faces })),
+        ("/api/crop", serde_json::json!({ "imageData": image_data, "faces": faces })),
+        ("/api/validate", serde_json::json!({ "imageData": image_data })),
+        ("/api/embed", serde_json::json!({ "imageData": image_data, "faces": faces })),
+    ];
+
+    // With the only slot taken, every endpoint sheds load; once it is free they all succeed
+    let busy = limiter.acquire().await.unwrap();
+    for (uri, body) in &requests {
+        let req = test::TestRequest::post().uri(uri).set_json(body).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 503, "{uri}");
+    }
+    drop(busy);
+    for (uri, body) in &requests {
+        let req = test::TestRequest::post().uri(uri).set_json(body).to_request();
+        assert!(test::call_service(&app, req).await.status().is_success(), "{uri}");
+    }
+    assert_eq!(limiter.available(), 1);
+}
+
+#[actix_web::test]
+async fn test_upload_without_faces_returns_original_as_processed() {
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+
+    let (content_type, body) = multipart_image(&jpeg_bytes(50, 50));
+    let req = test::TestRequest::post()
+        .uri("/api/upload")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    
+    let body_json: serde_json::Value = test::read_body_json(resp).await;
+    let data = &body_json["data"];
+    assert_eq!(data["detectionResult"]["totalFaces"], 0);
+    assert!(data["originalImage"].as_str().is_some_and(|image| !image.is_empty()));
+    assert_eq!(data["processedImage"], data["originalImage"]);
+}
+
+#[actix_web::test]
+async fn test_oversized_faceless_upload_passes_through_uncapped() {
+    let config = face_detect_rust::config::AppConfig {
+        max_response_image_dimension: 64,
+        ..face_detect_rust::config::AppConfig::default()
+    };
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap().with_min_dimension(10_000)
+            ))
+            .app_data(web::Data::new(config))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+    
+    let upload = jpeg_bytes(400, 300);
+    let (content_type, body) = multipart_image(&upload);
+    let req = test::TestRequest::post()
+        .uri("/api/upload?preserve_original=true")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    
+    // Still byte-identical to the upload, not a re-encoded 64px copy
+    let data = &body["data"];
+    assert_eq!(data["detectionResult"]["totalFaces"], 0);
+    assert_eq!(data["processedImage"], data["originalImage"]);
+    let (_, encoded) = data["processedImage"].as_str().unwrap().split_once(',').unwrap();
+    let bytes = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, encoded).unwrap();
+    assert_eq!(bytes, upload);
+}
+
+#[actix_web::test]
+async fn test_upload_snake_case_option() {
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+
+    let (content_type, body) = multipart_image(&jpeg_bytes(300, 300));
+    
+    let req = test::TestRequest::post()
+        .uri("/api/upload")
+        .insert_header(("Content-Type", content_type.clone()))
+        .set_payload(body.clone())
+        .to_request();
+    let camel: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(camel["data"]["detectionResult"]["totalFaces"], 1);
+    assert!(camel["data"]["detectionResult"].get("total_faces").is_none());
+
+    let req = test::TestRequest::post()
+        .uri("/api/upload?case=snake")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let snake: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(snake["data"]["detection_result"]["total_faces"], 1);
+    assert!(snake["data"]["detection_result"].get("totalFaces").is_none());
+    assert!(snake["data"]["processed_image"].is_string());
+}
+
+#[actix_web::test]
+async fn test_upload_square_padding() {
+    use base64::Engine;
+    use image::GenericImageView;
+    
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+
+    let (content_type, body) = multipart_image(&jpeg_bytes(200, 100));
+    
+    let req = test::TestRequest::post()
+        .uri("/api/upload?square=true&pad_color=ffffff")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    
+    assert_eq!(resp["data"]["padOffset"]["top"], 50);
+    assert_eq!(resp["data"]["padOffset"]["left"], 0);
+    
+    let processed = resp["data"]["processedImage"].as_str().unwrap();
+    let encoded = processed.split_once(',').unwrap().1;
+    let bytes = base64::engine::general_purpose::STANDARD.decode(encoded).unwrap();
+    let image = image::load_from_memory(&bytes).unwrap();
+    assert_eq!(image.dimensions(), (200, 200));
+}
+
+#[actix_web::test]
+async fn test_upload_rejects_extreme_aspect_ratio() {
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+
+    let mut png = Vec::new();
+    image::DynamicImage::new_rgb8(50000, 2)
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+        .unwrap();
+    let (content_type, body) = multipart_image(&png);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/upload")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    let details = body["details"].as_str().unwrap();
+    assert!(details.contains("width 50000"), "{}", details);
+}
+
+#[actix_web::test]
+async fn test_upload_detects_on_requested_tiff_page() {
+    use tiff::encoder::{colortype, TiffEncoder};
+    
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+
+    let mut tiff = Vec::new();
+    let mut encoder = TiffEncoder::new(std::io::Cursor::new(&mut tiff)).unwrap();
+    for (width, height) in [(100u32, 80u32), (320, 240), (500, 450)] {
+        let data = vec![128u8; (width * height * 3) as usize];
+        encoder.write_image::<colortype::RGB8>(width, height, &data).unwrap();
+    }
+    let (content_type, body) = multipart_image(&tiff);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/upload?frame=2")
+        .insert_header(("Content-Type", content_type.clone()))
+        .set_payload(body.clone())
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["data"]["detectionResult"]["imageWidth"], 500);
+    assert_eq!(resp["data"]["detectionResult"]["imageHeight"], 450);
+    assert_eq!(resp["data"]["detectionResult"]["totalFaces"], 2);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/upload?frame=3")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_web::test]
+async fn test_upload_quality_param() {
+    use base64::Engine;
+    
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+
+    // A busy pattern so quality visibly affects size
+    let textured = image::RgbImage::from_fn(300, 300, |x, y| {
+        image::Rgb([((x * 7) ^ (y * 13)) as u8, (x * y) as u8, (x + y * 3) as u8])
+    });
+    let mut png = Vec::new();
+    image::DynamicImage::ImageRgb8(textured)
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+        .unwrap();
+    let (content_type, body) = multipart_image(&png);
+    
+    let mut sizes = Vec::new();
+    for quality in [30, 95] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/upload?quality={}", quality))
+            .insert_header(("Content-Type", content_type.clone()))
+            .set_payload(body.clone())
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        
+        let processed = resp["data"]["processedImage"].as_str().unwrap();
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(processed.split_once(',').unwrap().1)
+            .unwrap();
+        sizes.push(bytes.len());
+    }
+    assert!(sizes[0] < sizes[1], "quality 30 ({}) should be smaller than 95 ({})", sizes[0], sizes[1]);
+    
+    for quality in [0, 101] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/upload?quality={}", quality))
+            .insert_header(("Content-Type", content_type.clone()))
+            .set_payload(body.clone())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+    }
+}
+
+#[actix_web::test]
+async fn test_upload_phase_timings() {
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+
+    let (content_type, body) = multipart_image(&jpeg_bytes(400, 300));
+    
+    let req = test::TestRequest::post()
+        .uri("/api/upload")
+        .insert_header(("Content-Type", content_type.clone()))
+        .set_payload(body.clone())
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(resp["data"]["detectionResult"].get("timings").is_none());
+    
+    let req = test::TestRequest::post()
+        .uri("/api/upload?timings=true")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let timings = &resp["data"]["detectionResult"]["timings"];
+    
+    let phases: Vec<f64> = ["decodeMs", "detectMs", "drawMs", "encodeMs"]
+        .iter()
+        .map(|phase| timings[phase].as_f64().unwrap())
+        .collect();
+    assert!(phases.iter().all(|ms| *ms >= 0.0), "{:?}", phases);
+    
+    let sum: f64 = phases.iter().sum();
+    let total = timings["totalMs"].as_f64().unwrap();
+    assert!(sum <= total, "phases {} exceed total {}", sum, total);
+    assert!(total - sum < 5.0 + total * 0.1, "phases {} far from total {}", sum, total);
+}
+
+#[actix_web::test]
+async fn test_upload_rejects_oversized_content_length() {
+    let config = face_detect_rust::config::AppConfig {
+        max_file_size: 1024,
+        ..Default::default()
+    };
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(config))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+
+    let (content_type, _) = multipart_image(&[]);
+    let req = test::TestRequest::post()
+        .uri("/api/upload")
+        .insert_header(("Content-Type", content_type))
+        .insert_header(("Content-Length", "1048576"))
+        .to_request();
+    
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 413);
+    
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["success"], false);
+    assert_eq!(body["error"], "File too large");
+}
+
+#[actix_web::test]
+async fn test_chunked_upload_is_capped_on_streamed_bytes() {
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let image = jpeg_bytes(200, 200);
+    let config = face_detect_rust::config::AppConfig {
+        max_file_size: image.len() + 16,
+        ..Default::default()
+    };
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(config))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+    
+    // Streamed in small chunks with no Content-Length, as chunked clients send it
+    let chunked_upload = |bytes: &[u8]| {
+        let (content_type, body) = multipart_image(bytes);
+        let mut req = test::TestRequest::post()
+            .uri("/api/upload")
+            .insert_header(("Content-Type", content_type))
+            .insert_header(("Transfer-Encoding", "chunked"))
+            .to_request();
+        req.headers_mut().remove("content-length");
+        let chunks: Vec<Result<web::Bytes, actix_web::error::PayloadError>> = body
+            .chunks(512)
+            .map(|chunk| Ok(web::Bytes::copy_from_slice(chunk)))
+            .collect();
+        let stream: std::pin::Pin<Box<dyn futures_util::Stream<Item = _>>> = Box::pin(futures_util::stream::iter(chunks));
+        req.replace_payload(actix_web::dev::Payload::from(stream)).0
+    };
+    
+    let resp = test::call_service(&app, chunked_upload(&image)).await;
+    assert!(resp.status().is_success(), "{}", resp.status());
+    
+    let mut oversized = image.clone();
+    oversized.extend(std::iter::repeat_n(0, 1024));
+    let resp = test::call_service(&app, chunked_upload(&oversized)).await;
+    assert_eq!(resp.status(), 413);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "File too large");
+}
+
+#[actix_web::test]
+async fn test_crop_endpoint_basic() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .service(face_detect_rust::api::crop_faces)
+    ).await;
+
+    // Test with valid JSON structure
+    let crop_request = serde_json::json!({
+        "image_data": "data:image/jpeg;base64,/9j/4AAQSkZJRgABAQAAAQABAAD/2wBDAAYEBQYFBAYGBQYHBwYIChAKCgkJChQODwwQFxQYGBcUFhYaHSUfGhsjHBYWICwgIyYnKSopGR8tMC0oMCUoKSj/2wBDAQcHBwoIChMKChMoGhYaKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCj/wAARCAABAAEDASIAAhEBAxEB/8QAFQABAQAAAAAAAAAAAAAAAAAAAAv/xAAUEAEAAAAAAAAAAAAAAAAAAAAA/8QAFQEBAQAAAAAAAAAAAAAAAAAAAAX/xAAUEQEAAAAAAAAAAAAAAAAAAAAA/9oADAMBAAIRAxEAPwCwAA8A/9k=",
+        "faces": [
+            {
+                "x": 50,
+                "y": 50,
+                "width": 100,
+                "height": 100,
+                "confidence": 0.9
+            }
+        ]
+    });
+
+    let req = test::TestRequest::post()
+        .uri("/api/crop")
+        .insert_header(("Content-Type", "application/json"))
+        .set_json(&crop_request)
+        .to_request();
+    
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["success"], true);
+    assert!(body["data"]["cropped_faces"].is_array());
+}
+
+#[actix_web::test]
+async fn test_wrong_content_type_is_rejected_with_415() {
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .app_data(face_detect_rust::api::json_config(1024 * 1024))
+            .service(face_detect_rust::api::crop_faces)
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+    
+    // Multipart posted to the JSON crop endpoint
+    let (content_type, body) = multipart_image(&jpeg_bytes(50, 50));
+    let req = test::TestRequest::post()
+        .uri("/api/crop")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 415);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["success"], false);
+    assert_eq!(body["error"], "Unsupported media type");
+    assert!(body["details"].as_str().unwrap().contains("expected application/json"));
+    
+    // JSON posted to the multipart upload endpoint
+    let req = test::TestRequest::post()
+        .uri("/api/upload")
+        .set_json(serde_json::json!({ "imageData": "data:image/jpeg;base64,AAAA" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 415);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["success"], false);
+    assert_eq!(body["error"], "Unsupported media type");
+    assert_eq!(
+        body["details"],
+        "Unsupported content type application/json; expected multipart/form-data"
+    );
+}
+
+#[actix_web::test]
+async fn test_crop_endpoint_preserves_face_indices() {
+    use base64::Engine;
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .service(face_detect_rust::api::crop_faces)
+    ).await;
+
+    let image_data = format!(
+        "data:image/jpeg;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(200, 200))
+    );
+    let crop_request = serde_json::json!({
+        "imageData": image_data,
+        "faces": [
+            { "x": 10, "y": 10, "width": 50, "height": 50, "confidence": 0.9 },
+            { "x": 500, "y": 500, "width": 50, "height": 50, "confidence": 0.8 },
+            { "x": 100, "y": 100, "width": 40, "height": 40, "confidence": 0.7 }
+        ]
+    });
+
+    let req = test::TestRequest::post()
+        .uri("/api/crop")
+        .set_json(&crop_request)
+        .to_request();
+    
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    let crops = body["data"]["croppedFaces"].as_array().unwrap();
+    assert_eq!(crops.len(), 3);
+    
+    for (i, crop) in crops.iter().enumerate() {
+        assert_eq!(crop["index"], i);
+        assert_eq!(crop["face"], crop_request["faces"][i]);
+    }
+    assert!(crops[0]["image"].is_string() && crops[0]["error"].is_null());
+    assert!(crops[1]["image"].is_null() && crops[1]["error"].is_string());
+    assert!(crops[2]["image"].is_string() && crops[2]["error"].is_null());
+}
+
+#[actix_web::test]
+async fn test_crop_face_count_limit() {
+    use base64::Engine;
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::crop_faces)
+    ).await;
+
+    let image_data = base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(100, 100));
+    let crop_request = |count: usize| serde_json::json!({
+        "imageData": image_data,
+        "faces": vec![serde_json::json!({ "x": 10, "y": 10, "width": 20, "height": 20, "confidence": 0.9 }); count]
+    });
+
+    let req = test::TestRequest::post()
+        .uri("/api/crop")
+        .set_json(crop_request(101))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["details"], "Request validation failed: Request has 101 faces; at most 100 are allowed");
+
+    let req = test::TestRequest::post()
+        .uri("/api/crop")
+        .set_json(crop_request(100))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["data"]["croppedFaces"].as_array().unwrap().len(), 100);
+}
+
+#[actix_web::test]
+async fn test_crop_response_has_standard_envelope() {
+    use base64::Engine;
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .service(face_detect_rust::api::crop_faces)
+            .service(face_detect_rust::api::health_check)
+    ).await;
+
+    let crop_request = serde_json::json!({
+        "imageData": base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(64, 64)),
+        "faces": [{ "x": 8, "y": 8, "width": 16, "height": 16, "confidence": 0.9 }]
+    });
+    let req = test::TestRequest::post()
+        .uri("/api/crop")
+        .set_json(&crop_request)
+        .to_request();
+    let crop: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    
+    let req = test::TestRequest::get().uri("/api/health").to_request();
+    let health: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    
+    // Same envelope as every other endpoint, metadata block included
+    assert_eq!(crop["success"], true);
+    assert!(crop["data"]["croppedFaces"][0]["image"].is_string());
+    assert!(crop["metadata"]["timestamp"].is_string());
+    assert_eq!(crop["metadata"]["version"], health["metadata"]["version"]);
+    let keys = |body: &serde_json::Value| {
+        let mut keys: Vec<_> = body.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    };
+    assert_eq!(keys(&crop), keys(&health));
+    
+    // Rejected crops carry the same metadata block
+    let mut too_many = crop_request.clone();
+    too_many["faces"] = serde_json::json!(vec![crop_request["faces"][0].clone(); 101]);
+    let req = test::TestRequest::post()
+        .uri("/api/crop")
+        .set_json(&too_many)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let rejected: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(rejected["success"], false);
+    assert!(rejected["metadata"]["timestamp"].is_string());
+    assert_eq!(rejected["metadata"]["version"], health["metadata"]["version"]);
+}
+
+#[actix_web::test]
+async fn test_crop_zip_contains_crops_and_manifest() {
+    use base64::Engine;
+    use std::io::Read;
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .service(face_detect_rust::api::crop_faces)
+    ).await;
+
+    let crop_request = serde_json::json!({
+        "imageData": base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(64, 64)),
+        "faces": [
+            { "x": 0, "y": 0, "width": 16, "height": 16, "confidence": 0.9 },
+            { "x": 500, "y": 500, "width": 16, "height": 16, "confidence": 0.8 },
+            { "x": 32, "y": 32, "width": 20, "height": 10, "confidence": 0.7 }
+        ]
+    });
+    let req = test::TestRequest::post()
+        .uri("/api/crop?format=zip")
+        .set_json(&crop_request)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert_eq!(resp.headers().get("content-type").unwrap(), "application/zip");
+    let disposition = resp.headers().get("content-disposition").unwrap().to_str().unwrap();
+    assert!(disposition.starts_with("attachment"));
+    
+    let body = test::read_body(resp).await;
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(body.to_vec())).unwrap();
+    let mut names: Vec<_> = archive.file_names().map(str::to_string).collect();
+    names.sort();
+    
+    // The face outside the image is left out of the archive
+    assert_eq!(names, ["face_0.jpg", "face_2.jpg", "manifest.json"]);
+    
+    let mut crop = Vec::new();
+    archive.by_name("face_2.jpg").unwrap().read_to_end(&mut crop).unwrap();
+    assert_eq!(image::load_from_memory(&crop).unwrap().to_rgb8().dimensions(), (20, 10));
+    
+    let manifest: serde_json::Value = serde_json::from_reader(archive.by_name("manifest.json").unwrap()).unwrap();
+    let manifest = manifest.as_array().unwrap();
+    assert_eq!(manifest.len(), 3);
+    assert_eq!(manifest[0]["file"], "face_0.jpg");
+    assert!(manifest[1]["file"].is_null());
+    assert!(manifest[1]["error"].is_string());
+    assert_eq!(manifest[2]["file"], "face_2.jpg");
+}
+
+#[actix_web::test]
+async fn test_crop_output_size_returns_fixed_squares() {
+    use base64::Engine;
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .service(face_detect_rust::api::crop_faces)
+    ).await;
+    
+    let crop_request = serde_json::json!({
+        "imageData": base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(400, 300)),
+        "faces": [
+            { "x": 10, "y": 10, "width": 100, "height": 100, "confidence": 0.9 },
+            { "x": 150, "y": 20, "width": 200, "height": 80, "confidence": 0.8 },
+            { "x": 50, "y": 150, "width": 30, "height": 120, "confidence": 0.7 }
+        ],
+        "outputSize": 160
+    });
+    // The body setting wins over the query parameter
+    let req = test::TestRequest::post().uri("/api/crop?size=64").set_json(&crop_request).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    
+    let crops = body["data"]["croppedFaces"].as_array().unwrap();
+    assert_eq!(crops.len(), 3);
+    for crop in crops {
+        let encoded = crop["image"].as_str().unwrap().trim_start_matches("data:image/jpeg;base64,");
+        let bytes = base64::engine::general_purpose::STANDARD.decode(encoded).unwrap();
+        let image = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (160, 160));
+    }
+}
+
+#[actix_web::test]
+async fn test_crops_are_exempt_from_response_image_cap() {
+    use base64::Engine;
+    use image::GenericImageView;
+    
+    let config = face_detect_rust::config::AppConfig {
+        max_response_image_dimension: 64,
+        ..Default::default()
+    };
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(config))
+            .app_data(web::Data::new(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .service(face_detect_rust::api::annotate_image)
+            .service(face_detect_rust::api::crop_faces)
+    ).await;
+    let image_data = base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(400, 300));
+    let faces = serde_json::json!([{ "x": 100, "y": 50, "width": 200, "height": 160, "confidence": 0.9 }]);
+    let decode = |data: &str| {
+        let bytes = base64::engine::general_purpose::STANDARD.decode(data.split_once(',').unwrap().1).unwrap();
+        image::load_from_memory(&bytes).unwrap()
+    };
+    
+    // The annotated image is capped
+    let req = test::TestRequest::post()
+        .uri("/api/annotate")
+        .set_json(serde_json::json!({ "imageData": image_data, "faces": faces }))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(decode(body["data"]["annotatedImage"].as_str().unwrap()).dimensions(), (64, 48));
+    
+    // The crop keeps its native box size
+    let req = test::TestRequest::post()
+        .uri("/api/crop")
+        .set_json(serde_json::json!({ "imageData": image_data, "faces": faces }))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let crop = decode(body["data"]["croppedFaces"][0]["image"].as_str().unwrap());
+    assert_eq!(crop.dimensions(), (200, 160));
+}
+
+#[actix_web::test]
+async fn test_crop_persist_writes_through_storage() {
+    use base64::Engine;
+    use face_detect_rust::storage::{LocalStorage, Storage};
+    use std::sync::Arc;
+    
+    let root = tempfile::tempdir().unwrap();
+    let storage: Arc<dyn Storage> = Arc::new(LocalStorage::new(root.path()));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::from(storage.clone()))
+            .service(face_detect_rust::api::crop_faces)
+    ).await;
+    
+    let crop_request = serde_json::json!({
+        "imageData": base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(400, 300)),
+        "faces": [
+            { "x": 10, "y": 10, "width": 100, "height": 80, "confidence": 0.9 },
+            { "x": 150, "y": 20, "width": 60, "height": 60, "confidence": 0.8 }
+        ]
+    });
+    let req = test::TestRequest::post().uri("/api/crop?persist=true").set_json(&crop_request).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    
+    let crops = body["data"]["croppedFaces"].as_array().unwrap();
+    assert_eq!(crops.len(), 2);
+    for (index, crop) in crops.iter().enumerate() {
+        let key = crop["storageKey"].as_str().unwrap();
+        assert!(key.starts_with("crops/") && key.ends_with(&format!("face_{}.jpg", index)));
+        let stored = image::load_from_memory(&storage.get(key).await.unwrap()).unwrap();
+        assert_eq!(stored.width(), crop["face"]["width"].as_u64().unwrap() as u32);
+    }
+    
+    // Without the flag nothing is stored
+    let req = test::TestRequest::post().uri("/api/crop").set_json(&crop_request).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(body["data"]["croppedFaces"][0].get("storageKey").is_none());
+    assert_eq!(std::fs::read_dir(root.path().join("crops")).unwrap().count(), 1);
+}
+
+#[actix_web::test]
+async fn test_primary_face_crops_largest_mock_face() {
+    use base64::Engine;
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .service(face_detect_rust::api::primary_face)
+    ).await;
+    
+    // The mock finds a 125px face at (125, 125) and a smaller 100px face
+    let request = serde_json::json!({
+        "imageData": base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(500, 500))
+    });
+    let req = test::TestRequest::post().uri("/api/primary-face").set_json(&request).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    
+    let data = &body["data"];
+    assert_eq!((data["face"]["x"].as_u64(), data["face"]["width"].as_u64()), (Some(125), Some(125)));
+    assert_eq!(data["crop"], serde_json::json!({ "x": 100, "y": 100, "width": 175, "height": 175 }));
+    let encoded = data["image"].as_str().unwrap().trim_start_matches("data:image/jpeg;base64,");
+    let image = image::load_from_memory(&base64::engine::general_purpose::STANDARD.decode(encoded).unwrap()).unwrap();
+    assert_eq!((image.width(), image.height()), (175, 175));
+    
+    // Too small for the mock to find anything
+    let request = serde_json::json!({
+        "imageData": base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(40, 40))
+    });
+    let req = test::TestRequest::post().uri("/api/primary-face").set_json(&request).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 422);
+}
+
+#[actix_web::test]
+async fn test_detect_and_crop_returns_selected_faces_only() {
+    use base64::Engine;
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .service(face_detect_rust::api::detect_and_crop)
+    ).await;
+    
+    // The mock finds two faces in a 500x500 image
+    let image_data = base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(500, 500));
+    let request = serde_json::json!({ "imageData": image_data, "indices": [1] });
+    let req = test::TestRequest::post().uri("/api/detect-and-crop").set_json(&request).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    
+    let data = &body["data"];
+    assert_eq!(data["detectionResult"]["totalFaces"], 2);
+    let crops = data["crops"].as_array().unwrap();
+    assert_eq!(crops.len(), 1);
+    assert_eq!(crops[0]["index"], 1);
+    assert_eq!(crops[0]["face"], data["detectionResult"]["faces"][1]);
+    assert!(crops[0]["image"].as_str().unwrap().starts_with("data:image/jpeg;base64,"));
+    
+    let request = serde_json::json!({ "imageData": image_data, "indices": [2] });
+    let req = test::TestRequest::post().uri("/api/detect-and-crop").set_json(&request).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+    
+    // Repeated indices are cropped once, and too many are rejected outright
+    let request = serde_json::json!({ "imageData": image_data, "indices": [1, 0, 1, 1] });
+    let req = test::TestRequest::post().uri("/api/detect-and-crop").set_json(&request).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let indices: Vec<_> = body["data"]["crops"].as_array().unwrap().iter().map(|crop| crop["index"].clone()).collect();
+    assert_eq!(indices, [1, 0]);
+    
+    let request = serde_json::json!({ "imageData": image_data, "indices": vec![0; 101] });
+    let req = test::TestRequest::post().uri("/api/detect-and-crop").set_json(&request).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
+
+#[actix_web::test]
+async fn test_smart_crop_encloses_all_faces() {
+    use base64::Engine;
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .service(face_detect_rust::api::smart_crop)
+    ).await;
+    
+    // The mock finds faces at (125, 125) 125x125 and (333, 166) 100x100
+    let request = serde_json::json!({
+        "imageData": base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(500, 500)),
+        "padding": 10
+    });
+    let req = test::TestRequest::post().uri("/api/smart-crop").set_json(&request).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    
+    let data = &body["data"];
+    assert_eq!(data["faces"].as_array().unwrap().len(), 2);
+    assert_eq!(data["crop"], serde_json::json!({ "x": 115, "y": 115, "width": 328, "height": 161 }));
+    let encoded = data["image"].as_str().unwrap().trim_start_matches("data:image/jpeg;base64,");
+    let image = image::load_from_memory(&base64::engine::general_purpose::STANDARD.decode(encoded).unwrap()).unwrap();
+    assert_eq!((image.width(), image.height()), (328, 161));
+    
+    let request = serde_json::json!({
+        "imageData": base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(40, 40))
+    });
+    let req = test::TestRequest::post().uri("/api/smart-crop").set_json(&request).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 422);
+}
+
+#[actix_web::test]
+async fn test_crop_dedupe_overlapping_skips_duplicates() {
+    use base64::Engine;
     
     let app = test::init_service(
         App::new()
             .app_data(web::Data::new(
                 face_detect_rust::detection::FaceDetector::new().unwrap()
             ))
-            .service(face_detect_rust::api::upload_image)
+            .service(face_detect_rust::api::crop_faces)
     ).await;
+    
+    // The two boxes overlap with an IoU of about 0.9
+    let mut crop_request = serde_json::json!({
+        "imageData": base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(64, 64)),
+        "faces": [
+            { "x": 0, "y": 0, "width": 40, "height": 40, "confidence": 0.9 },
+            { "x": 2, "y": 0, "width": 40, "height": 40, "confidence": 0.8 }
+        ]
+    });
+    let crops = |body: &serde_json::Value| {
+        body["data"]["croppedFaces"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|crop| crop["image"].is_string())
+            .count()
+    };
+    
+    let req = test::TestRequest::post().uri("/api/crop").set_json(&crop_request).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(crops(&body), 2);
+    assert!(body["data"].get("skipped").is_none());
+    
+    crop_request["dedupeOverlapping"] = serde_json::json!(true);
+    let req = test::TestRequest::post().uri("/api/crop").set_json(&crop_request).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(crops(&body), 1);
+    assert_eq!(body["data"]["skipped"], serde_json::json!([1]));
+    assert_eq!(body["data"]["croppedFaces"][1]["duplicateOf"], 0);
+}
 
-    // Test with empty multipart form
-    let boundary = "----WebKitFormBoundary7MA4YWxkTrZu0gW";
-    let body = format!(
-        "------WebKitFormBoundary7MA4YWxkTrZu0gW\r\n\
-         Content-Disposition: form-data; name=\"image\"; filename=\"\"\r\n\
-         Content-Type: image/jpeg\r\n\r\n\
-         \r\n\
-         ------WebKitFormBoundary7MA4YWxkTrZu0gW--\r\n"
-    );
+#[actix_web::test]
+async fn test_crop_resize_honors_filter_and_size() {
+    use base64::Engine;
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .service(face_detect_rust::api::crop_faces)
+    ).await;
+
+    let board = image::RgbImage::from_fn(32, 32, |x, y| {
+        if (x + y) % 2 == 0 { image::Rgb([255, 255, 255]) } else { image::Rgb([0, 0, 0]) }
+    });
+    let mut png = Vec::new();
+    image::DynamicImage::ImageRgb8(board)
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+        .unwrap();
+    let crop_request = serde_json::json!({
+        "imageData": base64::engine::general_purpose::STANDARD.encode(&png),
+        "faces": [{ "x": 4, "y": 4, "width": 16, "height": 8, "confidence": 0.9 }]
+    });
+
+    let mut crops = Vec::new();
+    for filter in ["nearest", "lanczos"] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/crop?size=64&filter={filter}&pad_color=00ff00"))
+            .set_json(&crop_request)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        let data_uri = body["data"]["croppedFaces"][0]["image"].as_str().unwrap();
+        let (_, encoded) = data_uri.split_once(',').unwrap();
+        let bytes = base64::engine::general_purpose::STANDARD.decode(encoded).unwrap();
+        let crop = image::load_from_memory(&bytes).unwrap().to_rgb8();
+        assert_eq!(crop.dimensions(), (64, 64));
+        crops.push(crop);
+    }
+    assert_ne!(crops[0], crops[1]);
+
+    let req = test::TestRequest::post()
+        .uri("/api/crop?size=0")
+        .set_json(&crop_request)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_web::test]
+async fn test_crop_after_upload_reuses_decoded_image() {
+    use base64::Engine;
+    use face_detect_rust::cache::DecodeCache;
+    
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let cache = web::Data::new(DecodeCache::new(4, 64 * 1024 * 1024));
+    let stats = web::Data::new(face_detect_rust::stats::RuntimeStats::new());
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .app_data(cache.clone())
+            .app_data(stats.clone())
+            .service(face_detect_rust::api::upload_image)
+            .service(face_detect_rust::api::crop_faces)
+    ).await;
 
+    let image = jpeg_bytes(300, 300);
+    let (content_type, body) = multipart_image(&image);
     let req = test::TestRequest::post()
         .uri("/api/upload")
-        .insert_header(("Content-Type", format!("multipart/form-data; boundary={}", boundary)))
-        .set_payload(body.into_bytes())
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert_eq!(cache.decode_count(), 1);
+
+    let request = serde_json::json!({
+        "imageData": base64::engine::general_purpose::STANDARD.encode(&image),
+        "faces": [{ "x": 10, "y": 10, "width": 50, "height": 50, "confidence": 0.9 }]
+    });
+    let req = test::TestRequest::post()
+        .uri("/api/crop")
+        .set_json(&request)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(body["data"]["croppedFaces"][0]["image"].is_string());
+    assert_eq!(cache.decode_count(), 1);
+    assert_eq!(stats.snapshot().cache_hit_rate, Some(0.5));
+}
+
+#[actix_web::test]
+async fn test_upload_reports_hash_of_raw_bytes() {
+    use face_detect_rust::detection::image_content_hash;
+    
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+
+    let mut hashes = Vec::new();
+    for image in [jpeg_bytes(60, 40), jpeg_bytes(60, 40), jpeg_bytes(40, 60)] {
+        let (content_type, body) = multipart_image(&image);
+        let req = test::TestRequest::post()
+            .uri("/api/upload")
+            .insert_header(("Content-Type", content_type))
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        
+        let body_json: serde_json::Value = test::read_body_json(resp).await;
+        let hash = body_json["data"]["imageHash"].as_str().unwrap().to_string();
+        assert_eq!(hash, image_content_hash(&image));
+        hashes.push(hash);
+    }
+
+    assert_eq!(hashes[0], hashes[1]);
+    assert_ne!(hashes[0], hashes[2]);
+}
+
+#[actix_web::test]
+async fn test_repeated_upload_reports_duplicate() {
+    use face_detect_rust::dedup::RecentUploads;
+    
+    std::fs::create_dir_all("uploads").unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .app_data(web::Data::new(RecentUploads::new(8, 6)))
+            .service(face_detect_rust::api::upload_image)
+    ).await;
+
+    let (content_type, body) = multipart_image(&jpeg_bytes(120, 80));
+    let mut responses = Vec::new();
+    for _ in 0..2 {
+        let req = test::TestRequest::post()
+            .uri("/api/upload")
+            .insert_header(("Content-Type", content_type.clone()))
+            .set_payload(body.clone())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        let body_json: serde_json::Value = test::read_body_json(resp).await;
+        responses.push(body_json["data"].clone());
+    }
+
+    let (first, second) = (&responses[0], &responses[1]);
+    assert!(first.get("duplicateOf").is_none());
+    assert_eq!(first["phash"].as_str().unwrap().len(), 16);
+    assert_eq!(second["phash"], first["phash"]);
+    assert_ne!(second["uploadId"], first["uploadId"]);
+    assert_eq!(second["duplicateOf"], first["uploadId"]);
+}
+
+#[actix_web::test]
+async fn test_validate_endpoint_reports_without_detecting() {
+    use base64::Engine;
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::validate_image)
+    ).await;
+
+    let request = serde_json::json!({
+        "imageData": base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(64, 48))
+    });
+    let req = test::TestRequest::post()
+        .uri("/api/validate")
+        .set_json(&request)
         .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
     
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["data"]["valid"], true);
+    assert_eq!(body["data"]["format"], "jpeg");
+    assert_eq!(body["data"]["width"], 64);
+    assert_eq!(body["data"]["height"], 48);
+    assert_eq!(body["data"]["reasons"], serde_json::json!([]));
+
+    let (content_type, body) = multipart_image(b"just some text, not an image");
+    let req = test::TestRequest::post()
+        .uri("/api/validate")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["data"]["valid"], false);
+    assert_eq!(body["data"]["sizeBytes"], 28);
+    assert!(body["data"]["format"].is_null());
+    assert!(!body["data"]["reasons"].as_array().unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn test_overlay_endpoint_returns_transparent_png() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::overlay_png)
+    ).await;
+
+    let request = serde_json::json!({
+        "width": 120,
+        "height": 80,
+        "faces": [{ "x": 20, "y": 20, "width": 40, "height": 40, "confidence": 0.9 }],
+        "drawOptions": { "color": "#ff0000", "thickness": 1, "showLabels": false }
+    });
+    let req = test::TestRequest::post()
+        .uri("/api/overlay.png")
+        .set_json(&request)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert_eq!(resp.headers().get("content-type").unwrap(), "image/png");
+    
+    let bytes = test::read_body(resp).await;
+    let overlay = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png)
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(overlay.dimensions(), (120, 80));
+    assert_eq!(overlay.get_pixel(20, 40).0, [255, 0, 0, 255]);
+    assert_eq!(overlay.get_pixel(40, 40).0[3], 0);
+    assert_eq!(overlay.get_pixel(100, 10).0[3], 0);
+
+    let req = test::TestRequest::post()
+        .uri("/api/overlay.png")
+        .set_json(serde_json::json!({ "width": 0, "height": 80, "faces": [] }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+
+    let req = test::TestRequest::post()
+        .uri("/api/overlay.png")
+        .set_json(serde_json::json!({
+            "width": 120,
+            "height": 80,
+            "faces": [{ "x": 20, "y": 20, "width": 40, "height": 40, "confidence": 0.9 }],
+            "drawOptions": { "thickness": 4_000_000_000u32 }
+        }))
+        .to_request();
     let resp = test::call_service(&app, req).await;
-    // Should return error for empty file
     assert_eq!(resp.status(), 400);
 }
 
 #[actix_web::test]
-async fn test_crop_endpoint_basic() {
+async fn test_annotate_endpoint_draws_supplied_faces() {
+    use base64::Engine;
+    use image::GenericImageView;
+    
     let app = test::init_service(
         App::new()
             .app_data(web::Data::new(
                 face_detect_rust::detection::FaceDetector::new().unwrap()
             ))
-            .service(face_detect_rust::api::crop_faces)
+            .app_data(web::Data::new(face_detect_rust::config::AppConfig::default()))
+            .service(face_detect_rust::api::annotate_image)
     ).await;
 
-    // Test with valid JSON structure
-    let crop_request = serde_json::json!({
-        "image_data": "data:image/jpeg;base64,/9j/4AAQSkZJRgABAQAAAQABAAD/2wBDAAYEBQYFBAYGBQYHBwYIChAKCgkJChQODwwQFxQYGBcUFhYaHSUfGhsjHBYWICwgIyYnKSopGR8tMC0oMCUoKSj/2wBDAQcHBwoIChMKChMoGhYaKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCj/wAARCAABAAEDASIAAhEBAxEB/8QAFQABAQAAAAAAAAAAAAAAAAAAAAv/xAAUEAEAAAAAAAAAAAAAAAAAAAAA/8QAFQEBAQAAAAAAAAAAAAAAAAAAAAX/xAAUEQEAAAAAAAAAAAAAAAAAAAAA/9oADAMBAAIRAxEAPwCwAA8A/9k=",
+    let input = image::load_from_memory(&jpeg_bytes(200, 200)).unwrap();
+    let request = serde_json::json!({
+        "imageData": format!(
+            "data:image/jpeg;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(200, 200))
+        ),
         "faces": [
-            {
-                "x": 50,
-                "y": 50,
-                "width": 100,
-                "height": 100,
-                "confidence": 0.9
-            }
+            { "x": 20, "y": 20, "width": 60, "height": 60, "confidence": 0.9 },
+            { "x": 110, "y": 110, "width": 60, "height": 60, "confidence": 0.8 }
+        ],
+        "drawOptions": { "color": "#ff0000", "thickness": 4, "showLabels": false }
+    });
+
+    let req = test::TestRequest::post()
+        .uri("/api/annotate")
+        .set_json(&request)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    let annotated = body["data"]["annotatedImage"].as_str().unwrap();
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(annotated.split_once(',').unwrap().1)
+        .unwrap();
+    let output = image::load_from_memory(&bytes).unwrap();
+    assert_eq!(output.dimensions(), input.dimensions());
+    
+    let redness = |img: &image::DynamicImage, x, y| {
+        let [r, g, b, _] = img.get_pixel(x, y).0;
+        i32::from(r) - (i32::from(g) + i32::from(b)) / 2
+    };
+    // Box edges turn red; the area between the boxes is left alone
+    for (x, y) in [(21, 50), (50, 21), (111, 140), (140, 111)] {
+        assert!(redness(&output, x, y) - redness(&input, x, y) > 100, "no box at ({}, {})", x, y);
+    }
+    assert!((redness(&output, 100, 10) - redness(&input, 100, 10)).abs() < 30);
+}
+
+#[actix_web::test]
+async fn test_annotate_and_overlay_validate_supplied_faces() {
+    let config = face_detect_rust::config::AppConfig {
+        max_crop_faces: 2,
+        ..face_detect_rust::config::AppConfig::default()
+    };
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::new(config))
+            .service(face_detect_rust::api::annotate_image)
+            .service(face_detect_rust::api::overlay_png)
+    ).await;
+
+    let face = serde_json::json!({ "x": 10, "y": 10, "width": 20, "height": 20, "confidence": 0.9 });
+    let negative = serde_json::json!({ "x": -10, "y": 10, "width": 20, "height": 20, "confidence": 0.9 });
+    let huge = serde_json::json!({ "x": 4_000_000_000u32, "y": 10, "width": 20, "height": 20, "confidence": 0.9 });
+    let image_data = format!(
+        "data:image/jpeg;base64,{}",
+        base64::Engine::encode(&base64::engine::general_purpose::STANDARD, jpeg_bytes(100, 100))
+    );
+    
+    for faces in [vec![face.clone(); 3], vec![negative.clone()], vec![huge.clone()]] {
+        let req = test::TestRequest::post()
+            .uri("/api/annotate")
+            .set_json(serde_json::json!({ "imageData": image_data, "faces": faces }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400, "annotate {faces:?}");
+        
+        let req = test::TestRequest::post()
+            .uri("/api/overlay.png")
+            .set_json(serde_json::json!({ "width": 100, "height": 100, "faces": faces }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400, "overlay {faces:?}");
+    }
+}
+
+#[actix_web::test]
+async fn test_embed_endpoint_returns_vector_per_face() {
+    use base64::Engine;
+    use face_detect_rust::embedding::{ColorHistogramEmbedder, Embedder};
+    use std::sync::Arc;
+    
+    let embedder = ColorHistogramEmbedder::default();
+    let dimension = embedder.dimension();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::from(Arc::new(embedder) as Arc<dyn Embedder>))
+            .service(face_detect_rust::api::embed_faces)
+    ).await;
+
+    let embed_request = serde_json::json!({
+        "imageData": base64::engine::general_purpose::STANDARD.encode(jpeg_bytes(200, 200)),
+        "faces": [
+            { "x": 10, "y": 10, "width": 50, "height": 50, "confidence": 0.9 },
+            { "x": 100, "y": 100, "width": 80, "height": 60, "confidence": 0.8 }
         ]
     });
 
     let req = test::TestRequest::post()
-        .uri("/api/crop")
-        .insert_header(("Content-Type", "application/json"))
-        .set_json(&crop_request)
+        .uri("/api/embed")
+        .set_json(&embed_request)
         .to_request();
     
     let resp = test::call_service(&app, req).await;
     assert!(resp.status().is_success());
     
     let body: serde_json::Value = test::read_body_json(resp).await;
-    assert_eq!(body["success"], true);
-    assert!(body["data"]["cropped_faces"].is_array());
+    let embeddings = body["data"]["embeddings"].as_array().unwrap();
+    assert_eq!(embeddings.len(), 2);
+    for embedding in embeddings {
+        assert_eq!(embedding.as_array().unwrap().len(), dimension);
+    }
+}
+
+#[actix_web::test]
+async fn test_group_clusters_identical_faces() {
+    use base64::Engine;
+    use face_detect_rust::embedding::{ColorHistogramEmbedder, Embedder};
+    use face_detect_rust::matcher::{CosineMatcher, FaceMatcher};
+    use std::sync::Arc;
+    
+    let app = test::init_service(
+        App::new()
+            .configure(register_detector(
+                face_detect_rust::detection::FaceDetector::new().unwrap()
+            ))
+            .app_data(web::Data::from(Arc::new(ColorHistogramEmbedder::default()) as Arc<dyn Embedder>))
+            .app_data(web::Data::from(Arc::new(CosineMatcher) as Arc<dyn FaceMatcher>))
+            .service(face_detect_rust::api::group_faces)
+    ).await;
+    
+    let png = |color: [u8; 3]| {
+        let mut bytes = Vec::new();
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(80, 80, image::Rgb(color)))
+            .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+            .unwrap();
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    };
+    let face = serde_json::json!([{ "x": 10, "y": 10, "width": 60, "height": 60, "confidence": 0.9 }]);
+    let request = serde_json::json!({
+        "images": [
+            { "imageData": png([200, 40, 40]), "faces": face },
+            { "imageData": png([40, 40, 200]), "faces": face },
+            { "imageData": png([200, 40, 40]), "faces": face }
+        ]
+    });
+    
+    let req = test::TestRequest::post().uri("/api/group").set_json(&request).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(
+        body["data"]["groups"],
+        serde_json::json!([
+            [{ "image": 0, "face": 0 }, { "image": 2, "face": 0 }],
+            [{ "image": 1, "face": 0 }]
+        ])
+    );
+    assert_eq!(body["data"]["faces"].as_array().unwrap().len(), 3);
+    
+    // Supplied faces are validated like /api/crop
+    let negative = serde_json::json!([{ "x": -10, "y": 10, "width": 60, "height": 60, "confidence": 0.9 }]);
+    let request = serde_json::json!({ "images": [{ "imageData": png([200, 40, 40]), "faces": negative }] });
+    let req = test::TestRequest::post().uri("/api/group").set_json(&request).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+    
+    // Oversized images are rejected from their header
+    let mut bytes = Vec::new();
+    image::DynamicImage::ImageLuma8(image::GrayImage::new(50_000, 1))
+        .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+        .unwrap();
+    let request = serde_json::json!({
+        "images": [{ "imageData": base64::engine::general_purpose::STANDARD.encode(bytes), "faces": face }]
+    });
+    let req = test::TestRequest::post().uri("/api/group").set_json(&request).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(body["details"].as_str().unwrap().contains("50000"), "{body}");
 }
 
 #[actix_web::test]
@@ -147,6 +2043,77 @@ async fn test_cors_headers() {
     assert!(headers.contains_key("access-control-allow-headers"));
 }
 
+#[actix_web::test]
+async fn test_upload_preflight_returns_no_content() {
+    let config = face_detect_rust::config::AppConfig::default();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(config.clone()))
+            .wrap(face_detect_rust::api::cors_middleware(&config))
+            .service(face_detect_rust::api::upload_image)
+            .default_service(web::to(face_detect_rust::api::not_found))
+    ).await;
+
+    let req = test::TestRequest::default()
+        .method(actix_web::http::Method::OPTIONS)
+        .uri("/api/upload")
+        .insert_header(("Origin", "http://localhost:3000"))
+        .insert_header(("Access-Control-Request-Method", "POST"))
+        .insert_header(("Access-Control-Request-Headers", "content-type"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    
+    assert_eq!(resp.status(), 204);
+    let headers = resp.headers();
+    let methods = headers.get("access-control-allow-methods").unwrap().to_str().unwrap();
+    assert!(methods.split(", ").any(|method| method == "POST"), "{}", methods);
+    assert_eq!(headers.get("access-control-allow-origin").unwrap(), "http://localhost:3000");
+    assert_eq!(headers.get("access-control-allow-headers").unwrap(), "content-type");
+    
+    // Unknown routes still get a 404
+    let req = test::TestRequest::default()
+        .method(actix_web::http::Method::OPTIONS)
+        .uri("/api/missing")
+        .insert_header(("Origin", "http://localhost:3000"))
+        .insert_header(("Access-Control-Request-Method", "POST"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[actix_web::test]
+async fn test_cors_exposes_custom_headers() {
+    let config = face_detect_rust::config::AppConfig {
+        cors_max_age_secs: 600,
+        ..face_detect_rust::config::AppConfig::default()
+    };
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(config.clone()))
+            .wrap(face_detect_rust::api::cors_middleware(&config))
+            .service(face_detect_rust::api::health_check)
+            .default_service(web::to(face_detect_rust::api::not_found))
+    ).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/health")
+        .insert_header(("Origin", "http://localhost:3000"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    
+    let exposed = resp.headers().get("access-control-expose-headers").unwrap().to_str().unwrap();
+    assert!(exposed.split(',').any(|header| header.trim().eq_ignore_ascii_case("x-face-count")), "{}", exposed);
+    
+    let req = test::TestRequest::default()
+        .method(actix_web::http::Method::OPTIONS)
+        .uri("/api/health")
+        .insert_header(("Origin", "http://localhost:3000"))
+        .insert_header(("Access-Control-Request-Method", "GET"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("access-control-max-age").unwrap(), "600");
+}
+
 #[actix_web::test]
 async fn test_json_content_type() {
     let app = test::init_service(